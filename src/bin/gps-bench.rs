@@ -0,0 +1,684 @@
+//! Benchmark the planners on suites of problems.
+//!
+//! Every instance is solved by every planner configuration in a process of
+//! its own, which is stopped when it runs out of time or memory without
+//! affecting the other runs. Memory is read from `/proc`, so the memory limit
+//! only applies on Linux.
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, ExitCode, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use paip_rust::gps::domains;
+use paip_rust::gps::generator::Generator;
+use paip_rust::gps::plan;
+use paip_rust::gps::portfolio::{CancellationToken, Portfolio};
+use paip_rust::gps::problem::Problem;
+use paip_rust::gps::search::{Limits, Outcome};
+use paip_rust::gps::GeneralProblemSolver;
+
+const USAGE: &str = "\
+usage: gps-bench [options]
+
+Solves every instance of the chosen suites with every chosen planner and
+prints a table of the results. The suites are the classic domains school,
+monkey, maze and blocks, and random, which holds generated problems.
+
+options:
+  --suite <name>              run this suite, may be repeated (default all)
+  --planner <gps|portfolio>   run this planner, may be repeated (default all)
+  --random <n>                generate this many random problems (default 10)
+  --timeout <seconds>         time limit of each run (default 10)
+  --memory <mib>              memory limit of each run (default 1024)
+  --max-expansions <n>        give up after trying this many operations
+  --max-depth <n>             do not nest subgoals deeper than this
+  --output <markdown|csv>     print the table in this format (default markdown)
+  -h, --help                  print this help";
+
+const SUITES: [&str; 5] = ["school", "monkey", "maze", "blocks", "random"];
+
+/// How long a run may take beyond its time limit to stop by itself.
+const GRACE: Duration = Duration::from_secs(1);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Configuration {
+    Gps,
+    Portfolio,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Markdown,
+    Csv,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Options {
+    suites: Vec<String>,
+    configurations: Vec<Configuration>,
+    random: u64,
+    timeout: Duration,
+    /// In KiB, as `/proc` reports it.
+    memory: u64,
+    limits: Limits,
+    output: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Solved,
+    Unsolvable,
+    LimitExceeded,
+    Timeout,
+    OutOfMemory,
+    Error,
+}
+
+/// What one run measured. Unknown values are `None`.
+#[derive(Debug, Clone, PartialEq)]
+struct Measurement {
+    status: Status,
+    length: Option<usize>,
+    cost: Option<u64>,
+    expansions: Option<usize>,
+    time: Duration,
+    /// The peak resident memory in KiB.
+    memory: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Run {
+    instance: String,
+    configuration: Configuration,
+    measurement: Measurement,
+}
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    if arguments
+        .iter()
+        .any(|argument| argument == "-h" || argument == "--help")
+    {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    // The benchmark runs itself with `--worker` to solve each instance.
+    if let [flag, instance, configuration, rest @ ..] = arguments.as_slice() {
+        if flag == "--worker" {
+            return worker(instance, configuration, rest);
+        }
+    }
+
+    let options = match parse_arguments(&arguments) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("gps-bench: {message}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let executable = match env::current_exe() {
+        Ok(executable) => executable,
+        Err(error) => {
+            eprintln!("gps-bench: cannot find the benchmark executable: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut runs = Vec::new();
+    for (instance, _) in instances(&options) {
+        for configuration in &options.configurations {
+            let measurement = measure(&executable, &instance, *configuration, &options);
+            eprintln!(
+                "{instance} {}: {}",
+                configuration.name(),
+                measurement.status.name()
+            );
+            runs.push(Run {
+                instance: instance.clone(),
+                configuration: *configuration,
+                measurement,
+            });
+        }
+    }
+
+    match options.output {
+        OutputFormat::Markdown => print!("{}", write_markdown(&runs, &options.configurations)),
+        OutputFormat::Csv => print!("{}", write_csv(&runs)),
+    }
+    ExitCode::SUCCESS
+}
+
+fn parse_arguments(arguments: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        suites: Vec::new(),
+        configurations: Vec::new(),
+        random: 10,
+        timeout: Duration::from_secs(10),
+        memory: 1024 * 1024,
+        limits: Limits::new(),
+        output: OutputFormat::Markdown,
+    };
+
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        let value = arguments
+            .next()
+            .ok_or_else(|| format!("`{argument}` needs a value"))?;
+        let invalid = || format!("invalid value `{value}` for `{argument}`");
+
+        match argument.as_str() {
+            "--suite" => {
+                if !SUITES.contains(&value.as_str()) {
+                    return Err(invalid());
+                }
+                options.suites.push(value.clone());
+            }
+            "--planner" => options
+                .configurations
+                .push(Configuration::try_from(value.as_str()).map_err(|_| invalid())?),
+            "--random" => options.random = value.parse().map_err(|_| invalid())?,
+            "--timeout" => {
+                let seconds: f64 = value.parse().map_err(|_| invalid())?;
+                options.timeout = Duration::try_from_secs_f64(seconds).map_err(|_| invalid())?;
+            }
+            "--memory" => {
+                let mebibytes: u64 = value.parse().map_err(|_| invalid())?;
+                options.memory = mebibytes * 1024;
+            }
+            "--max-expansions" => {
+                options.limits = options
+                    .limits
+                    .with_max_expansions(value.parse().map_err(|_| invalid())?)
+            }
+            "--max-depth" => {
+                options.limits = options
+                    .limits
+                    .with_max_depth(value.parse().map_err(|_| invalid())?)
+            }
+            "--output" => {
+                options.output = match value.as_str() {
+                    "markdown" => OutputFormat::Markdown,
+                    "csv" => OutputFormat::Csv,
+                    _ => return Err(invalid()),
+                }
+            }
+            _ => return Err(format!("unknown option `{argument}`")),
+        }
+    }
+
+    if options.suites.is_empty() {
+        options.suites = SUITES.iter().map(|suite| (*suite).to_owned()).collect();
+    }
+    if options.configurations.is_empty() {
+        options.configurations = vec![Configuration::Gps, Configuration::Portfolio];
+    }
+    Ok(options)
+}
+
+/// The instances of the chosen suites, named `suite/instance`.
+fn instances(options: &Options) -> Vec<(String, Problem)> {
+    let mut instances: Vec<_> = domains::instances()
+        .into_iter()
+        .map(|instance| (instance.name().to_owned(), instance.into_problem()))
+        .collect();
+    instances.extend((0..options.random).map(|seed| (random_name(seed), random_problem(seed))));
+    instances.retain(|(name, _)| {
+        options
+            .suites
+            .iter()
+            .any(|suite| name.split('/').next() == Some(suite.as_str()))
+    });
+    instances
+}
+
+fn random_name(seed: u64) -> String {
+    format!("random/{seed}")
+}
+
+fn random_problem(seed: u64) -> Problem {
+    Generator::new(seed).generate().into_inner().0
+}
+
+/// Find an instance by name without building the others.
+fn find_instance(name: &str) -> Option<Problem> {
+    if let Some(seed) = name.strip_prefix("random/") {
+        return seed.parse().ok().map(random_problem);
+    }
+    domains::instances()
+        .into_iter()
+        .find(|instance| instance.name() == name)
+        .map(|instance| instance.into_problem())
+}
+
+/// Solve an instance in a new process and watch its time and memory.
+fn measure(
+    executable: &Path,
+    instance: &str,
+    configuration: Configuration,
+    options: &Options,
+) -> Measurement {
+    let start = Instant::now();
+    let failure = |status, memory| Measurement {
+        status,
+        length: None,
+        cost: None,
+        expansions: None,
+        time: start.elapsed(),
+        memory,
+    };
+
+    let mut command = Command::new(executable);
+    command
+        .args(["--worker", instance, configuration.name()])
+        .args(["--timeout", &options.timeout.as_secs_f64().to_string()]);
+    if let Some(max_expansions) = options.limits.max_expansions() {
+        command.args(["--max-expansions", &max_expansions.to_string()]);
+    }
+    if let Some(max_depth) = options.limits.max_depth() {
+        command.args(["--max-depth", &max_depth.to_string()]);
+    }
+    let Ok(mut child) = command.stdout(Stdio::piped()).stderr(Stdio::null()).spawn() else {
+        return failure(Status::Error, None);
+    };
+
+    let mut peak = None;
+    let stopped = loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break None,
+            Ok(None) => {}
+            Err(_) => break Some(Status::Error),
+        }
+        if let Some(memory) = resident_memory(&format!("/proc/{}/status", child.id()), "VmRSS:") {
+            peak = peak.max(Some(memory));
+            if memory > options.memory {
+                break Some(Status::OutOfMemory);
+            }
+        }
+        if start.elapsed() > options.timeout + GRACE {
+            break Some(Status::Timeout);
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    if let Some(status) = stopped {
+        let _ = child.kill();
+        let _ = child.wait();
+        return failure(status, peak);
+    }
+
+    let mut output = String::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        let _ = stdout.read_to_string(&mut output);
+    }
+    match parse_measurement(output.trim()) {
+        Some(mut measurement) => {
+            // Polling may miss the peak, which the worker reports itself.
+            measurement.memory = measurement.memory.max(peak);
+            measurement
+        }
+        None => failure(Status::Error, peak),
+    }
+}
+
+/// A size in KiB from a line of a `/proc` status file, such as `VmRSS:`.
+fn resident_memory(path: &str, field: &str) -> Option<u64> {
+    let status = fs::read_to_string(path).ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(field))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Solve one instance and print the measurement on one line.
+fn worker(instance: &str, configuration: &str, arguments: &[String]) -> ExitCode {
+    let (Ok(configuration), Ok(options)) = (
+        Configuration::try_from(configuration),
+        parse_arguments(arguments),
+    ) else {
+        eprintln!("gps-bench: invalid worker arguments");
+        return ExitCode::FAILURE;
+    };
+    let Some(problem) = find_instance(instance) else {
+        eprintln!("gps-bench: unknown instance `{instance}`");
+        return ExitCode::FAILURE;
+    };
+
+    let mut solver: GeneralProblemSolver = problem.into();
+    solver.set_limits(options.limits);
+    let start = Instant::now();
+    let deadline = start + options.timeout;
+
+    let (outcome, expansions) = match configuration {
+        Configuration::Gps => {
+            let (outcome, statistics) = solver.search(&CancellationToken::with_deadline(deadline));
+            (outcome, Some(statistics.expansions()))
+        }
+        // The portfolio cannot be interrupted and is stopped from outside
+        // when it runs out of time.
+        Configuration::Portfolio => {
+            let solution = Portfolio::with_goal_orderings(&solver).solve_first();
+            let expansions = solution.statistics().expansions();
+            (solution.into_outcome(), Some(expansions))
+        }
+    };
+    let time = start.elapsed();
+
+    let status = match &outcome {
+        Outcome::Solved(_) => Status::Solved,
+        Outcome::Unsolvable => Status::Unsolvable,
+        Outcome::LimitExceeded if Instant::now() >= deadline => Status::Timeout,
+        Outcome::LimitExceeded => Status::LimitExceeded,
+    };
+    let operations = outcome.into_operations();
+    let measurement = Measurement {
+        status,
+        length: operations.as_ref().map(Vec::len),
+        cost: operations.as_deref().map(plan::cost),
+        expansions,
+        time,
+        memory: resident_memory("/proc/self/status", "VmHWM:"),
+    };
+    println!("{}", write_measurement(&measurement));
+    ExitCode::SUCCESS
+}
+
+/// Write a measurement as `status length cost expansions seconds memory`,
+/// with `-` for unknown values.
+fn write_measurement(measurement: &Measurement) -> String {
+    format!(
+        "{} {} {} {} {} {}",
+        measurement.status.name(),
+        optional(measurement.length),
+        optional(measurement.cost),
+        optional(measurement.expansions),
+        measurement.time.as_secs_f64(),
+        optional(measurement.memory)
+    )
+}
+
+fn parse_measurement(line: &str) -> Option<Measurement> {
+    fn field<T: std::str::FromStr>(field: &str) -> Option<Option<T>> {
+        match field {
+            "-" => Some(None),
+            field => field.parse().ok().map(Some),
+        }
+    }
+
+    let fields: Vec<_> = line.split_whitespace().collect();
+    let [status, length, cost, expansions, time, memory] = fields.as_slice() else {
+        return None;
+    };
+    Some(Measurement {
+        status: Status::try_from(*status).ok()?,
+        length: field(length)?,
+        cost: field(cost)?,
+        expansions: field(expansions)?,
+        time: Duration::try_from_secs_f64(time.parse().ok()?).ok()?,
+        memory: field(memory)?,
+    })
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "-".to_owned(), |value| value.to_string())
+}
+
+fn write_csv(runs: &[Run]) -> String {
+    let mut out = String::from("instance,planner,status,length,cost,expansions,time,memory\n");
+    for run in runs {
+        let measurement = &run.measurement;
+        let or_empty = |value: Option<String>| value.unwrap_or_default();
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{:.6},{}",
+            csv_field(&run.instance),
+            run.configuration.name(),
+            measurement.status.name(),
+            or_empty(measurement.length.map(|length| length.to_string())),
+            or_empty(measurement.cost.map(|cost| cost.to_string())),
+            or_empty(measurement.expansions.map(|n| n.to_string())),
+            measurement.time.as_secs_f64(),
+            or_empty(measurement.memory.map(|memory| memory.to_string()))
+        )
+        .unwrap();
+    }
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Write a table of every run followed by a summary of each planner.
+fn write_markdown(runs: &[Run], configurations: &[Configuration]) -> String {
+    let mut out = String::new();
+    out.push_str(
+        "| instance | planner | status | length | cost | expansions | time (s) | memory (MiB) |\n",
+    );
+    out.push_str("|---|---|---|--:|--:|--:|--:|--:|\n");
+    for run in runs {
+        let measurement = &run.measurement;
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} | {:.3} | {} |",
+            run.instance,
+            run.configuration.name(),
+            measurement.status.name(),
+            optional(measurement.length),
+            optional(measurement.cost),
+            optional(measurement.expansions),
+            measurement.time.as_secs_f64(),
+            optional(measurement.memory.map(mebibytes))
+        )
+        .unwrap();
+    }
+
+    out.push_str("\n| planner | coverage | mean length | mean cost | expansions | time (s) |\n");
+    out.push_str("|---|--:|--:|--:|--:|--:|\n");
+    for configuration in configurations {
+        let runs: Vec<_> = runs
+            .iter()
+            .filter(|run| run.configuration == *configuration)
+            .map(|run| &run.measurement)
+            .collect();
+        let solved: Vec<_> = runs
+            .iter()
+            .filter(|measurement| measurement.status == Status::Solved)
+            .collect();
+        let mean = |values: Vec<usize>| {
+            (!values.is_empty()).then(|| {
+                format!(
+                    "{:.1}",
+                    values.iter().sum::<usize>() as f64 / values.len() as f64
+                )
+            })
+        };
+        let lengths = solved.iter().filter_map(|m| m.length).collect();
+        let costs = solved
+            .iter()
+            .filter_map(|m| m.cost.map(|c| c as usize))
+            .collect();
+        let expansions = runs
+            .iter()
+            .filter_map(|measurement| measurement.expansions)
+            .reduce(|total, expansions| total + expansions);
+        let time: Duration = runs.iter().map(|measurement| measurement.time).sum();
+        writeln!(
+            out,
+            "| {} | {}/{} | {} | {} | {} | {:.3} |",
+            configuration.name(),
+            solved.len(),
+            runs.len(),
+            optional(mean(lengths)),
+            optional(mean(costs)),
+            optional(expansions),
+            time.as_secs_f64()
+        )
+        .unwrap();
+    }
+    out
+}
+
+fn mebibytes(kibibytes: u64) -> String {
+    format!("{:.1}", kibibytes as f64 / 1024.0)
+}
+
+impl Configuration {
+    fn name(&self) -> &'static str {
+        match self {
+            Configuration::Gps => "gps",
+            Configuration::Portfolio => "portfolio",
+        }
+    }
+}
+
+impl TryFrom<&str> for Configuration {
+    type Error = ();
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        match name {
+            "gps" => Ok(Configuration::Gps),
+            "portfolio" => Ok(Configuration::Portfolio),
+            _ => Err(()),
+        }
+    }
+}
+
+const STATUSES: [Status; 6] = [
+    Status::Solved,
+    Status::Unsolvable,
+    Status::LimitExceeded,
+    Status::Timeout,
+    Status::OutOfMemory,
+    Status::Error,
+];
+
+impl Status {
+    fn name(&self) -> &'static str {
+        match self {
+            Status::Solved => "solved",
+            Status::Unsolvable => "unsolvable",
+            Status::LimitExceeded => "limit-exceeded",
+            Status::Timeout => "timeout",
+            Status::OutOfMemory => "out-of-memory",
+            Status::Error => "error",
+        }
+    }
+}
+
+impl TryFrom<&str> for Status {
+    type Error = ();
+
+    fn try_from(name: &str) -> Result<Self, ()> {
+        STATUSES
+            .into_iter()
+            .find(|status| status.name() == name)
+            .ok_or(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arguments(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn it_should_choose_suites_and_planners() {
+        let options = parse_arguments(&arguments(
+            "--suite maze --suite random --random 3 --planner gps --memory 64 --output csv",
+        ))
+        .unwrap();
+        assert_eq!(options.configurations, vec![Configuration::Gps]);
+        assert_eq!(options.memory, 64 * 1024);
+        assert_eq!(options.output, OutputFormat::Csv);
+
+        let names: Vec<_> = instances(&options)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "maze/1-to-25",
+                "maze/1-to-14",
+                "random/0",
+                "random/1",
+                "random/2"
+            ]
+        );
+        assert!(find_instance("random/2").is_some());
+        assert!(find_instance("maze/1-to-14").is_some());
+        assert!(find_instance("maze/nowhere").is_none());
+
+        let options = parse_arguments(&[]).unwrap();
+        assert_eq!(options.suites.len(), SUITES.len());
+        assert_eq!(options.configurations.len(), 2);
+        assert!(parse_arguments(&arguments("--suite chess")).is_err());
+        assert!(parse_arguments(&arguments("--planner")).is_err());
+    }
+
+    #[test]
+    fn it_should_report_measurements_as_tables() {
+        let solved = Measurement {
+            status: Status::Solved,
+            length: Some(6),
+            cost: Some(6),
+            expansions: Some(12),
+            time: Duration::from_millis(250),
+            memory: Some(2048),
+        };
+        let line = write_measurement(&solved);
+        assert_eq!(line, "solved 6 6 12 0.25 2048");
+        assert_eq!(parse_measurement(&line), Some(solved.clone()));
+        assert_eq!(parse_measurement("solved 6"), None);
+
+        let timeout = Measurement {
+            status: Status::Timeout,
+            length: None,
+            cost: None,
+            expansions: None,
+            time: Duration::from_secs(2),
+            memory: None,
+        };
+        let runs = vec![
+            Run {
+                instance: "school/battery".to_owned(),
+                configuration: Configuration::Gps,
+                measurement: solved,
+            },
+            Run {
+                instance: "maze/1-to-25".to_owned(),
+                configuration: Configuration::Gps,
+                measurement: timeout,
+            },
+        ];
+
+        assert_eq!(
+            write_csv(&runs),
+            "instance,planner,status,length,cost,expansions,time,memory\n\
+             school/battery,gps,solved,6,6,12,0.250000,2048\n\
+             maze/1-to-25,gps,timeout,,,,2.000000,\n"
+        );
+        let table = write_markdown(&runs, &[Configuration::Gps]);
+        assert!(table.contains("| school/battery | gps | solved | 6 | 6 | 12 | 0.250 | 2.0 |\n"));
+        assert!(table.contains("| maze/1-to-25 | gps | timeout | - | - | - | 2.000 | - |\n"));
+        assert!(table.ends_with("| gps | 1/2 | 6.0 | 6.0 | 12 | 2.250 |\n"));
+    }
+}
@@ -0,0 +1,477 @@
+//! Solve a planning problem read from files.
+//!
+//! The exit code tells the outcome apart: 0 when a plan was found, 1 when the
+//! problem has no solution, 2 when a limit was exceeded before either was
+//! known and 3 when the input could not be read.
+
+use std::env;
+use std::fmt::Write;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use paip_rust::gps::format::{self, pddl, Format};
+use paip_rust::gps::operation::Operation;
+use paip_rust::gps::plan;
+use paip_rust::gps::portfolio::{CancellationToken, Portfolio};
+use paip_rust::gps::problem::Problem;
+use paip_rust::gps::repl::{self, Session};
+use paip_rust::gps::search::{Limits, Outcome, Statistics};
+use paip_rust::gps::utility;
+use paip_rust::gps::GeneralProblemSolver;
+
+const USAGE: &str = "\
+usage: gps [options] <domain> [<problem>]
+       gps repl [repl options] [<domain> [<problem>]]
+
+Reads the domain and the problem, solves it and prints the plan. The format
+is chosen by the extension of the domain: .pddl for PDDL, .lisp or .lsp for
+PAIP's make-op programs and .gps for the plain-text format. PDDL needs a
+separate problem; for the other formats the problem is optional and is read
+after the domain. `gps repl` starts an interactive shell instead, where
+`help` lists the commands. It takes `--format`, `--timeout`,
+`--max-expansions` and `--max-depth`, which apply to each `plan`.
+
+options:
+  --format <pddl|paip|text>   read the files in this format
+  --planner <gps|portfolio|utility>
+                              search with GPS alone (default), with a
+                              portfolio of goal orderings, or for the plan
+                              whose soft goals are worth most beyond its cost
+  --timeout <seconds>         give up after this many seconds
+  --max-expansions <n>        give up after trying this many operations
+  --max-depth <n>             do not nest subgoals deeper than this
+  --output <plain|json|ipc>   print the plan in this format (default plain)
+  -h, --help                  print this help
+
+exit codes: 0 solved, 1 unsolvable, 2 limit exceeded, 3 error";
+
+const EXIT_SOLVED: u8 = 0;
+const EXIT_UNSOLVABLE: u8 = 1;
+const EXIT_LIMIT_EXCEEDED: u8 = 2;
+const EXIT_ERROR: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlannerKind {
+    Gps,
+    Portfolio,
+    Utility,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Plain,
+    Json,
+    Ipc,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Options {
+    domain: String,
+    problem: Option<String>,
+    format: Option<Format>,
+    planner: PlannerKind,
+    timeout: Option<Duration>,
+    limits: Limits,
+    output: OutputFormat,
+}
+
+/// The result of running the chosen planner.
+struct Report {
+    planner: String,
+    outcome: Outcome,
+    /// Added up over every planner of a portfolio.
+    statistics: Statistics,
+    elapsed: Duration,
+}
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    if arguments
+        .iter()
+        .any(|argument| argument == "-h" || argument == "--help")
+    {
+        println!("{USAGE}");
+        return ExitCode::from(EXIT_SOLVED);
+    }
+
+    if arguments.first().is_some_and(|argument| argument == "repl") {
+        return repl(&arguments[1..]);
+    }
+
+    let options = match parse_arguments(&arguments) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("gps: {message}\n\n{USAGE}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+
+    let problem = match format::load(
+        Path::new(&options.domain),
+        options.problem.as_deref().map(Path::new),
+        options.format,
+    ) {
+        Ok(problem) => problem,
+        Err(message) => {
+            eprintln!("gps: {message}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+
+    let report = run(&options, problem);
+    match options.output {
+        OutputFormat::Plain => {
+            print!("{}", write_plain(&report));
+            eprint!("{}", write_statistics(&report, ""));
+        }
+        OutputFormat::Json => println!("{}", write_json(&report)),
+        OutputFormat::Ipc => print!("{}", write_ipc(&report)),
+    }
+
+    ExitCode::from(match report.outcome {
+        Outcome::Solved(_) => EXIT_SOLVED,
+        Outcome::Unsolvable => EXIT_UNSOLVABLE,
+        Outcome::LimitExceeded => EXIT_LIMIT_EXCEEDED,
+    })
+}
+
+fn repl(arguments: &[String]) -> ExitCode {
+    let options = match parse_repl_arguments(arguments) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("gps: {message}\n\n{USAGE}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+    let mut session = Session::new().with_limits(options.limits);
+    if let Some(timeout) = options.timeout {
+        session = session.with_timeout(timeout);
+    }
+
+    if !options.domain.is_empty() {
+        match format::load(
+            Path::new(&options.domain),
+            options.problem.as_deref().map(Path::new),
+            options.format,
+        ) {
+            Ok(problem) => session.load(problem),
+            Err(message) => {
+                eprintln!("gps: {message}");
+                return ExitCode::from(EXIT_ERROR);
+            }
+        }
+    }
+
+    let stdin = io::stdin();
+    let interactive = stdin.is_terminal();
+    match repl::run(&mut session, stdin.lock(), io::stdout(), interactive) {
+        Ok(()) => ExitCode::from(EXIT_SOLVED),
+        Err(error) => {
+            eprintln!("gps: {error}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+fn parse_arguments(arguments: &[String]) -> Result<Options, String> {
+    let options = parse_options(arguments)?;
+    if options.domain.is_empty() {
+        return Err("missing the domain file".to_owned());
+    }
+    Ok(options)
+}
+
+/// Parse the arguments of `gps repl`, where the files are optional and only
+/// the format and the limits of the searches can be chosen. The domain is
+/// empty without files.
+fn parse_repl_arguments(arguments: &[String]) -> Result<Options, String> {
+    let options = parse_options(arguments)?;
+    if let Some(option) = ["--planner", "--output"]
+        .into_iter()
+        .find(|option| arguments.iter().any(|argument| argument == option))
+    {
+        return Err(format!("`{option}` does not apply to `gps repl`"));
+    }
+    Ok(options)
+}
+
+/// Parse the options and up to two files, leaving the domain empty if there
+/// are none.
+fn parse_options(arguments: &[String]) -> Result<Options, String> {
+    let mut files = Vec::new();
+    let mut format = None;
+    let mut planner = PlannerKind::Gps;
+    let mut timeout = None;
+    let mut limits = Limits::new();
+    let mut output = OutputFormat::Plain;
+
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        if !argument.starts_with("--") {
+            files.push(argument.clone());
+            continue;
+        }
+
+        let value = arguments
+            .next()
+            .ok_or_else(|| format!("`{argument}` needs a value"))?;
+        let invalid = || format!("invalid value `{value}` for `{argument}`");
+
+        match argument.as_str() {
+            "--format" => format = Some(Format::try_from(value.as_str()).map_err(|_| invalid())?),
+            "--planner" => {
+                planner = match value.as_str() {
+                    "gps" => PlannerKind::Gps,
+                    "portfolio" => PlannerKind::Portfolio,
+                    "utility" => PlannerKind::Utility,
+                    _ => return Err(invalid()),
+                }
+            }
+            "--timeout" => {
+                let seconds: f64 = value.parse().map_err(|_| invalid())?;
+                timeout = Some(Duration::try_from_secs_f64(seconds).map_err(|_| invalid())?);
+            }
+            "--max-expansions" => {
+                limits = limits.with_max_expansions(value.parse().map_err(|_| invalid())?)
+            }
+            "--max-depth" => limits = limits.with_max_depth(value.parse().map_err(|_| invalid())?),
+            "--output" => {
+                output = match value.as_str() {
+                    "plain" => OutputFormat::Plain,
+                    "json" => OutputFormat::Json,
+                    "ipc" => OutputFormat::Ipc,
+                    _ => return Err(invalid()),
+                }
+            }
+            _ => return Err(format!("unknown option `{argument}`")),
+        }
+    }
+
+    let mut files = files.into_iter();
+    let domain = files.next().unwrap_or_default();
+    let problem = files.next();
+    if let Some(extra) = files.next() {
+        return Err(format!("unexpected argument `{extra}`"));
+    }
+
+    Ok(Options {
+        domain,
+        problem,
+        format,
+        planner,
+        timeout,
+        limits,
+        output,
+    })
+}
+
+fn run(options: &Options, problem: Problem) -> Report {
+    let soft_goals = problem.soft_goals().clone();
+    let mut solver: GeneralProblemSolver = problem.into();
+    solver.set_limits(options.limits);
+
+    let start = Instant::now();
+    let deadline = options.timeout.map(|timeout| start + timeout);
+    let cancellation = match deadline {
+        Some(deadline) => CancellationToken::with_deadline(deadline),
+        None => CancellationToken::new(),
+    };
+
+    match options.planner {
+        PlannerKind::Gps => {
+            let (outcome, statistics) = solver.search(&cancellation);
+            Report {
+                planner: "gps".to_owned(),
+                outcome,
+                statistics,
+                elapsed: start.elapsed(),
+            }
+        }
+        PlannerKind::Portfolio => {
+            let portfolio = Portfolio::with_goal_orderings(&solver);
+            let solution = match deadline {
+                Some(deadline) => portfolio.solve_best(deadline),
+                None => portfolio.solve_first(),
+            };
+            Report {
+                planner: solution.planner().unwrap_or("portfolio").to_owned(),
+                statistics: *solution.statistics(),
+                outcome: solution.into_outcome(),
+                elapsed: start.elapsed(),
+            }
+        }
+        PlannerKind::Utility => {
+            let (outcome, statistics) = utility::search(&solver, &soft_goals, &cancellation);
+            Report {
+                planner: "utility".to_owned(),
+                outcome,
+                statistics,
+                elapsed: start.elapsed(),
+            }
+        }
+    }
+}
+
+fn status(outcome: &Outcome) -> &'static str {
+    match outcome {
+        Outcome::Solved(_) => "solved",
+        Outcome::Unsolvable => "unsolvable",
+        Outcome::LimitExceeded => "limit exceeded",
+    }
+}
+
+fn plan(outcome: &Outcome) -> &[Operation] {
+    match outcome {
+        Outcome::Solved(operations) => operations,
+        _ => &[],
+    }
+}
+
+fn write_plain(report: &Report) -> String {
+    let mut out = String::new();
+    if !report.outcome.is_solved() {
+        writeln!(out, "{}", status(&report.outcome)).unwrap();
+    }
+    for (step, operation) in plan(&report.outcome).iter().enumerate() {
+        writeln!(out, "{}. {}", step + 1, operation.name()).unwrap();
+    }
+    out
+}
+
+/// Statistics one per line, each line starting with the prefix.
+fn write_statistics(report: &Report, prefix: &str) -> String {
+    let mut out = String::new();
+    writeln!(out, "{prefix}status: {}", status(&report.outcome)).unwrap();
+    writeln!(out, "{prefix}planner: {}", report.planner).unwrap();
+    if report.outcome.is_solved() {
+        writeln!(out, "{prefix}plan length: {}", plan(&report.outcome).len()).unwrap();
+        writeln!(
+            out,
+            "{prefix}plan cost: {}",
+            plan::cost(plan(&report.outcome))
+        )
+        .unwrap();
+    }
+    let statistics = &report.statistics;
+    writeln!(out, "{prefix}expansions: {}", statistics.expansions()).unwrap();
+    writeln!(out, "{prefix}goals: {}", statistics.goals()).unwrap();
+    writeln!(out, "{prefix}max depth: {}", statistics.max_depth()).unwrap();
+    writeln!(out, "{prefix}time: {:.3}s", report.elapsed.as_secs_f64()).unwrap();
+    out
+}
+
+fn write_ipc(report: &Report) -> String {
+    let mut out = String::new();
+    if report.outcome.is_solved() {
+        out.push_str(&pddl::write_plan(plan(&report.outcome)));
+    }
+    out.push_str(&write_statistics(report, "; "));
+    out
+}
+
+fn write_json(report: &Report) -> String {
+    let steps: Vec<_> = plan(&report.outcome)
+        .iter()
+        .map(|operation| json_string(operation.name()))
+        .collect();
+
+    let mut out = String::new();
+    write!(
+        out,
+        "{{\"status\": {}, \"planner\": {}, \"plan\": ",
+        json_string(status(&report.outcome)),
+        json_string(&report.planner)
+    )
+    .unwrap();
+    if report.outcome.is_solved() {
+        write!(out, "[{}]", steps.join(", ")).unwrap();
+    } else {
+        out.push_str("null");
+    }
+
+    write!(
+        out,
+        ", \"statistics\": {{\"expansions\": {}, \"goals\": {}, \"max_depth\": {}, \
+         \"time\": {:.6}}}}}",
+        report.statistics.expansions(),
+        report.statistics.goals(),
+        report.statistics.max_depth(),
+        report.elapsed.as_secs_f64()
+    )
+    .unwrap();
+    out
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use paip_rust::gps::operation::OperationBuilder;
+
+    fn arguments(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn it_should_parse_options_and_files() {
+        let options = parse_arguments(&arguments(
+            "--planner portfolio domain.pddl --timeout 1.5 problem.pddl --output json \
+             --max-expansions 10",
+        ))
+        .unwrap();
+
+        assert_eq!(options.domain, "domain.pddl");
+        assert_eq!(options.problem.as_deref(), Some("problem.pddl"));
+        assert_eq!(options.planner, PlannerKind::Portfolio);
+        assert_eq!(options.timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(options.limits, Limits::new().with_max_expansions(10));
+        assert_eq!(options.output, OutputFormat::Json);
+
+        let options = parse_arguments(&arguments("--planner utility d.gps")).unwrap();
+        assert_eq!(options.planner, PlannerKind::Utility);
+        assert!(parse_arguments(&arguments("--output xml d.gps")).is_err());
+        assert!(parse_arguments(&arguments("--timeout")).is_err());
+        assert!(parse_arguments(&arguments("a.gps b.gps c.gps")).is_err());
+        assert!(parse_arguments(&arguments("--timeout 1")).is_err());
+
+        let options = parse_repl_arguments(&arguments("--max-depth 3")).unwrap();
+        assert_eq!(
+            (options.domain.as_str(), options.limits),
+            ("", Limits::new().with_max_depth(3))
+        );
+        assert!(parse_repl_arguments(&arguments("--planner utility d.gps")).is_err());
+    }
+
+    #[test]
+    fn it_should_write_plans_as_json() {
+        let report = Report {
+            planner: "gps".to_owned(),
+            outcome: Outcome::Solved(vec![OperationBuilder::new("say \"hi\"".to_owned()).build()]),
+            statistics: Statistics::default(),
+            elapsed: Duration::ZERO,
+        };
+
+        assert_eq!(
+            write_json(&report),
+            "{\"status\": \"solved\", \"planner\": \"gps\", \"plan\": [\"say \\\"hi\\\"\"], \
+             \"statistics\": {\"expansions\": 0, \"goals\": 0, \"max_depth\": 0, \
+             \"time\": 0.000000}}"
+        );
+    }
+}
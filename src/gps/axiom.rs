@@ -0,0 +1,244 @@
+//! States derived from other states by rules.
+//!
+//! A derived state such as `car-works` holds exactly when the body of one of
+//! its rules holds, for example `battery-ok` and `tires-ok`. Derived states
+//! are recomputed from the other states after every change, so operations
+//! may require them but never add or remove them. The solver achieves a
+//! derived goal by achieving the body of one of its rules.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use super::condition::{Condition, ConditionImpl, Truth};
+use super::operation::Operation;
+use super::state::{State, StateData, StateSet};
+use super::symbol::Symbol;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    head: Symbol,
+    body: Vec<ConditionImpl>,
+}
+
+/// The rules of a problem.
+#[derive(Debug, Clone, Default)]
+pub struct Axioms {
+    rules: Vec<Rule>,
+    derived: HashSet<Symbol>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AxiomError {
+    /// A rule needs a derived state not to hold or to have a value, which
+    /// cannot be told before every rule is done.
+    NegatedDerivedState {
+        head: Symbol,
+        state: Symbol,
+    },
+    WrittenByOperation {
+        operation: Symbol,
+        state: Symbol,
+    },
+}
+
+impl Rule {
+    /// The state `head` holds whenever all of `body` does.
+    pub fn new(head: String, body: Vec<ConditionImpl>) -> Self {
+        Self {
+            head: head.into(),
+            body,
+        }
+    }
+
+    pub fn head(&self) -> Symbol {
+        self.head
+    }
+
+    pub fn body(&self) -> &Vec<ConditionImpl> {
+        &self.body
+    }
+}
+
+impl Axioms {
+    /// Rules whose bodies only require derived states to hold.
+    pub fn new(rules: Vec<Rule>) -> Result<Self, AxiomError> {
+        let derived: HashSet<_> = rules.iter().map(Rule::head).collect();
+        for rule in &rules {
+            let negated = rule.body.iter().find(|condition| {
+                !matches!(condition, ConditionImpl::Contain(_))
+                    && derived.contains(&condition.state_symbol())
+            });
+            if let Some(condition) = negated {
+                return Err(AxiomError::NegatedDerivedState {
+                    head: rule.head,
+                    state: condition.state_symbol(),
+                });
+            }
+        }
+        Ok(Self { rules, derived })
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn is_derived(&self, state: Symbol) -> bool {
+        self.derived.contains(&state)
+    }
+
+    pub fn rules_for(&self, state: Symbol) -> impl Iterator<Item = &Rule> {
+        self.rules.iter().filter(move |rule| rule.head == state)
+    }
+
+    /// The state and, if it is derived, every state the bodies of its rules
+    /// depend on, so writing any of them may change the state.
+    pub fn dependencies(&self, state: Symbol) -> HashSet<Symbol> {
+        let mut dependencies = HashSet::from([state]);
+        let mut pending = vec![state];
+        while let Some(state) = pending.pop() {
+            for rule in self.rules_for(state) {
+                for condition in &rule.body {
+                    if dependencies.insert(condition.state_symbol()) {
+                        pending.push(condition.state_symbol());
+                    }
+                }
+            }
+        }
+        dependencies
+    }
+
+    /// Replace the derived states with those following from the others.
+    pub fn derive(&self, states: &mut StateSet) {
+        if self.rules.is_empty() {
+            return;
+        }
+        for state in &self.derived {
+            states.remove_by_symbol(*state);
+        }
+        // Rules only need derived states to hold, so adding them until
+        // nothing changes reaches the same states in any order.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in &self.rules {
+                if states.get_by_symbol(rule.head).is_none() && states.has_reached(&rule.body) {
+                    states.insert(State::with_symbol(rule.head, StateData::Symbol));
+                    changed = true;
+                }
+            }
+        }
+        // Derived states which might hold depending on unknown states are
+        // unknown as well.
+        changed = true;
+        while changed {
+            changed = false;
+            for rule in &self.rules {
+                let undecided = states.get_by_symbol(rule.head).is_none()
+                    && !states.is_unknown(rule.head)
+                    && rule
+                        .body
+                        .iter()
+                        .all(|condition| condition.evaluate(states) != Truth::False);
+                if undecided {
+                    states.insert_unknown(rule.head);
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    /// Make sure none of the operations adds, removes or modifies a derived
+    /// state.
+    pub fn check(&self, operations: &[Operation]) -> Result<(), AxiomError> {
+        for operation in operations {
+            let written = operation
+                .add_states()
+                .iter()
+                .map(State::symbol)
+                .chain(operation.remove_states().iter().copied())
+                .chain(
+                    operation
+                        .modification_states()
+                        .iter()
+                        .map(|modification| modification.target_symbol()),
+                )
+                .find(|state| self.is_derived(*state));
+            if let Some(state) = written {
+                return Err(AxiomError::WrittenByOperation {
+                    operation: operation.symbol(),
+                    state,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for AxiomError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            AxiomError::NegatedDerivedState { head, state } => write!(
+                f,
+                "the rule for `{head}` can only require derived state `{state}` to hold"
+            ),
+            AxiomError::WrittenByOperation { operation, state } => {
+                write!(f, "operation `{operation}` changes derived state `{state}`")
+            }
+        }
+    }
+}
+
+impl Error for AxiomError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps::condition::{Contain, NotContain};
+
+    fn contain(name: &str) -> ConditionImpl {
+        Contain::new(name.to_owned()).into()
+    }
+
+    #[test]
+    fn it_should_derive_states_to_a_fixpoint() {
+        let axioms = Axioms::new(vec![
+            Rule::new(
+                "can-drive".to_owned(),
+                vec![contain("car-works"), contain("have-key")],
+            ),
+            Rule::new(
+                "car-works".to_owned(),
+                vec![contain("battery-ok"), contain("tires-ok")],
+            ),
+        ])
+        .unwrap();
+
+        let mut states = StateSet::new();
+        for name in ["battery-ok", "tires-ok", "have-key", "can-drive"] {
+            states.insert(State::new_symbol(name.to_owned()));
+        }
+        axioms.derive(&mut states);
+        assert!(states.get("car-works").is_some());
+        assert!(states.get("can-drive").is_some());
+
+        states.remove("tires-ok");
+        axioms.derive(&mut states);
+        assert!(states.get("car-works").is_none());
+        assert!(states.get("can-drive").is_none());
+
+        let error = Axioms::new(vec![
+            Rule::new("a".to_owned(), vec![NotContain::new("b".to_owned()).into()]),
+            Rule::new("b".to_owned(), vec![]),
+        ])
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the rule for `a` can only require derived state `b` to hold"
+        );
+    }
+}
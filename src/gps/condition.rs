@@ -1,22 +1,48 @@
 use std::collections::HashMap;
 
 use super::state::{StateData, StateSet};
+use super::symbol::Symbol;
 use enum_dispatch::enum_dispatch;
 
+/// Whether a condition holds in states which may leave some facts unknown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Truth {
+    True,
+    False,
+    /// The state of the condition is unknown.
+    Unknown,
+}
+
 #[enum_dispatch]
 pub trait Condition {
+    /// Test if the condition is known to hold.
     fn check(&self, state_set: &StateSet) -> bool {
-        match state_set.get(self.name()) {
-            Some(state_data) => self.check_data(state_data),
-            None => false,
+        self.evaluate(state_set) == Truth::True
+    }
+
+    fn evaluate(&self, state_set: &StateSet) -> Truth {
+        if state_set.is_unknown(self.state_symbol()) {
+            return Truth::Unknown;
+        }
+        match state_set.get_by_symbol(self.state_symbol()) {
+            Some(state_data) => self.check_data(state_data).into(),
+            None => Truth::False,
         }
     }
 
     fn check_data(&self, state_data: &StateData) -> bool;
 
-    fn name(&self) -> &str;
+    fn name(&self) -> &str {
+        self.symbol().as_str()
+    }
+
+    fn state_name(&self) -> &str {
+        self.state_symbol().as_str()
+    }
+
+    fn symbol(&self) -> Symbol;
 
-    fn state_name(&self) -> &str;
+    fn state_symbol(&self) -> Symbol;
 }
 
 #[enum_dispatch(Condition)]
@@ -29,18 +55,18 @@ pub enum ConditionImpl {
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Contain {
-    name: String,
+    name: Symbol,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct NotContain {
-    name: String,
+    name: Symbol,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Compare {
-    name: String,
-    state_name: String,
+    name: Symbol,
+    state_name: Symbol,
     operator: CompareOperator,
     value: StateData,
 }
@@ -57,11 +83,25 @@ pub enum CompareOperator {
 
 #[derive(Debug)]
 pub struct ConditionSet {
-    conditions: HashMap<String, Vec<ConditionImpl>>,
+    conditions: HashMap<Symbol, Vec<ConditionImpl>>,
+}
+
+impl From<bool> for Truth {
+    fn from(value: bool) -> Self {
+        if value {
+            Self::True
+        } else {
+            Self::False
+        }
+    }
 }
 
 impl Contain {
     pub fn new(name: String) -> Self {
+        Self::with_symbol(name.into())
+    }
+
+    pub fn with_symbol(name: Symbol) -> Self {
         Self { name }
     }
 }
@@ -71,36 +111,43 @@ impl Condition for Contain {
         true
     }
 
-    fn name(&self) -> &str {
-        &self.name
+    fn symbol(&self) -> Symbol {
+        self.name
     }
 
-    fn state_name(&self) -> &str {
-        &self.name
+    fn state_symbol(&self) -> Symbol {
+        self.name
     }
 }
 
 impl NotContain {
     pub fn new(name: String) -> Self {
+        Self::with_symbol(name.into())
+    }
+
+    pub fn with_symbol(name: Symbol) -> Self {
         Self { name }
     }
 }
 
 impl Condition for NotContain {
-    fn check(&self, state_set: &StateSet) -> bool {
-        state_set.get(self.name()).is_none()
+    fn evaluate(&self, state_set: &StateSet) -> Truth {
+        if state_set.is_unknown(self.name) {
+            return Truth::Unknown;
+        }
+        state_set.get_by_symbol(self.name).is_none().into()
     }
 
     fn check_data(&self, _state_data: &StateData) -> bool {
         false
     }
 
-    fn name(&self) -> &str {
-        &self.name
+    fn symbol(&self) -> Symbol {
+        self.name
     }
 
-    fn state_name(&self) -> &str {
-        &self.name
+    fn state_symbol(&self) -> Symbol {
+        self.name
     }
 }
 
@@ -110,6 +157,15 @@ impl Compare {
         state_name: String,
         operator: CompareOperator,
         value: StateData,
+    ) -> Self {
+        Self::with_symbols(name.into(), state_name.into(), operator, value)
+    }
+
+    pub fn with_symbols(
+        name: Symbol,
+        state_name: Symbol,
+        operator: CompareOperator,
+        value: StateData,
     ) -> Self {
         Self {
             name,
@@ -118,26 +174,75 @@ impl Compare {
             value,
         }
     }
+
+    pub fn operator(&self) -> &CompareOperator {
+        &self.operator
+    }
+
+    pub fn value(&self) -> &StateData {
+        &self.value
+    }
 }
 
 impl Condition for Compare {
     fn check_data(&self, state_data: &StateData) -> bool {
-        match self.operator {
-            CompareOperator::Equal => state_data.eq(&self.value),
-            CompareOperator::NotEqual => state_data.ne(&self.value),
-            CompareOperator::Greater => state_data.gt(&self.value),
-            CompareOperator::GreaterEqual => state_data.ge(&self.value),
-            CompareOperator::Less => state_data.lt(&self.value),
-            CompareOperator::LessEqual => state_data.le(&self.value),
+        self.operator.evaluate(state_data, &self.value)
+    }
+
+    fn symbol(&self) -> Symbol {
+        self.name
+    }
+
+    fn state_symbol(&self) -> Symbol {
+        self.state_name
+    }
+}
+
+impl CompareOperator {
+    pub fn evaluate(&self, left: &StateData, right: &StateData) -> bool {
+        match self {
+            Self::Equal => left.eq(right),
+            Self::NotEqual => left.ne(right),
+            Self::Greater => left.gt(right),
+            Self::GreaterEqual => left.ge(right),
+            Self::Less => left.lt(right),
+            Self::LessEqual => left.le(right),
         }
     }
 
-    fn name(&self) -> &str {
-        &self.name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+            Self::Greater => ">",
+            Self::GreaterEqual => ">=",
+            Self::Less => "<",
+            Self::LessEqual => "<=",
+        }
     }
 
-    fn state_name(&self) -> &str {
-        &self.state_name
+    /// The operator which holds exactly when this one does not.
+    pub fn negate(&self) -> Self {
+        match self {
+            Self::Equal => Self::NotEqual,
+            Self::NotEqual => Self::Equal,
+            Self::Greater => Self::LessEqual,
+            Self::GreaterEqual => Self::Less,
+            Self::Less => Self::GreaterEqual,
+            Self::LessEqual => Self::Greater,
+        }
+    }
+
+    /// The operator comparing the operands the other way around.
+    pub fn flip(&self) -> Self {
+        match self {
+            Self::Equal => Self::Equal,
+            Self::NotEqual => Self::NotEqual,
+            Self::Greater => Self::Less,
+            Self::GreaterEqual => Self::LessEqual,
+            Self::Less => Self::Greater,
+            Self::LessEqual => Self::GreaterEqual,
+        }
     }
 }
 
@@ -157,6 +262,12 @@ impl TryFrom<&str> for CompareOperator {
     }
 }
 
+impl Default for ConditionSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ConditionSet {
     pub fn new() -> Self {
         Self {
@@ -165,20 +276,19 @@ impl ConditionSet {
     }
 
     // Find relevant conditions of the given state.
-    pub fn get(&self, name: &str) -> Option<&Vec<ConditionImpl>> {
-        self.conditions.get(name)
+    pub fn get(&self, name: impl Into<Symbol>) -> Option<&Vec<ConditionImpl>> {
+        self.conditions.get(&name.into())
     }
 
-    pub fn insert(&mut self, name: &str, condition: ConditionImpl) {
-        if let Some(conditions) = self.conditions.get_mut(name) {
-            conditions.push(condition);
-        } else {
-            self.conditions.insert(name.to_owned(), vec![condition]);
-        }
+    pub fn insert(&mut self, name: impl Into<Symbol>, condition: ConditionImpl) {
+        self.conditions
+            .entry(name.into())
+            .or_default()
+            .push(condition);
     }
 
-    pub fn remove(&mut self, name: &str, condition: &ConditionImpl) {
-        if let Some(conditions) = self.conditions.get_mut(name) {
+    pub fn remove(&mut self, name: impl Into<Symbol>, condition: &ConditionImpl) {
+        if let Some(conditions) = self.conditions.get_mut(&name.into()) {
             conditions.retain(|c| c != condition);
         }
     }
@@ -0,0 +1,125 @@
+//! The blocks world, where blocks are moved between each other and the table.
+//!
+//! States are named like `a on b` and `space on a`, moves like
+//! `move a from b to table`. The table always has space, so problems should
+//! start with `space on table`.
+
+use super::{op, problem, Instance};
+use crate::gps::operation::Operation;
+use crate::gps::problem::Problem;
+
+/// Every move of one block onto another block or the table, like PAIP's
+/// `make-block-ops`. The operations are listed in the order of the book.
+pub fn make_block_ops(blocks: &[&str]) -> Vec<Operation> {
+    let mut operations = Vec::new();
+
+    for a in blocks {
+        for b in blocks {
+            if a == b {
+                continue;
+            }
+            for c in blocks {
+                if c != a && c != b {
+                    operations.push(move_op(a, b, c));
+                }
+            }
+            operations.push(move_op(a, "table", b));
+            operations.push(move_op(a, b, "table"));
+        }
+    }
+
+    // The book pushes each operation on the front of the list.
+    operations.reverse();
+    operations
+}
+
+/// Move block `a` from `b` to `c`.
+fn move_op(a: &str, b: &str, c: &str) -> Operation {
+    let add_list = move_ons(a, b, c);
+    let del_list = move_ons(a, c, b);
+    op(
+        &format!("move {a} from {b} to {c}"),
+        &[
+            &format!("space on {a}"),
+            &format!("space on {c}"),
+            &format!("{a} on {b}"),
+        ],
+        &add_list.iter().map(String::as_str).collect::<Vec<_>>(),
+        &del_list.iter().map(String::as_str).collect::<Vec<_>>(),
+    )
+}
+
+fn move_ons(a: &str, b: &str, c: &str) -> Vec<String> {
+    if b == "table" {
+        vec![format!("{a} on {c}")]
+    } else {
+        vec![format!("{a} on {c}"), format!("space on {b}")]
+    }
+}
+
+/// A problem over the given blocks, with states and goals like `a on b`.
+pub fn blocks_problem(blocks: &[&str], states: &[&str], goals: &[&str]) -> Problem {
+    problem(make_block_ops(blocks), states, goals)
+}
+
+/// The Sussman anomaly, which GPS cannot solve by achieving one goal after
+/// the other.
+pub fn sussman_anomaly() -> Problem {
+    blocks_problem(
+        &["a", "b", "c"],
+        &[
+            "c on a",
+            "a on table",
+            "b on table",
+            "space on c",
+            "space on b",
+            "space on table",
+        ],
+        &["a on b", "b on c"],
+    )
+}
+
+pub fn instances() -> Vec<Instance> {
+    vec![
+        Instance::new(
+            "blocks/stack-two",
+            blocks_problem(
+                &["a", "b"],
+                &[
+                    "a on table",
+                    "b on table",
+                    "space on a",
+                    "space on b",
+                    "space on table",
+                ],
+                &["a on b", "b on table"],
+            ),
+            Some(1),
+        ),
+        Instance::new(
+            "blocks/swap-two",
+            blocks_problem(
+                &["a", "b"],
+                &["a on b", "b on table", "space on a", "space on table"],
+                &["b on a"],
+            ),
+            Some(2),
+        ),
+        Instance::new(
+            "blocks/reverse-three",
+            blocks_problem(
+                &["a", "b", "c"],
+                &[
+                    "a on b",
+                    "b on c",
+                    "c on table",
+                    "space on a",
+                    "space on table",
+                ],
+                &["b on a", "c on b"],
+            ),
+            Some(3),
+        ),
+        Instance::new("blocks/sussman-anomaly", sussman_anomaly(), None),
+    ]
+}
@@ -0,0 +1,75 @@
+//! Finding a way through a maze of numbered squares.
+//!
+//! The maze of the book is a five by five grid numbered row by row from 1 in
+//! the top left to 25 in the bottom right. Moves are named like
+//! `move from 1 to 2` and positions like `at 1`.
+
+use super::{op, problem, Instance};
+use crate::gps::operation::Operation;
+use crate::gps::problem::Problem;
+
+/// The passages between neighbouring squares of the maze of the book.
+pub const PASSAGES: [(u32, u32); 24] = [
+    (1, 2),
+    (2, 3),
+    (3, 4),
+    (4, 9),
+    (9, 14),
+    (9, 8),
+    (8, 7),
+    (7, 12),
+    (12, 13),
+    (12, 11),
+    (11, 6),
+    (11, 16),
+    (16, 17),
+    (17, 22),
+    (21, 22),
+    (22, 23),
+    (23, 18),
+    (23, 24),
+    (24, 19),
+    (19, 20),
+    (20, 15),
+    (15, 10),
+    (10, 5),
+    (20, 25),
+];
+
+/// Operations moving both ways through each passage, like PAIP's
+/// `make-maze-ops`.
+pub fn make_maze_ops(passages: &[(u32, u32)]) -> Vec<Operation> {
+    passages
+        .iter()
+        .flat_map(|&(a, b)| [make_maze_op(a, b), make_maze_op(b, a)])
+        .collect()
+}
+
+fn make_maze_op(here: u32, there: u32) -> Operation {
+    op(
+        &format!("move from {here} to {there}"),
+        &[&format!("at {here}")],
+        &[&format!("at {there}")],
+        &[&format!("at {here}")],
+    )
+}
+
+pub fn operations() -> Vec<Operation> {
+    make_maze_ops(&PASSAGES)
+}
+
+/// Going from one square of the maze of the book to another.
+pub fn path(from: u32, to: u32) -> Problem {
+    problem(
+        operations(),
+        &[&format!("at {from}")],
+        &[&format!("at {to}")],
+    )
+}
+
+pub fn instances() -> Vec<Instance> {
+    vec![
+        Instance::new("maze/1-to-25", path(1, 25), Some(16)),
+        Instance::new("maze/1-to-14", path(1, 14), Some(5)),
+    ]
+}
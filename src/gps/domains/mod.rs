@@ -0,0 +1,110 @@
+//! The classic domains of PAIP's GPS chapter with standard problem instances.
+//!
+//! Each domain module provides its operations and the instances used in the
+//! book, together with the length of the plan GPS finds for each of them, so
+//! planners can be benchmarked and checked against known results.
+
+pub mod blocks;
+pub mod maze;
+pub mod monkey;
+pub mod school;
+
+use super::condition::{ConditionImpl, Contain};
+use super::operation::{Operation, OperationBuilder};
+use super::problem::Problem;
+use super::state::{State, StateSet};
+
+/// A named problem with the known result of solving it with GPS.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    name: String,
+    problem: Problem,
+    expected_length: Option<usize>,
+}
+
+impl Instance {
+    pub fn new(name: impl Into<String>, problem: Problem, expected_length: Option<usize>) -> Self {
+        Self {
+            name: name.into(),
+            problem,
+            expected_length,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn problem(&self) -> &Problem {
+        &self.problem
+    }
+
+    pub fn into_problem(self) -> Problem {
+        self.problem
+    }
+
+    /// The length of the plan GPS finds, or `None` when it finds none.
+    pub fn expected_length(&self) -> Option<usize> {
+        self.expected_length
+    }
+}
+
+/// The instances of every domain.
+pub fn instances() -> Vec<Instance> {
+    let mut instances = school::instances();
+    instances.extend(monkey::instances());
+    instances.extend(maze::instances());
+    instances.extend(blocks::instances());
+    instances
+}
+
+/// An operation in the style of PAIP's `make-op`.
+fn op(name: &str, preconds: &[&str], add_list: &[&str], del_list: &[&str]) -> Operation {
+    let mut builder = OperationBuilder::new(name.to_owned());
+    for precond in preconds {
+        builder = builder.insert_prerequisite(Contain::new((*precond).to_owned()).into());
+    }
+    for state in add_list {
+        builder = builder.insert_add_state(State::new_symbol((*state).to_owned()));
+    }
+    for state in del_list {
+        builder = builder.insert_remove_state((*state).to_owned());
+    }
+    builder.build()
+}
+
+fn problem(operations: Vec<Operation>, states: &[&str], goals: &[&str]) -> Problem {
+    let mut state_set = StateSet::new();
+    for state in states {
+        state_set.insert(State::new_symbol((*state).to_owned()));
+    }
+    let goals: Vec<ConditionImpl> = goals
+        .iter()
+        .map(|goal| Contain::new((*goal).to_owned()).into())
+        .collect();
+    Problem::new(operations, state_set, goals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps::GeneralProblemSolver;
+
+    #[test]
+    fn it_should_find_plans_of_the_expected_length() {
+        for instance in instances() {
+            let name = instance.name().to_owned();
+            let expected = instance.expected_length();
+            let gps: GeneralProblemSolver = instance.into_problem().into();
+            assert_eq!(
+                gps.solve().map(|plan| plan.len()),
+                expected,
+                "instance {name}"
+            );
+        }
+
+        // Each ordered pair of blocks gives a move onto each other block and
+        // to and from the table.
+        assert_eq!(blocks::make_block_ops(&["a", "b", "c", "d"]).len(), 12 * 4);
+    }
+}
@@ -0,0 +1,54 @@
+//! The monkey and bananas problem.
+
+use super::{op, problem, Instance};
+use crate::gps::operation::Operation;
+use crate::gps::problem::Problem;
+
+pub fn operations() -> Vec<Operation> {
+    vec![
+        op(
+            "climb-on-chair",
+            &["chair-at-middle-room", "at-middle-room", "on-floor"],
+            &["at-bananas", "on-chair"],
+            &["at-middle-room", "on-floor"],
+        ),
+        op(
+            "push-chair-from-door-to-middle-room",
+            &["chair-at-door", "at-door"],
+            &["chair-at-middle-room", "at-middle-room"],
+            &["chair-at-door", "at-door"],
+        ),
+        op(
+            "walk-from-door-to-middle-room",
+            &["at-door", "on-floor"],
+            &["at-middle-room"],
+            &["at-door"],
+        ),
+        op(
+            "grasp-bananas",
+            &["at-bananas", "empty-handed"],
+            &["has-bananas"],
+            &["empty-handed"],
+        ),
+        op("drop-ball", &["has-ball"], &["empty-handed"], &["has-ball"]),
+        op(
+            "eat-bananas",
+            &["has-bananas"],
+            &["empty-handed", "not-hungry"],
+            &["has-bananas", "hungry"],
+        ),
+    ]
+}
+
+/// A hungry monkey holding a ball, with the chair at the door.
+pub fn hungry_monkey() -> Problem {
+    problem(
+        operations(),
+        &["at-door", "on-floor", "has-ball", "hungry", "chair-at-door"],
+        &["not-hungry"],
+    )
+}
+
+pub fn instances() -> Vec<Instance> {
+    vec![Instance::new("monkey/hungry", hungry_monkey(), Some(5))]
+}
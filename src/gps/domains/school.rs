@@ -0,0 +1,99 @@
+//! Driving a son to school, the first example of the book.
+
+use super::{op, problem, Instance};
+use crate::gps::operation::Operation;
+use crate::gps::problem::Problem;
+
+pub fn operations() -> Vec<Operation> {
+    vec![
+        op(
+            "drive-son-to-school",
+            &["son-at-home", "car-works"],
+            &["son-at-school"],
+            &["son-at-home"],
+        ),
+        op(
+            "shop-installs-battery",
+            &["car-needs-battery", "shop-knows-problem", "shop-has-money"],
+            &["car-works"],
+            &[],
+        ),
+        op(
+            "tell-shop-problem",
+            &["in-communication-with-shop"],
+            &["shop-knows-problem"],
+            &[],
+        ),
+        op(
+            "telephone-shop",
+            &["know-phone-number"],
+            &["in-communication-with-shop"],
+            &[],
+        ),
+        op(
+            "look-up-number",
+            &["have-phone-book"],
+            &["know-phone-number"],
+            &[],
+        ),
+        op(
+            "give-shop-money",
+            &["have-money"],
+            &["shop-has-money"],
+            &["have-money"],
+        ),
+    ]
+}
+
+/// Getting the son to school when the car needs a new battery.
+pub fn problem_with_battery() -> Problem {
+    problem(
+        operations(),
+        &[
+            "son-at-home",
+            "car-needs-battery",
+            "have-money",
+            "have-phone-book",
+        ],
+        &["son-at-school"],
+    )
+}
+
+pub fn instances() -> Vec<Instance> {
+    vec![
+        Instance::new("school/battery", problem_with_battery(), Some(6)),
+        Instance::new(
+            "school/no-phone-book",
+            problem(
+                operations(),
+                &["son-at-home", "car-needs-battery", "have-money"],
+                &["son-at-school"],
+            ),
+            None,
+        ),
+        Instance::new(
+            "school/car-works",
+            problem(
+                operations(),
+                &["son-at-home", "car-works"],
+                &["son-at-school"],
+            ),
+            Some(1),
+        ),
+        // Paying the shop spends the money, which is a goal as well.
+        Instance::new(
+            "school/keep-money",
+            problem(
+                operations(),
+                &[
+                    "son-at-home",
+                    "car-needs-battery",
+                    "have-money",
+                    "have-phone-book",
+                ],
+                &["have-money", "son-at-school"],
+            ),
+            None,
+        ),
+    ]
+}
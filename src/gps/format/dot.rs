@@ -0,0 +1,387 @@
+//! Graphviz diagrams of operations and plans.
+//!
+//! [`write_dependency_graph`] draws states as ellipses and operations as
+//! boxes, with an edge from each state to the operations requiring it and from
+//! each operation to the states it adds, removes or modifies. Rules are drawn
+//! as diamonds between the states of their bodies and the states they derive.
+//! Conditions that do not hold initially and that no operation or rule can
+//! achieve are filled in red.
+//! [`write_plan`] draws the steps of a plan with their causal links, and
+//! [`write_search_tree`] the goals and operations tried by a search.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+use crate::gps::axiom::Rule;
+use crate::gps::condition::{Condition, ConditionImpl};
+use crate::gps::index::AchieverIndex;
+use crate::gps::operation::Operation;
+use crate::gps::plan::{causal_links, Step};
+use crate::gps::problem::Problem;
+use crate::gps::state::StateSet;
+use crate::gps::symbol::Symbol;
+use crate::gps::tree::{NodeKind, SearchTree};
+
+use super::text;
+
+/// Draw which operations need, add, remove and modify which states.
+pub fn write_dependency_graph(problem: &Problem) -> String {
+    let index = AchieverIndex::new(problem.operations());
+    let mut states = problem.states().clone();
+    problem.axioms().derive(&mut states);
+    let missing: BTreeSet<Symbol> = problem
+        .operations()
+        .iter()
+        .flat_map(|operation| operation.prerequisites().iter())
+        .chain(problem.axioms().rules().iter().flat_map(Rule::body))
+        .chain(problem.goals().iter())
+        .filter(|condition| {
+            !is_achievable(problem, &index, &states, condition, &mut BTreeSet::new())
+        })
+        .map(|condition| condition.state_symbol())
+        .collect();
+    let goals: BTreeSet<Symbol> = problem
+        .goals()
+        .iter()
+        .map(|condition| condition.state_symbol())
+        .collect();
+
+    let mut state_names = BTreeSet::new();
+    for (name, _) in states.iter() {
+        state_names.insert(name);
+    }
+    state_names.extend(goals.iter().copied());
+    for rule in problem.axioms().rules() {
+        state_names.insert(rule.head());
+        state_names.extend(rule.body().iter().map(|c| c.state_symbol()));
+    }
+    for operation in problem.operations() {
+        state_names.extend(operation.prerequisites().iter().map(|c| c.state_symbol()));
+        state_names.extend(operation.add_states().iter().map(|s| s.symbol()));
+        state_names.extend(operation.remove_states().iter().copied());
+        state_names.extend(
+            operation
+                .modification_states()
+                .iter()
+                .map(|modification| modification.target_symbol()),
+        );
+    }
+    let mut state_names: Vec<_> = state_names.into_iter().collect();
+    state_names.sort_by_key(|name| name.as_str());
+    let ids: HashMap<Symbol, usize> = state_names
+        .iter()
+        .enumerate()
+        .map(|(id, name)| (*name, id))
+        .collect();
+    // Every state is collected above; any other gets a node of its own name.
+    let state_id = |name: Symbol| match ids.get(&name) {
+        Some(id) => format!("s{id}"),
+        None => quote(name.as_str()),
+    };
+
+    let mut out = String::new();
+    out.push_str("digraph dependencies {\n  rankdir=LR;\n");
+
+    for name in &state_names {
+        let initial = states.get_by_symbol(*name);
+        let label = match initial {
+            Some(data) => text::write_state(name.as_str(), data),
+            None => name.as_str().to_owned(),
+        };
+        let mut attributes = vec![format!("label={}", quote(&label))];
+        let mut styles = Vec::new();
+        if initial.is_some() {
+            attributes.push("peripheries=2".to_owned());
+        }
+        if goals.contains(name) {
+            styles.push("bold");
+        }
+        if missing.contains(name) {
+            styles.push("filled");
+            attributes.push("fillcolor=\"#f4cccc\", color=red".to_owned());
+        }
+        if !styles.is_empty() {
+            attributes.push(format!("style=\"{}\"", styles.join(",")));
+        }
+        writeln!(out, "  {} [{}];", state_id(*name), attributes.join(", ")).unwrap();
+    }
+
+    for (position, operation) in problem.operations().iter().enumerate() {
+        let id = format!("o{position}");
+        writeln!(
+            out,
+            "  {id} [shape=box, label={}];",
+            quote(operation.name())
+        )
+        .unwrap();
+
+        for condition in operation.prerequisites() {
+            write_requirement(
+                &mut out,
+                &state_id(condition.state_symbol()),
+                &id,
+                condition,
+            );
+        }
+        for state in operation.add_states() {
+            writeln!(
+                out,
+                "  {id} -> {} [color=darkgreen];",
+                state_id(state.symbol())
+            )
+            .unwrap();
+        }
+        for state_name in operation.remove_states() {
+            writeln!(
+                out,
+                "  {id} -> {} [color=red, style=dashed, arrowhead=tee];",
+                state_id(*state_name)
+            )
+            .unwrap();
+        }
+        for modification in operation.modification_states() {
+            writeln!(
+                out,
+                "  {id} -> {} [color=blue, style=dotted];",
+                state_id(modification.target_symbol())
+            )
+            .unwrap();
+        }
+    }
+
+    for (position, rule) in problem.axioms().rules().iter().enumerate() {
+        let id = format!("r{position}");
+        writeln!(out, "  {id} [shape=diamond, label=\"rule\"];").unwrap();
+        for condition in rule.body() {
+            write_requirement(
+                &mut out,
+                &state_id(condition.state_symbol()),
+                &id,
+                condition,
+            );
+        }
+        writeln!(
+            out,
+            "  {id} -> {} [color=darkgreen, style=dotted];",
+            state_id(rule.head())
+        )
+        .unwrap();
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+/// Draw an edge from a state to what requires the condition on it.
+fn write_requirement(out: &mut String, from: &str, to: &str, condition: &ConditionImpl) {
+    match condition {
+        ConditionImpl::Contain(_) => writeln!(out, "  {from} -> {to};"),
+        ConditionImpl::NotContain(_) => {
+            writeln!(out, "  {from} -> {to} [arrowhead=tee, label=\"not\"];")
+        }
+        ConditionImpl::Compare(_) => writeln!(
+            out,
+            "  {from} -> {to} [label={}];",
+            quote(&text::write_condition(condition))
+        ),
+    }
+    .unwrap();
+}
+
+/// Draw a plan of the problem as a chain of steps, with an edge labelled by
+/// the condition for every causal link.
+pub fn write_plan(problem: &Problem, plan: &[Operation]) -> String {
+    let step_id = |step: Step| match step {
+        Step::Initial => "initial".to_owned(),
+        Step::Operation(position) => format!("step{position}"),
+        Step::Goal => "goal".to_owned(),
+    };
+
+    let mut out = String::new();
+    out.push_str("digraph plan {\n  rankdir=LR;\n");
+    out.push_str("  initial [shape=circle, label=\"initial\"];\n");
+    for (position, operation) in plan.iter().enumerate() {
+        writeln!(
+            out,
+            "  step{position} [shape=box, label={}];",
+            quote(&format!("{}. {}", position + 1, operation.name()))
+        )
+        .unwrap();
+    }
+    out.push_str("  goal [shape=doublecircle, label=\"goal\"];\n");
+
+    // The order of execution, kept apart from the causal links.
+    let sequence: Vec<_> = std::iter::once(Step::Initial)
+        .chain((0..plan.len()).map(Step::Operation))
+        .chain(std::iter::once(Step::Goal))
+        .map(step_id)
+        .collect();
+    writeln!(out, "  {} [style=invis, weight=10];", sequence.join(" -> ")).unwrap();
+
+    for link in causal_links(problem.states(), plan, problem.goals(), problem.axioms()) {
+        writeln!(
+            out,
+            "  {} -> {} [label={}];",
+            step_id(link.producer()),
+            step_id(link.consumer()),
+            quote(&text::write_condition(link.condition()))
+        )
+        .unwrap();
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+/// Draw a search tree. Lists of goals, which need all their children, are
+/// drawn as diamonds; goals, which need any of their children, as ellipses;
+/// operations as boxes. Failed nodes are red and give the reason.
+pub fn write_search_tree(tree: &SearchTree) -> String {
+    let mut out = String::new();
+    out.push_str("digraph search {\n");
+
+    for (id, node) in tree.nodes() {
+        let shape = match node.kind() {
+            NodeKind::Goals(_) => "diamond",
+            NodeKind::Goal(_) => "ellipse",
+            NodeKind::Operation(_) => "box",
+        };
+        let color = if node.is_success() {
+            "darkgreen"
+        } else {
+            "red"
+        };
+        writeln!(
+            out,
+            "  n{} [shape={shape}, color={color}, label={}];",
+            id.index(),
+            quote(&format!("{}\n{}", node.kind(), node.status()))
+        )
+        .unwrap();
+        if let Some(parent) = node.parent() {
+            writeln!(out, "  n{} -> n{};", parent.index(), id.index()).unwrap();
+        }
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+/// Whether the condition holds initially or some operation achieves it, or
+/// for a derived state whether the body of one of its rules is achievable.
+fn is_achievable(
+    problem: &Problem,
+    index: &AchieverIndex,
+    states: &StateSet,
+    condition: &ConditionImpl,
+    visiting: &mut BTreeSet<Symbol>,
+) -> bool {
+    if condition.check(states) || has_achiever(index, condition) {
+        return true;
+    }
+    let state = condition.state_symbol();
+    let axioms = problem.axioms();
+    if !matches!(condition, ConditionImpl::Contain(_))
+        || !axioms.is_derived(state)
+        || !visiting.insert(state)
+    {
+        return false;
+    }
+    let achievable = axioms.rules_for(state).any(|rule| {
+        rule.body()
+            .iter()
+            .all(|condition| is_achievable(problem, index, states, condition, visiting))
+    });
+    visiting.remove(&state);
+    achievable
+}
+
+fn has_achiever(index: &AchieverIndex, condition: &ConditionImpl) -> bool {
+    let state_name = condition.state_symbol();
+    match condition {
+        ConditionImpl::Contain(_) => !index.adders(state_name).is_empty(),
+        ConditionImpl::NotContain(_) => !index.deleters(state_name).is_empty(),
+        ConditionImpl::Compare(_) => {
+            !index.modifiers(state_name).is_empty() || !index.adders(state_name).is_empty()
+        }
+    }
+}
+
+fn quote(text: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROBLEM: &str = "
+        state son-at-home
+        state car-needs-battery
+        operation drive-son-to-school {
+            require son-at-home
+            require car-works
+            add son-at-school
+            remove son-at-home
+        }
+        operation shop-installs-battery {
+            require car-needs-battery
+            require shop-has-money
+            add car-works
+        }
+        goal son-at-school";
+
+    #[test]
+    fn it_should_highlight_states_without_achievers() {
+        let problem = text::parse(PROBLEM).unwrap();
+        let graph = write_dependency_graph(&problem);
+
+        assert!(graph.contains(
+            "  s2 [label=\"shop-has-money\", fillcolor=\"#f4cccc\", color=red, style=\"filled\"];"
+        ));
+        assert!(graph.contains("  s3 [label=\"son-at-home\", peripheries=2];"));
+        assert!(graph.contains("  s4 [label=\"son-at-school\", style=\"bold\"];"));
+        assert!(graph.contains("  s1 [label=\"car-works\"];"));
+        assert!(graph.contains("  o1 -> s1 [color=darkgreen];"));
+        assert!(graph.contains("  o0 -> s3 [color=red, style=dashed, arrowhead=tee];"));
+
+        let problem = text::parse(
+            "operation drive { require car-works add at-school }
+             operation fix-tires { add tires-ok }
+             rule car-works { require have-spare-battery }
+             rule car-works { require tires-ok }
+             goal at-school",
+        );
+        let graph = write_dependency_graph(&problem.unwrap());
+        assert!(graph.contains("  s2 [label=\"have-spare-battery\", fillcolor"));
+        assert!(graph.contains("  s1 [label=\"car-works\"];"));
+        assert!(graph.contains("  r1 [shape=diamond, label=\"rule\"];"));
+        assert!(graph.contains("  r1 -> s1 [color=darkgreen, style=dotted];"));
+    }
+
+    #[test]
+    fn it_should_draw_the_causal_links_of_a_plan() {
+        let problem = text::parse(PROBLEM).unwrap();
+        let plan = &problem.operations()[..1];
+        let graph = write_plan(&problem, plan);
+
+        assert!(graph.contains("  step0 [shape=box, label=\"1. drive-son-to-school\"];"));
+        assert!(graph.contains("  initial -> step0 -> goal [style=invis, weight=10];"));
+        assert!(graph.contains("  initial -> step0 [label=\"son-at-home\"];"));
+        assert!(graph.contains("  step0 -> goal [label=\"son-at-school\"];"));
+        // `car-works` does not hold, so nothing provides it.
+        assert!(!graph.contains("car-works"));
+    }
+}
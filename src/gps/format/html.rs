@@ -0,0 +1,104 @@
+//! Self-contained HTML pages for browsing search trees.
+
+use std::fmt::Write;
+
+use crate::gps::tree::{NodeId, SearchTree};
+
+const STYLE: &str = "\
+body { font-family: sans-serif; }
+details, .leaf { margin-left: 1.5em; }
+summary { cursor: pointer; }
+.success { color: #1b5e20; }
+.failure { color: #b71c1c; }
+.status { font-style: italic; }";
+
+/// Write the tree as nested collapsible sections. Branches which failed are
+/// collapsed, so the path to the solution is what shows at first.
+pub fn write_search_tree(title: &str, tree: &SearchTree) -> String {
+    let mut out = String::new();
+    writeln!(out, "<!DOCTYPE html>").unwrap();
+    writeln!(out, "<html>\n<head>\n<meta charset=\"utf-8\">").unwrap();
+    writeln!(out, "<title>{}</title>", escape(title)).unwrap();
+    writeln!(out, "<style>\n{STYLE}\n</style>\n</head>\n<body>").unwrap();
+    writeln!(out, "<h1>{}</h1>", escape(title)).unwrap();
+    if let Some(root) = tree.root() {
+        write_node(&mut out, tree, root);
+    }
+    writeln!(out, "</body>\n</html>").unwrap();
+    out
+}
+
+fn write_node(out: &mut String, tree: &SearchTree, id: NodeId) {
+    let node = tree.get(id);
+    let class = if node.is_success() {
+        "success"
+    } else {
+        "failure"
+    };
+    let label = format!(
+        "<span class=\"{class}\">{}</span> <span class=\"status\">({})</span>",
+        escape(&node.kind().to_string()),
+        escape(&node.status().to_string())
+    );
+    let indent = "  ".repeat(node.depth());
+
+    if node.children().is_empty() {
+        writeln!(out, "{indent}<div class=\"leaf\">{label}</div>").unwrap();
+        return;
+    }
+
+    let open = if node.is_success() { " open" } else { "" };
+    writeln!(out, "{indent}<details{open}>").unwrap();
+    writeln!(out, "{indent}<summary>{label}</summary>").unwrap();
+    for child in node.children() {
+        write_node(out, tree, *child);
+    }
+    writeln!(out, "{indent}</details>").unwrap();
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps::format::text;
+    use crate::gps::portfolio::CancellationToken;
+    use crate::gps::GeneralProblemSolver;
+
+    #[test]
+    fn it_should_collapse_failed_branches() {
+        let problem = text::parse(
+            "state poor
+             operation rob-bank { require armed add rich }
+             operation work { require poor add rich }
+             goal rich",
+        )
+        .unwrap();
+        let gps: GeneralProblemSolver = problem.into();
+        let (_, _, tree) = gps.search_tree(&CancellationToken::new());
+
+        let page = write_search_tree("rich & famous", &tree);
+        assert!(page.contains("<title>rich &amp; famous</title>"));
+        assert!(page
+            .contains("    <details>\n    <summary><span class=\"failure\">apply rob-bank</span>"));
+        assert!(page.contains(
+            "        <div class=\"leaf\"><span class=\"failure\">achieve armed</span> \
+             <span class=\"status\">(failed: no usable operation achieves the goal)</span></div>"
+        ));
+        assert!(
+            page.contains("    <details open>\n    <summary><span class=\"success\">apply work")
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::{paip, pddl, text, ParseError, Position, Span};
+use crate::gps::axiom::Axioms;
+use crate::gps::problem::Problem;
+use crate::gps::state::State;
+
+/// The formats problems can be read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Pddl,
+    Paip,
+    Text,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        error: ParseError,
+    },
+    UnknownFormat(PathBuf),
+    /// PDDL keeps the problem in a file of its own.
+    MissingProblem,
+}
+
+impl Format {
+    /// Tell the format by the extension of a file: `.pddl` for PDDL, `.lisp`
+    /// or `.lsp` for PAIP programs and `.gps` for the plain-text format.
+    pub fn of_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "pddl" => Some(Format::Pddl),
+            "lisp" | "lsp" => Some(Format::Paip),
+            "gps" => Some(Format::Text),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&str> for Format {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "pddl" => Ok(Format::Pddl),
+            "paip" => Ok(Format::Paip),
+            "text" => Ok(Format::Text),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            LoadError::Io { path, error } => write!(f, "cannot read `{}`: {error}", path.display()),
+            LoadError::Parse { path, error } => write!(f, "{}:{error}", path.display()),
+            LoadError::UnknownFormat(path) => {
+                write!(f, "cannot tell the format of `{}`", path.display())
+            }
+            LoadError::MissingProblem => f.write_str("PDDL needs a problem file as well"),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+/// Read a domain and optionally a problem from files. Unless given, the
+/// format is told by the extension of the domain. For formats other than
+/// PDDL the problem adds its operations, states, goals and the rest to the
+/// domain.
+pub fn load(
+    domain_path: &Path,
+    problem_path: Option<&Path>,
+    format: Option<Format>,
+) -> Result<Problem, LoadError> {
+    let format = format
+        .or_else(|| Format::of_path(domain_path))
+        .ok_or_else(|| LoadError::UnknownFormat(domain_path.to_owned()))?;
+
+    let read = |path: &Path| {
+        fs::read_to_string(path).map_err(|error| LoadError::Io {
+            path: path.to_owned(),
+            error,
+        })
+    };
+    let located = |path: &Path| {
+        let path = path.to_owned();
+        move |error| LoadError::Parse { path, error }
+    };
+
+    let domain_source = read(domain_path)?;
+    let Some(problem_path) = problem_path else {
+        return match format {
+            Format::Pddl => Err(LoadError::MissingProblem),
+            Format::Paip => paip::read_problem(&domain_source).map_err(located(domain_path)),
+            Format::Text => text::parse(&domain_source).map_err(located(domain_path)),
+        };
+    };
+    let problem_source = read(problem_path)?;
+
+    match format {
+        Format::Pddl => {
+            let domain = pddl::parse_domain(&domain_source).map_err(located(domain_path))?;
+            let problem =
+                pddl::parse_problem(&problem_source, &domain).map_err(located(problem_path))?;
+            pddl::ground(&domain, &problem).map_err(|error| match error {
+                pddl::GroundError::Domain(error) => located(domain_path)(error),
+                pddl::GroundError::Problem(error) => located(problem_path)(error),
+            })
+        }
+        Format::Paip => {
+            // The problem may refer to operators defined by the domain, so
+            // both are read as one program.
+            let separator = if domain_source.ends_with('\n') {
+                ""
+            } else {
+                "\n"
+            };
+            let domain_lines = domain_source.lines().count();
+            let source = format!("{domain_source}{separator}{problem_source}");
+
+            paip::read_problem(&source).map_err(|error| {
+                if error.line() <= domain_lines {
+                    return located(domain_path)(error);
+                }
+                let shift = |position: Position| Position {
+                    line: position.line - domain_lines,
+                    offset: position
+                        .offset
+                        .saturating_sub(domain_source.len() + separator.len()),
+                    ..position
+                };
+                let span = Span::new(shift(error.span().start), shift(error.span().end));
+                located(problem_path)(ParseError::new(span, error.message()))
+            })
+        }
+        Format::Text => {
+            let domain = text::parse(&domain_source).map_err(located(domain_path))?;
+            let problem = text::parse(&problem_source).map_err(located(problem_path))?;
+
+            let mut invariants = domain.invariants().clone();
+            invariants.extend(problem.invariants().iter().cloned());
+            let mut bounds = domain.resource_bounds().clone();
+            bounds.extend(problem.resource_bounds().iter().cloned());
+            let mut soft_goals = domain.soft_goals().clone();
+            soft_goals.extend(problem.soft_goals().iter().cloned());
+            let mut constraints = domain.constraints().clone();
+            constraints.extend(problem.constraints().iter().cloned());
+            let mut rules = domain.axioms().rules().to_vec();
+            rules.extend(problem.axioms().rules().iter().cloned());
+            let (mut operations, mut states, mut goals) = domain.into_inner();
+            let (more_operations, more_states, more_goals) = problem.into_inner();
+            operations.extend(more_operations);
+            for (name, data) in more_states.iter() {
+                states.insert(State::with_symbol(name, data.clone()));
+            }
+            for name in more_states.unknown() {
+                states.insert_unknown(name);
+            }
+            goals.extend(more_goals);
+            // Both files are fine on their own, so the error comes from what
+            // the problem adds to the domain.
+            let axioms = Axioms::new(rules)
+                .and_then(|axioms| axioms.check(&operations).map(|()| axioms))
+                .map_err(|error| {
+                    let start = Position::new();
+                    located(problem_path)(ParseError::new(
+                        Span::new(start, start),
+                        error.to_string(),
+                    ))
+                })?;
+            // Constraints of the problem may name operations of the domain.
+            let constraints = constraints
+                .into_iter()
+                .map(|constraint| constraint.resolve(&operations))
+                .collect();
+            Ok(Problem::new(operations, states, goals)
+                .with_invariants(invariants)
+                .with_resource_bounds(bounds)
+                .with_constraints(constraints)
+                .with_axioms(axioms)
+                .with_soft_goals(soft_goals))
+        }
+    }
+}
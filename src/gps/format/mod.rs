@@ -0,0 +1,131 @@
+pub mod dot;
+pub mod html;
+mod load;
+pub mod paip;
+pub mod pddl;
+pub mod sexpr;
+pub mod text;
+
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+pub use load::{load, Format, LoadError};
+
+/// A location in a source text. Lines and columns start from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The part of a source text between two positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    span: Span,
+    message: String,
+}
+
+/// Raised when something cannot be expressed in the target format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteError {
+    message: String,
+}
+
+impl Position {
+    pub fn new() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// Move past the given character.
+    pub fn advance(&mut self, c: char) {
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+}
+
+/// The start of a source text.
+impl Default for Position {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+
+    /// The smallest span covering both spans.
+    pub fn to(self, other: Span) -> Self {
+        Self::new(self.start, other.end)
+    }
+}
+
+impl ParseError {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn line(&self) -> usize {
+        self.span.start.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.span.start.column
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}:{}: {}", self.line(), self.column(), self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+impl WriteError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for WriteError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for WriteError {}
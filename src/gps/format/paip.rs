@@ -0,0 +1,539 @@
+//! Reading and writing operators in the s-expression format of PAIP's GPS.
+//!
+//! Operators are written as
+//! `(make-op :action drive-son-to-school :preconds (son-at-home car-works)
+//! :add-list (son-at-school) :del-list (son-at-home))`, either as top-level
+//! forms or inside `(defparameter *school-ops* (list ...))`. Quoted values such
+//! as `'(son-at-home)` are accepted as well, so the examples of the book can be
+//! loaded verbatim. Nested atoms like `(at robot room)` become the state named
+//! `at robot room`, and names are read in lowercase, so names with uppercase
+//! letters, parentheses, quotes, `;` or `|` cannot be written.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::sexpr::{self, SExpr};
+use super::{ParseError, Span, WriteError};
+use crate::gps::condition::{Condition, ConditionImpl, Contain};
+use crate::gps::operation::{Operation, OperationBuilder};
+use crate::gps::problem::Problem;
+use crate::gps::state::{State, StateData, StateSet};
+
+/// The result of evaluating a form of a PAIP program.
+#[derive(Debug, Clone)]
+enum Value {
+    Data(SExpr),
+    Operation(Operation),
+    List(Vec<Value>, Span),
+}
+
+/// Variables defined by `defparameter` and the operators chosen by `use`.
+#[derive(Debug, Default)]
+struct Environment {
+    variables: HashMap<String, Value>,
+    operations: Option<Vec<Operation>>,
+}
+
+/// Read every operator defined in the source, whether at the top level or
+/// bound to a variable.
+pub fn read_operations(source: &str) -> Result<Vec<Operation>, ParseError> {
+    let mut environment = Environment::default();
+    let mut operations = Vec::new();
+
+    for expr in sexpr::parse(source)? {
+        let value = environment.evaluate_top_level(&expr)?;
+        collect_operations(&value, &mut operations);
+    }
+
+    Ok(operations)
+}
+
+/// Read a list of states such as `(son-at-home have-money)`.
+pub fn read_states(source: &str) -> Result<StateSet, ParseError> {
+    let mut states = StateSet::new();
+    for name in read_names(source)? {
+        states.insert(State::new_symbol(name));
+    }
+    Ok(states)
+}
+
+/// Read a list of goals such as `(son-at-school)`.
+pub fn read_goals(source: &str) -> Result<Vec<ConditionImpl>, ParseError> {
+    Ok(read_names(source)?
+        .into_iter()
+        .map(|name| Contain::new(name).into())
+        .collect())
+}
+
+/// Read a program defining operators and calling
+/// `(gps '(<state>...) '(<goal>...) <operators>)`. The operators may be
+/// omitted when they were chosen earlier by `(use <operators>)`.
+pub fn read_problem(source: &str) -> Result<Problem, ParseError> {
+    let mut environment = Environment::default();
+    let mut problem = None;
+
+    for expr in sexpr::parse(source)? {
+        if !expr.is_form("gps") {
+            environment.evaluate_top_level(&expr)?;
+            continue;
+        }
+
+        let arguments = &expr.as_list().unwrap_or_default()[1..];
+        let (states, goals, operations) = match arguments {
+            [states, goals] => {
+                let operations = environment.operations.clone().ok_or_else(|| {
+                    ParseError::new(expr.span(), "no operators were chosen by `use`")
+                })?;
+                (states, goals, operations)
+            }
+            [states, goals, operations] => {
+                let mut collected = Vec::new();
+                collect_operations(&environment.evaluate(operations)?, &mut collected);
+                (states, goals, collected)
+            }
+            _ => {
+                return Err(ParseError::new(
+                    expr.span(),
+                    "`gps` takes a state, goals and optionally operators",
+                ))
+            }
+        };
+
+        let mut state_set = StateSet::new();
+        for name in names(&environment.evaluate(states)?)? {
+            state_set.insert(State::new_symbol(name));
+        }
+        let goals = names(&environment.evaluate(goals)?)?
+            .into_iter()
+            .map(|name| Contain::new(name).into())
+            .collect();
+
+        if problem.is_some() {
+            return Err(ParseError::new(
+                expr.span(),
+                "only one call to `gps` is allowed",
+            ));
+        }
+        problem = Some(Problem::new(operations, state_set, goals));
+    }
+
+    problem.ok_or_else(|| ParseError::new(Span::default(), "expected a call to `gps`"))
+}
+
+/// Write an operator as a `make-op` form.
+pub fn write_operation(operation: &Operation) -> Result<String, WriteError> {
+    let cannot = |what: &str| {
+        Err(WriteError::new(format!(
+            "operation `{}` {what}, which PAIP operators cannot express",
+            operation.name()
+        )))
+    };
+
+    let mut preconds = Vec::new();
+    for condition in operation.prerequisites() {
+        match condition {
+            ConditionImpl::Contain(_) => preconds.push(write_name(condition.state_name())?),
+            _ => return cannot(&format!("requires `{}`", condition.name())),
+        }
+    }
+
+    let mut add_list = Vec::new();
+    for state in operation.add_states() {
+        match state.data() {
+            StateData::Symbol => add_list.push(write_name(state.name())?),
+            StateData::Integer(_) => return cannot(&format!("sets the number `{}`", state.name())),
+        }
+    }
+
+    if let Some(modification) = operation.modification_states().first() {
+        return cannot(&format!("modifies `{}`", modification.target_name()));
+    }
+
+    let del_list: Vec<_> = operation
+        .remove_states()
+        .iter()
+        .map(|state_name| write_name(state_name.as_str()))
+        .collect::<Result<_, _>>()?;
+
+    let indent = "\n         ";
+    Ok(format!(
+        "(make-op :action {}{indent}:preconds ({}){indent}:add-list ({}){indent}:del-list ({}))",
+        write_name(operation.name())?,
+        preconds.join(" "),
+        add_list.join(" "),
+        del_list.join(" ")
+    ))
+}
+
+/// Write operators as `make-op` forms, one after another.
+pub fn write_operations(operations: &[Operation]) -> Result<String, WriteError> {
+    let mut out = String::new();
+    for operation in operations {
+        writeln!(out, "{}\n", write_operation(operation)?).unwrap();
+    }
+    Ok(out)
+}
+
+/// Write states as a list sorted by name.
+pub fn write_states(states: &StateSet) -> Result<String, WriteError> {
+    if let Some(name) = states.unknown().next() {
+        return Err(WriteError::new(format!(
+            "the unknown state `{name}` cannot be expressed as a PAIP state"
+        )));
+    }
+    let mut names = Vec::new();
+    for (name, data) in states.iter() {
+        match data {
+            StateData::Symbol => names.push(write_name(name.as_str())?),
+            StateData::Integer(_) => {
+                return Err(WriteError::new(format!(
+                    "the number `{name}` cannot be expressed as a PAIP state"
+                )))
+            }
+        }
+    }
+    names.sort();
+    Ok(format!("({})", names.join(" ")))
+}
+
+/// Write goals as a list.
+pub fn write_goals(goals: &[ConditionImpl]) -> Result<String, WriteError> {
+    let mut names = Vec::new();
+    for goal in goals {
+        match goal {
+            ConditionImpl::Contain(_) => names.push(write_name(goal.state_name())?),
+            _ => {
+                return Err(WriteError::new(format!(
+                    "the goal `{}` cannot be expressed as a PAIP goal",
+                    goal.name()
+                )))
+            }
+        }
+    }
+    Ok(format!("({})", names.join(" ")))
+}
+
+/// Write a whole problem as a program that [`read_problem`] reads back.
+pub fn write_problem(problem: &Problem) -> Result<String, WriteError> {
+    let mut out = String::from("(defparameter *ops*\n  (list");
+    for operation in problem.operations() {
+        let form = write_operation(operation)?.replace('\n', "\n    ");
+        write!(out, "\n    {form}").unwrap();
+    }
+    out.push_str("))\n\n");
+
+    writeln!(
+        out,
+        "(gps '{}\n     '{}\n     *ops*)",
+        write_states(problem.states())?,
+        write_goals(problem.goals())?
+    )
+    .unwrap();
+    Ok(out)
+}
+
+/// Write a state name, turning `at robot room` back into `(at robot room)`.
+/// Names with characters the reader treats specially or lowercases cannot be
+/// read back.
+fn write_name(name: &str) -> Result<String, WriteError> {
+    if name.contains(|c: char| matches!(c, '(' | ')' | ';' | '\'' | '"' | '|') || c.is_uppercase())
+    {
+        return Err(WriteError::new(format!(
+            "the name `{name}` cannot be expressed in PAIP's s-expressions"
+        )));
+    }
+    if name.contains(char::is_whitespace) {
+        Ok(format!(
+            "({})",
+            name.split_whitespace().collect::<Vec<_>>().join(" ")
+        ))
+    } else {
+        Ok(name.to_owned())
+    }
+}
+
+fn read_names(source: &str) -> Result<Vec<String>, ParseError> {
+    let exprs = sexpr::parse(source)?;
+    match exprs.as_slice() {
+        [expr] => names(&Environment::default().evaluate(expr)?),
+        [] => Err(ParseError::new(Span::default(), "expected a list of names")),
+        [_, extra, ..] => Err(ParseError::new(extra.span(), "expected a single list")),
+    }
+}
+
+fn collect_operations(value: &Value, operations: &mut Vec<Operation>) {
+    match value {
+        Value::Operation(operation) => operations.push(operation.clone()),
+        Value::List(values, _) => values
+            .iter()
+            .for_each(|value| collect_operations(value, operations)),
+        Value::Data(_) => {}
+    }
+}
+
+/// Read a list of names, where each element is a symbol or a list of
+/// symbols.
+fn names(value: &Value) -> Result<Vec<String>, ParseError> {
+    let items: Vec<SExpr> = match value {
+        Value::Data(SExpr::List(items, _)) => items.clone(),
+        Value::Data(SExpr::Atom(atom, _)) if atom.eq_ignore_ascii_case("nil") => Vec::new(),
+        Value::List(values, span) => values
+            .iter()
+            .map(|value| match value {
+                Value::Data(expr) => Ok(expr.clone()),
+                _ => Err(ParseError::new(*span, "expected a list of names")),
+            })
+            .collect::<Result<_, _>>()?,
+        Value::Data(expr) => return Err(ParseError::new(expr.span(), "expected a list of names")),
+        Value::Operation(_) => {
+            return Err(ParseError::new(Span::default(), "expected a list of names"))
+        }
+    };
+
+    items.iter().map(name).collect()
+}
+
+fn name(expr: &SExpr) -> Result<String, ParseError> {
+    match expr {
+        SExpr::Atom(atom, _) => Ok(atom.to_ascii_lowercase()),
+        SExpr::List(items, span) => {
+            let parts = items
+                .iter()
+                .map(|item| {
+                    item.as_atom()
+                        .map(str::to_ascii_lowercase)
+                        .ok_or_else(|| ParseError::new(item.span(), "expected a symbol"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if parts.is_empty() {
+                return Err(ParseError::new(*span, "expected a name"));
+            }
+            Ok(parts.join(" "))
+        }
+    }
+}
+
+impl Environment {
+    /// Evaluate a top-level form, which may also define variables.
+    fn evaluate_top_level(&mut self, expr: &SExpr) -> Result<Value, ParseError> {
+        let list = expr.as_list().unwrap_or_default();
+
+        if expr.is_form("defparameter") || expr.is_form("defvar") || expr.is_form("setf") {
+            let [_, variable, value, ..] = list else {
+                return Err(ParseError::new(
+                    expr.span(),
+                    "expected a variable and a value",
+                ));
+            };
+            let variable = variable
+                .as_atom()
+                .ok_or_else(|| ParseError::new(variable.span(), "expected a variable"))?
+                .to_ascii_lowercase();
+            let value = self.evaluate(value)?;
+            self.variables.insert(variable, value.clone());
+            return Ok(value);
+        }
+
+        if expr.is_form("use") {
+            let [_, operations] = list else {
+                return Err(ParseError::new(expr.span(), "`use` takes the operators"));
+            };
+            let mut collected = Vec::new();
+            collect_operations(&self.evaluate(operations)?, &mut collected);
+            self.operations = Some(collected);
+            return Ok(Value::Data(expr.clone()));
+        }
+
+        self.evaluate(expr)
+    }
+
+    fn evaluate(&self, expr: &SExpr) -> Result<Value, ParseError> {
+        match expr {
+            SExpr::Atom(atom, span) if atom.starts_with('*') => self
+                .variables
+                .get(&atom.to_ascii_lowercase())
+                .cloned()
+                .ok_or_else(|| ParseError::new(*span, format!("unbound variable `{atom}`"))),
+            SExpr::Atom(..) => Ok(Value::Data(expr.clone())),
+            SExpr::List(items, span) => {
+                if expr.is_form("quote") {
+                    let [_, quoted] = items.as_slice() else {
+                        return Err(ParseError::new(*span, "`quote` takes one expression"));
+                    };
+                    Ok(Value::Data(quoted.clone()))
+                } else if expr.is_form("list") {
+                    let values = items[1..]
+                        .iter()
+                        .map(|item| self.evaluate(item))
+                        .collect::<Result<_, _>>()?;
+                    Ok(Value::List(values, *span))
+                } else if expr.is_form("make-op") || expr.is_form("op") {
+                    self.evaluate_operation(expr)
+                } else {
+                    // Unquoted lists of names are taken literally.
+                    Ok(Value::Data(expr.clone()))
+                }
+            }
+        }
+    }
+
+    /// Evaluate `(make-op :action <name> :preconds ... :add-list ...
+    /// :del-list ...)` or `(op <name> :preconds ...)`.
+    fn evaluate_operation(&self, expr: &SExpr) -> Result<Value, ParseError> {
+        let items = expr.as_list().unwrap_or_default();
+        let mut action = None;
+        let mut rest = &items[1..];
+
+        if expr.is_form("op") {
+            let Some((name, tail)) = rest.split_first() else {
+                return Err(ParseError::new(expr.span(), "`op` takes an action name"));
+            };
+            action = Some(self.evaluate(name)?);
+            rest = tail;
+        }
+
+        let mut preconds = Vec::new();
+        let mut add_list = Vec::new();
+        let mut del_list = Vec::new();
+
+        while let [key, value, tail @ ..] = rest {
+            rest = tail;
+            let keyword = key
+                .as_atom()
+                .ok_or_else(|| ParseError::new(key.span(), "expected a keyword"))?
+                .to_ascii_lowercase();
+            let value = self.evaluate(value)?;
+
+            match keyword.as_str() {
+                ":action" => action = Some(value),
+                ":preconds" => preconds = names(&value)?,
+                ":add-list" => add_list = names(&value)?,
+                ":del-list" => del_list = names(&value)?,
+                _ => {
+                    return Err(ParseError::new(
+                        key.span(),
+                        format!("unknown operator keyword `{keyword}`"),
+                    ))
+                }
+            }
+        }
+
+        if let [key] = rest {
+            return Err(ParseError::new(
+                key.span(),
+                "expected a value after the keyword",
+            ));
+        }
+
+        let action = match action {
+            Some(Value::Data(name_expr)) => name(&name_expr)?,
+            _ => return Err(ParseError::new(expr.span(), "the operator has no action")),
+        };
+
+        let mut builder = OperationBuilder::new(action);
+        for precond in preconds {
+            builder = builder.insert_prerequisite(Contain::new(precond).into());
+        }
+        for state in add_list {
+            builder = builder.insert_add_state(State::new_symbol(state));
+        }
+        for state in del_list {
+            builder = builder.insert_remove_state(state);
+        }
+        Ok(Value::Operation(builder.build()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps::{names, GeneralProblemSolver};
+
+    const SCHOOL: &str = "
+        ;; From chapter 4 of PAIP.
+        (defparameter *school-ops*
+          (list
+            (make-op :action 'drive-son-to-school
+              :preconds '(son-at-home car-works)
+              :add-list '(son-at-school)
+              :del-list '(son-at-home))
+            (make-op :action 'shop-installs-battery
+              :preconds '(car-needs-battery shop-knows-problem shop-has-money)
+              :add-list '(car-works))
+            (make-op :action 'tell-shop-problem
+              :preconds '(in-communication-with-shop)
+              :add-list '(shop-knows-problem))
+            (make-op :action 'telephone-shop
+              :preconds '(know-phone-number)
+              :add-list '(in-communication-with-shop))
+            (make-op :action 'look-up-number
+              :preconds '(have-phone-book)
+              :add-list '(know-phone-number))
+            (make-op :action 'give-shop-money
+              :preconds '(have-money)
+              :add-list '(shop-has-money)
+              :del-list '(have-money))))
+
+        (gps '(son-at-home car-needs-battery have-money have-phone-book)
+             '(son-at-school)
+             *school-ops*)";
+
+    #[test]
+    fn it_should_read_and_solve_the_school_example_from_the_book() {
+        let gps: GeneralProblemSolver = read_problem(SCHOOL).unwrap().into();
+        assert_eq!(
+            names(&gps.solve().unwrap()),
+            vec![
+                "look-up-number",
+                "telephone-shop",
+                "tell-shop-problem",
+                "give-shop-money",
+                "shop-installs-battery",
+                "drive-son-to-school",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_write_operators_that_read_back_the_same() {
+        let source = "(make-op :action move :preconds ((at robot a) clear) \
+                      :add-list ((at robot b)) :del-list ((at robot a)))";
+        let operations = read_operations(source).unwrap();
+        assert_eq!(operations[0].prerequisites()[0].name(), "at robot a");
+
+        let written = write_operations(&operations).unwrap();
+        assert_eq!(
+            written,
+            "(make-op :action move\n         :preconds ((at robot a) clear)\n         \
+             :add-list ((at robot b))\n         :del-list ((at robot a)))\n\n"
+        );
+        assert_eq!(
+            write_operations(&read_operations(&written).unwrap()).unwrap(),
+            written
+        );
+
+        let problem = read_problem(SCHOOL).unwrap();
+        let written = write_problem(&problem).unwrap();
+        assert_eq!(
+            write_problem(&read_problem(&written).unwrap()).unwrap(),
+            written
+        );
+
+        // Such names would be read back as other expressions.
+        let mut states = StateSet::new();
+        states.insert(State::new_symbol("a)b".to_owned()));
+        let problem = Problem::new(Vec::new(), states, Vec::new());
+        assert_eq!(
+            write_problem(&problem).unwrap_err().to_string(),
+            "the name `a)b` cannot be expressed in PAIP's s-expressions"
+        );
+        let go = OperationBuilder::new("go;now".to_owned()).build();
+        assert!(write_operation(&go).is_err());
+    }
+
+    #[test]
+    fn it_should_report_unknown_keywords_with_their_position() {
+        let error = read_operations("(make-op :action a\n  :precond (b))").unwrap_err();
+        assert_eq!((error.line(), error.column()), (2, 3));
+    }
+}
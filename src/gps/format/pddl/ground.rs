@@ -0,0 +1,499 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use super::{
+    ground_name, Action, ArithmeticOperator, Atom, DomainDefinition, Effect, Expression, Fact,
+    Literal, ModifyKind, ProblemDefinition, Term, TrajectoryConstraint, TypedName, COST_FLUENT,
+};
+use crate::gps::condition::{Compare, ConditionImpl, Contain, NotContain};
+use crate::gps::format::{ParseError, Span};
+use crate::gps::operation::{Modification, Operation, OperationBuilder};
+use crate::gps::problem::Problem;
+use crate::gps::state::{State, StateData, StateSet};
+use crate::gps::trajectory::{Constraint, Proposition};
+
+/// What is known about the problem while grounding it.
+struct Grounder<'a> {
+    domain: &'a DomainDefinition,
+    objects: Vec<&'a TypedName>,
+    /// Predicates and functions no action changes.
+    static_predicates: HashSet<&'a str>,
+    static_functions: HashSet<&'a str>,
+    facts: HashSet<String>,
+    values: HashMap<String, i32>,
+}
+
+/// A numeric expression after substituting everything known in advance.
+enum Reduced {
+    Value(i32),
+    /// A static fluent the initial state leaves undefined.
+    Undefined,
+    Fluent(String),
+}
+
+/// A literal after substituting everything known in advance.
+enum Grounded {
+    Condition(ConditionImpl),
+    Holds,
+    Never,
+}
+
+/// An error in the domain or in the problem being grounded, with a span in
+/// that file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroundError {
+    Domain(ParseError),
+    Problem(ParseError),
+}
+
+/// Instantiate the actions of the domain with the objects of the problem.
+/// Preconditions on predicates and functions no action changes are evaluated
+/// against the initial state right away, so impossible instances are dropped.
+pub fn ground(
+    domain: &DomainDefinition,
+    problem: &ProblemDefinition,
+) -> Result<Problem, GroundError> {
+    let changed_predicates: HashSet<&str> = domain
+        .actions
+        .iter()
+        .flat_map(|action| action.effects.iter())
+        .filter_map(|effect| match effect {
+            Effect::Add(atom) | Effect::Delete(atom) => Some(atom.predicate.as_str()),
+            Effect::Modify { .. } => None,
+        })
+        .collect();
+    let changed_functions: HashSet<&str> = domain
+        .actions
+        .iter()
+        .flat_map(|action| action.effects.iter())
+        .filter_map(|effect| match effect {
+            Effect::Modify { fluent, .. } => Some(fluent.predicate.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    let mut grounder = Grounder {
+        domain,
+        objects: domain.constants.iter().chain(&problem.objects).collect(),
+        static_predicates: domain
+            .predicates
+            .keys()
+            .map(String::as_str)
+            .filter(|p| !changed_predicates.contains(p))
+            .collect(),
+        static_functions: domain
+            .functions
+            .keys()
+            .map(String::as_str)
+            .filter(|f| !changed_functions.contains(f))
+            .collect(),
+        facts: HashSet::new(),
+        values: HashMap::new(),
+    };
+
+    let mut states = StateSet::new();
+    for fact in &problem.init {
+        match fact {
+            Fact::Atom(atom) => {
+                let name = grounder.atom_name(atom, &HashMap::new());
+                states.insert(State::new_symbol(name.clone()));
+                grounder.facts.insert(name);
+            }
+            Fact::Value(fluent, value) => {
+                let name = grounder.atom_name(fluent, &HashMap::new());
+                // Operations carry their costs, so nothing needs the total.
+                if name != COST_FLUENT {
+                    states.insert(State::new_integer(name.clone(), *value));
+                }
+                grounder.values.insert(name, *value);
+            }
+        }
+    }
+
+    let mut operations = Vec::new();
+    for action in &domain.actions {
+        grounder
+            .ground_action(action, &mut HashMap::new(), &mut operations)
+            .map_err(GroundError::Domain)?;
+    }
+
+    let mut goals = Vec::new();
+    for literal in &problem.goals {
+        let grounded = grounder.ground_literal(literal, &HashMap::new());
+        match grounded.map_err(GroundError::Problem)? {
+            Grounded::Condition(goal) => goals.push(goal),
+            Grounded::Holds => {}
+            Grounded::Never => goals.push(grounder.unreachable_goal(literal)),
+        }
+    }
+
+    let mut constraints = Vec::new();
+    for constraint in &problem.constraints {
+        let proposition =
+            |literal: &Literal| match grounder.ground_literal(literal, &HashMap::new())? {
+                Grounded::Condition(condition) => Ok(Proposition::Condition(condition)),
+                Grounded::Holds | Grounded::Never => Err(ParseError::new(
+                    literal_span(literal),
+                    "constraints must be about states which can change",
+                )),
+            };
+        let grounded = match constraint {
+            TrajectoryConstraint::Always(literal) => proposition(literal).map(Constraint::Always),
+            TrajectoryConstraint::Sometime(literal) => {
+                proposition(literal).map(Constraint::Sometime)
+            }
+            TrajectoryConstraint::AtMostOnce(literal) => {
+                proposition(literal).map(Constraint::AtMostOnce)
+            }
+            TrajectoryConstraint::SometimeBefore(first, second) => proposition(first)
+                .and_then(|first| Ok(Constraint::SometimeBefore(first, proposition(second)?))),
+        };
+        constraints.push(grounded.map_err(GroundError::Problem)?);
+    }
+
+    Ok(Problem::new(operations, states, goals).with_constraints(constraints))
+}
+
+impl Display for GroundError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            GroundError::Domain(error) | GroundError::Problem(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for GroundError {}
+
+/// Drops which file the error is in.
+impl From<GroundError> for ParseError {
+    fn from(value: GroundError) -> Self {
+        match value {
+            GroundError::Domain(error) | GroundError::Problem(error) => error,
+        }
+    }
+}
+
+fn literal_span(literal: &Literal) -> Span {
+    match literal {
+        Literal::Atom { atom, .. } => atom.span,
+        Literal::Equal { span, .. } | Literal::Compare { span, .. } => *span,
+    }
+}
+
+impl<'a> Grounder<'a> {
+    fn is_subtype(&self, type_name: &str, ancestor: &str) -> bool {
+        let mut current = type_name;
+        // Types may be declared in a cycle, so give up after visiting each
+        // of them once.
+        for _ in 0..=self.domain.types.len() {
+            if current == ancestor {
+                return true;
+            }
+            match self.domain.types.get(current) {
+                Some(parent) => current = parent,
+                None => return false,
+            }
+        }
+        false
+    }
+
+    fn resolve<'b>(&self, term: &'b Term, binding: &'b HashMap<&str, &'b str>) -> &'b str {
+        match term {
+            Term::Variable(variable) => binding[variable.as_str()],
+            Term::Object(object) => object,
+        }
+    }
+
+    fn atom_name(&self, atom: &Atom, binding: &HashMap<&str, &str>) -> String {
+        let arguments: Vec<_> = atom
+            .arguments
+            .iter()
+            .map(|term| self.resolve(term, binding))
+            .collect();
+        ground_name(&atom.predicate, &arguments)
+    }
+
+    /// Bind the parameters of the action one by one, pruning as soon as a
+    /// static precondition whose variables are all bound fails.
+    fn ground_action(
+        &self,
+        action: &'a Action,
+        binding: &mut HashMap<&'a str, &'a str>,
+        operations: &mut Vec<Operation>,
+    ) -> Result<(), ParseError> {
+        for literal in &action.preconditions {
+            if self.is_bound(literal, binding) && self.is_static(literal) {
+                if let Grounded::Never = self.ground_literal(literal, binding)? {
+                    return Ok(());
+                }
+            }
+        }
+
+        let Some(parameter) = action.parameters.get(binding.len()) else {
+            if let Some(operation) = self.build_operation(action, binding)? {
+                operations.push(operation);
+            }
+            return Ok(());
+        };
+
+        for object in &self.objects {
+            if self.is_subtype(&object.type_name, &parameter.type_name) {
+                binding.insert(&parameter.name, &object.name);
+                self.ground_action(action, binding, operations)?;
+                binding.remove(parameter.name.as_str());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn build_operation(
+        &self,
+        action: &Action,
+        binding: &HashMap<&str, &str>,
+    ) -> Result<Option<Operation>, ParseError> {
+        let arguments: Vec<_> = action
+            .parameters
+            .iter()
+            .map(|parameter| binding[parameter.name.as_str()])
+            .collect();
+        let mut builder = OperationBuilder::new(ground_name(&action.name, &arguments));
+
+        for literal in &action.preconditions {
+            match self.ground_literal(literal, binding)? {
+                Grounded::Condition(condition) => builder = builder.insert_prerequisite(condition),
+                Grounded::Holds => {}
+                Grounded::Never => return Ok(None),
+            }
+        }
+
+        let added: HashSet<String> = action
+            .effects
+            .iter()
+            .filter_map(|effect| match effect {
+                Effect::Add(atom) => Some(self.atom_name(atom, binding)),
+                _ => None,
+            })
+            .collect();
+
+        for effect in &action.effects {
+            match effect {
+                Effect::Add(atom) => {
+                    builder =
+                        builder.insert_add_state(State::new_symbol(self.atom_name(atom, binding)))
+                }
+                Effect::Delete(atom) => {
+                    // Adding wins over deleting the same atom in PDDL.
+                    let name = self.atom_name(atom, binding);
+                    if !added.contains(&name) {
+                        builder = builder.insert_remove_state(name);
+                    }
+                }
+                Effect::Modify {
+                    kind,
+                    fluent,
+                    value,
+                } => {
+                    let name = self.atom_name(fluent, binding);
+                    let value_span = value.span();
+                    let value = match self.reduce(value, binding)? {
+                        Reduced::Value(value) => value,
+                        // The effect is undefined, so the action cannot be applied.
+                        Reduced::Undefined => return Ok(None),
+                        Reduced::Fluent(_) => {
+                            return Err(ParseError::new(
+                                value.span(),
+                                "effects may only depend on fluents no action changes",
+                            ))
+                        }
+                    };
+                    // Action costs are kept as the cost of the operation
+                    // rather than as a state.
+                    if name == COST_FLUENT && matches!(kind, ModifyKind::Increase) {
+                        let cost = u32::try_from(value).map_err(|_| {
+                            ParseError::new(value_span, "action costs cannot be negative")
+                        })?;
+                        builder = builder.set_cost(cost);
+                        continue;
+                    }
+                    builder = builder.insert_modify_state(match kind {
+                        ModifyKind::Increase => Modification::increase(name, value),
+                        ModifyKind::Decrease => Modification::decrease(name, value),
+                        ModifyKind::Assign => Modification::assign(name, StateData::Integer(value)),
+                    });
+                }
+            }
+        }
+
+        Ok(Some(builder.build()))
+    }
+
+    /// A goal no operation achieves, standing for a goal over predicates and
+    /// functions no action changes which does not hold initially, so the
+    /// problem grounds to one without a plan.
+    fn unreachable_goal(&self, literal: &Literal) -> ConditionImpl {
+        match literal {
+            Literal::Atom { atom, negated } => {
+                let name = self.atom_name(atom, &HashMap::new());
+                if *negated {
+                    NotContain::new(name).into()
+                } else {
+                    Contain::new(name).into()
+                }
+            }
+            // Parentheses never appear in ground names.
+            _ => Contain::new("(unreachable goal)".to_owned()).into(),
+        }
+    }
+
+    fn is_bound(&self, literal: &Literal, binding: &HashMap<&str, &str>) -> bool {
+        let bound = |term: &Term| match term {
+            Term::Variable(variable) => binding.contains_key(variable.as_str()),
+            Term::Object(_) => true,
+        };
+
+        match literal {
+            Literal::Atom { atom, .. } => atom.arguments.iter().all(bound),
+            Literal::Equal { left, right, .. } => bound(left) && bound(right),
+            Literal::Compare { left, right, .. } => {
+                expression_terms(left).all(bound) && expression_terms(right).all(bound)
+            }
+        }
+    }
+
+    fn is_static(&self, literal: &Literal) -> bool {
+        match literal {
+            Literal::Atom { atom, .. } => self.static_predicates.contains(atom.predicate.as_str()),
+            Literal::Equal { .. } => true,
+            Literal::Compare { left, right, .. } => expression_fluents(left)
+                .chain(expression_fluents(right))
+                .all(|fluent| self.static_functions.contains(fluent.predicate.as_str())),
+        }
+    }
+
+    fn ground_literal(
+        &self,
+        literal: &Literal,
+        binding: &HashMap<&str, &str>,
+    ) -> Result<Grounded, ParseError> {
+        let known = |holds: bool| {
+            Ok(if holds {
+                Grounded::Holds
+            } else {
+                Grounded::Never
+            })
+        };
+
+        match literal {
+            Literal::Atom { atom, negated } => {
+                let name = self.atom_name(atom, binding);
+                if self.static_predicates.contains(atom.predicate.as_str()) {
+                    return known(self.facts.contains(&name) != *negated);
+                }
+                Ok(Grounded::Condition(if *negated {
+                    NotContain::new(name).into()
+                } else {
+                    Contain::new(name).into()
+                }))
+            }
+            Literal::Equal {
+                left,
+                right,
+                negated,
+                ..
+            } => known((self.resolve(left, binding) == self.resolve(right, binding)) != *negated),
+            Literal::Compare {
+                operator,
+                left,
+                right,
+                span,
+            } => {
+                let (operator, name, value) =
+                    match (self.reduce(left, binding)?, self.reduce(right, binding)?) {
+                        (Reduced::Undefined, _) | (_, Reduced::Undefined) => return known(false),
+                        (Reduced::Value(left), Reduced::Value(right)) => {
+                            return known(
+                                operator.evaluate(
+                                    &StateData::Integer(left),
+                                    &StateData::Integer(right),
+                                ),
+                            )
+                        }
+                        (Reduced::Fluent(name), Reduced::Value(value)) => {
+                            (operator.clone(), name, value)
+                        }
+                        (Reduced::Value(value), Reduced::Fluent(name)) => {
+                            (operator.flip(), name, value)
+                        }
+                        (Reduced::Fluent(_), Reduced::Fluent(_)) => {
+                            return Err(ParseError::new(
+                                *span,
+                                "comparing two fluents changed by actions is not supported",
+                            ))
+                        }
+                    };
+                let label = format!("{} {} {}", name, operator.as_str(), value);
+                Ok(Grounded::Condition(
+                    Compare::new(label, name, operator, StateData::Integer(value)).into(),
+                ))
+            }
+        }
+    }
+
+    fn reduce(
+        &self,
+        expression: &Expression,
+        binding: &HashMap<&str, &str>,
+    ) -> Result<Reduced, ParseError> {
+        match expression {
+            Expression::Number(value) => Ok(Reduced::Value(*value)),
+            Expression::Fluent(fluent) => {
+                let name = self.atom_name(fluent, binding);
+                if !self.static_functions.contains(fluent.predicate.as_str()) {
+                    return Ok(Reduced::Fluent(name));
+                }
+                Ok(match self.values.get(&name) {
+                    Some(value) => Reduced::Value(*value),
+                    None => Reduced::Undefined,
+                })
+            }
+            Expression::Binary(operator, left, right, span) => {
+                let (left, right) =
+                    match (self.reduce(left, binding)?, self.reduce(right, binding)?) {
+                        (Reduced::Value(left), Reduced::Value(right)) => (left, right),
+                        (Reduced::Fluent(_), _) | (_, Reduced::Fluent(_)) => {
+                            return Err(ParseError::new(
+                                *span,
+                                "arithmetic on fluents changed by actions is not supported",
+                            ))
+                        }
+                        _ => return Ok(Reduced::Undefined),
+                    };
+                let value = match operator {
+                    ArithmeticOperator::Add => left.checked_add(right),
+                    ArithmeticOperator::Subtract => left.checked_sub(right),
+                    ArithmeticOperator::Multiply => left.checked_mul(right),
+                    ArithmeticOperator::Divide => left.checked_div(right),
+                };
+                value.map(Reduced::Value).ok_or_else(|| {
+                    ParseError::new(*span, "the result of this expression is out of range")
+                })
+            }
+        }
+    }
+}
+
+fn expression_fluents(expression: &Expression) -> Box<dyn Iterator<Item = &Atom> + '_> {
+    match expression {
+        Expression::Number(_) => Box::new(std::iter::empty()),
+        Expression::Fluent(fluent) => Box::new(std::iter::once(fluent)),
+        Expression::Binary(_, left, right, _) => {
+            Box::new(expression_fluents(left).chain(expression_fluents(right)))
+        }
+    }
+}
+
+fn expression_terms(expression: &Expression) -> impl Iterator<Item = &Term> {
+    expression_fluents(expression).flat_map(|fluent| fluent.arguments.iter())
+}
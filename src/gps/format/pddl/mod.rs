@@ -0,0 +1,461 @@
+//! Reading and writing planning tasks in PDDL.
+//!
+//! The supported subset covers `:strips`, `:typing`, `:negative-preconditions`,
+//! `:equality` and `:numeric-fluents`, as well as the `always`, `sometime`,
+//! `at-most-once` and `sometime-before` constraints of PDDL3 on single
+//! literals. Actions are grounded over the objects of
+//! the problem, so each ground atom such as `(at truck depot)` becomes a state
+//! named `at truck depot` and each ground action becomes an [`Operation`]
+//! named after the action and its arguments.
+//!
+//! [`Operation`]: crate::gps::operation::Operation
+
+mod ground;
+mod parser;
+mod writer;
+
+use std::collections::HashMap;
+
+use super::{ParseError, Span};
+use crate::gps::condition::CompareOperator;
+use crate::gps::problem::Problem;
+
+pub use ground::{ground, GroundError};
+pub use parser::{parse_domain, parse_problem};
+pub use writer::{write_domain, write_plan, write_problem};
+
+const SUPPORTED_REQUIREMENTS: [&str; 8] = [
+    ":strips",
+    ":typing",
+    ":negative-preconditions",
+    ":equality",
+    ":numeric-fluents",
+    ":fluents",
+    ":action-costs",
+    ":constraints",
+];
+
+/// The fluent which actions increase by their cost under `:action-costs`.
+const COST_FLUENT: &str = "total-cost";
+
+/// A lifted PDDL domain.
+#[derive(Debug, Clone)]
+pub struct DomainDefinition {
+    name: String,
+    requirements: Vec<String>,
+    /// Every declared type mapped to its parent type.
+    types: HashMap<String, String>,
+    constants: Vec<TypedName>,
+    /// Every predicate mapped to its number of parameters.
+    predicates: HashMap<String, usize>,
+    /// Every function mapped to its number of parameters.
+    functions: HashMap<String, usize>,
+    actions: Vec<Action>,
+}
+
+/// A PDDL problem of some domain.
+#[derive(Debug, Clone)]
+pub struct ProblemDefinition {
+    name: String,
+    domain_name: String,
+    objects: Vec<TypedName>,
+    init: Vec<Fact>,
+    goals: Vec<Literal>,
+    constraints: Vec<TrajectoryConstraint>,
+}
+
+#[derive(Debug, Clone)]
+struct TypedName {
+    name: String,
+    type_name: String,
+    span: Span,
+}
+
+#[derive(Debug, Clone)]
+struct Action {
+    name: String,
+    parameters: Vec<TypedName>,
+    preconditions: Vec<Literal>,
+    effects: Vec<Effect>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Variable(String),
+    Object(String),
+}
+
+#[derive(Debug, Clone)]
+struct Atom {
+    predicate: String,
+    arguments: Vec<Term>,
+    span: Span,
+}
+
+#[derive(Debug, Clone)]
+enum Expression {
+    Number(i32),
+    Fluent(Atom),
+    Binary(ArithmeticOperator, Box<Expression>, Box<Expression>, Span),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ArithmeticOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Debug, Clone)]
+enum Literal {
+    Atom {
+        atom: Atom,
+        negated: bool,
+    },
+    Equal {
+        left: Term,
+        right: Term,
+        negated: bool,
+        span: Span,
+    },
+    Compare {
+        operator: CompareOperator,
+        left: Expression,
+        right: Expression,
+        span: Span,
+    },
+}
+
+/// A PDDL3 constraint over the states a plan goes through.
+#[derive(Debug, Clone)]
+enum TrajectoryConstraint {
+    Always(Literal),
+    Sometime(Literal),
+    AtMostOnce(Literal),
+    SometimeBefore(Literal, Box<Literal>),
+}
+
+#[derive(Debug, Clone)]
+enum Effect {
+    Add(Atom),
+    Delete(Atom),
+    Modify {
+        kind: ModifyKind,
+        fluent: Atom,
+        value: Expression,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ModifyKind {
+    Increase,
+    Decrease,
+    Assign,
+}
+
+#[derive(Debug, Clone)]
+enum Fact {
+    Atom(Atom),
+    Value(Atom, i32),
+}
+
+impl DomainDefinition {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn requirements(&self) -> &Vec<String> {
+        &self.requirements
+    }
+}
+
+impl ProblemDefinition {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn domain_name(&self) -> &str {
+        &self.domain_name
+    }
+}
+
+impl Expression {
+    fn span(&self) -> Span {
+        match self {
+            Expression::Number(_) => Span::default(),
+            Expression::Fluent(atom) => atom.span,
+            Expression::Binary(.., span) => *span,
+        }
+    }
+}
+
+/// Parse a domain and a problem and ground them into a [`Problem`].
+pub fn parse(domain: &str, problem: &str) -> Result<Problem, ParseError> {
+    let domain = parse_domain(domain)?;
+    let problem = parse_problem(problem, &domain)?;
+    Ok(ground(&domain, &problem)?)
+}
+
+/// The state name of a ground atom.
+fn ground_name(predicate: &str, arguments: &[&str]) -> String {
+    let mut name = predicate.to_owned();
+    for argument in arguments {
+        name.push(' ');
+        name.push_str(argument);
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps::condition::Condition;
+    use crate::gps::operation::{Modification, ModificationEffect, OperationBuilder};
+    use crate::gps::state::StateData;
+    use crate::gps::{names, GeneralProblemSolver};
+
+    const LOGISTICS_DOMAIN: &str = "
+        (define (domain logistics)
+          (:requirements :strips :typing :negative-preconditions :equality :numeric-fluents)
+          (:types truck location - object)
+          (:predicates (at ?t - truck ?l - location) (road ?from ?to - location))
+          (:functions (fuel ?t - truck) (distance ?from ?to - location) - number)
+          (:action drive
+            :parameters (?t - truck ?from ?to - location)
+            :precondition (and (at ?t ?from) (road ?from ?to) (not (= ?from ?to))
+                               (>= (fuel ?t) (distance ?from ?to)))
+            :effect (and (not (at ?t ?from)) (at ?t ?to)
+                         (decrease (fuel ?t) (distance ?from ?to)))))";
+
+    const LOGISTICS_PROBLEM: &str = "
+        (define (problem deliver)
+          (:domain logistics)
+          (:objects t1 - truck depot market port - location)
+          (:init (at t1 depot) (road depot market) (road market port)
+                 (= (fuel t1) 10)
+                 (= (distance depot market) 4) (= (distance market port) 5))
+          (:goal (and (at t1 port) (> (fuel t1) 0))))";
+
+    #[test]
+    fn it_should_ground_actions_over_typed_objects() {
+        let problem = parse(LOGISTICS_DOMAIN, LOGISTICS_PROBLEM).unwrap();
+
+        // Static road predicates prune every drive without a road.
+        assert_eq!(
+            names(problem.operations()),
+            vec!["drive t1 depot market", "drive t1 market port"]
+        );
+
+        let drive = &problem.operations()[0];
+        assert_eq!(drive.prerequisites().len(), 2);
+        assert_eq!(drive.prerequisites()[1].state_name(), "fuel t1");
+        let modification = &drive.modification_states()[0];
+        assert_eq!(modification.target_name(), "fuel t1");
+        assert!(matches!(
+            modification.effect(),
+            ModificationEffect::Decrease(4)
+        ));
+
+        assert_eq!(
+            problem.states().get("fuel t1"),
+            Some(&StateData::Integer(10))
+        );
+        assert!(problem.states().get("at t1 depot").is_some());
+    }
+
+    #[test]
+    fn it_should_solve_the_grounded_problem() {
+        let gps: GeneralProblemSolver = parse(LOGISTICS_DOMAIN, LOGISTICS_PROBLEM).unwrap().into();
+        assert_eq!(
+            names(&gps.solve().unwrap()),
+            vec!["drive t1 depot market", "drive t1 market port"]
+        );
+
+        // No action adds roads, so a missing one is a goal without a plan.
+        let problem = LOGISTICS_PROBLEM.replace("(at t1 port)", "(road port depot)");
+        let gps: GeneralProblemSolver = parse(LOGISTICS_DOMAIN, &problem).unwrap().into();
+        assert!(gps.solve().is_none());
+    }
+
+    #[test]
+    fn it_should_report_errors_with_line_and_column() {
+        let error = parse_domain(
+            "(define (domain d)\n  (:requirements :strips)\n  (:predicates (p))\n  \
+             (:action a :effect (q)))",
+        )
+        .unwrap_err();
+        assert_eq!((error.line(), error.column()), (4, 23));
+        assert_eq!(error.message(), "unknown predicate `q`");
+
+        let error = parse_domain("(define (domain d) (:requirements :adl))").unwrap_err();
+        assert_eq!((error.line(), error.column()), (1, 35));
+
+        // Grounding tells which file the error is in.
+        let domain = parse_domain(LOGISTICS_DOMAIN).unwrap();
+        let problem = parse_problem(
+            &LOGISTICS_PROBLEM.replace("(> (fuel t1) 0)", "(> (fuel t1) (fuel t1))"),
+            &domain,
+        )
+        .unwrap();
+        let Err(GroundError::Problem(error)) = ground(&domain, &problem) else {
+            panic!("expected an error in the problem");
+        };
+        assert_eq!(error.line(), 8);
+    }
+
+    #[test]
+    fn it_should_write_a_problem_that_reads_back_the_same() {
+        let problem = parse(LOGISTICS_DOMAIN, LOGISTICS_PROBLEM).unwrap();
+        let domain = write_domain("grounded", &problem).unwrap();
+        let written = write_problem("deliver", "grounded", &problem).unwrap();
+
+        let reread = parse(&domain, &written).unwrap();
+        assert_eq!(
+            write_domain("grounded", &reread).unwrap(),
+            domain,
+            "the written domain should be stable"
+        );
+        assert_eq!(reread.states(), problem.states());
+
+        let gps: GeneralProblemSolver = reread.into();
+        assert_eq!(
+            write_plan(&gps.solve().unwrap()),
+            "(drive_t1_depot_market)\n(drive_t1_market_port)\n; cost = 2 (unit cost)\n"
+        );
+    }
+
+    #[test]
+    fn it_should_read_and_write_trajectory_constraints() {
+        let problem = LOGISTICS_PROBLEM.replace(
+            "(:goal",
+            "(:constraints (and (always (>= (fuel t1) 2))
+                                (sometime-before (at t1 port) (at t1 market))))
+             (:goal",
+        );
+        let problem = parse(LOGISTICS_DOMAIN, &problem).unwrap();
+        let constraints: Vec<_> = problem
+            .constraints()
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        assert_eq!(
+            constraints,
+            vec![
+                "always \"fuel t1\" >= 2",
+                "sometime-before \"at t1 port\" \"at t1 market\""
+            ]
+        );
+
+        let domain = write_domain("grounded", &problem).unwrap();
+        let written = write_problem("deliver", "grounded", &problem).unwrap();
+        assert!(written.contains(
+            "(:constraints (and (always (>= (fuel t1) 2)) \
+             (sometime-before (at t1 port) (at t1 market))))"
+        ));
+        let reread = parse(&domain, &written).unwrap();
+        assert_eq!(reread.constraints(), problem.constraints());
+
+        let error = parse(
+            LOGISTICS_DOMAIN,
+            &LOGISTICS_PROBLEM.replace(
+                "(:goal",
+                "(:constraints (always (and (at t1 port) (at t1 depot)))) (:goal",
+            ),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.message(),
+            "constraints are supported on single literals only"
+        );
+
+        let error = parse(
+            LOGISTICS_DOMAIN,
+            &LOGISTICS_PROBLEM.replace("(:goal", "(:constraints (always (= t1 t1)))\n(:goal"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.message(),
+            "constraints must be about states which can change"
+        );
+        assert_eq!((error.line(), error.column()), (8, 33));
+    }
+
+    #[test]
+    fn it_should_keep_action_costs_as_operation_costs() {
+        let domain = "
+            (define (domain roads)
+              (:requirements :strips :action-costs)
+              (:predicates (at ?l) (road ?from ?to))
+              (:functions (length ?from ?to) (total-cost) - number)
+              (:action drive
+                :parameters (?from ?to)
+                :precondition (and (at ?from) (road ?from ?to))
+                :effect (and (not (at ?from)) (at ?to)
+                             (increase (total-cost) (length ?from ?to)))))";
+        let problem = "
+            (define (problem trip)
+              (:domain roads)
+              (:objects a b c)
+              (:init (at a) (road a b) (road b c) (= (length a b) 3) (= (length b c) 4)
+                     (= (total-cost) 0))
+              (:goal (at c))
+              (:metric minimize (total-cost)))";
+        let problem = parse(domain, problem).unwrap();
+        let costs: Vec<_> = problem.operations().iter().map(|o| o.cost()).collect();
+        assert_eq!(costs, vec![3, 4]);
+        assert!(problem.operations()[0].modification_states().is_empty());
+
+        let domain = write_domain("roads", &problem).unwrap();
+        assert!(domain.contains(":numeric-fluents :action-costs)"));
+        assert!(domain.contains("(increase (total-cost) 3)"));
+        let written = write_problem("trip", "roads", &problem).unwrap();
+        assert!(written.ends_with("(:metric minimize (total-cost)))\n"));
+
+        let reread = parse(&domain, &written).unwrap();
+        let gps: GeneralProblemSolver = reread.into();
+        assert_eq!(
+            write_plan(&gps.solve().unwrap()),
+            "(drive_a_b)\n(drive_b_c)\n; cost = 7\n"
+        );
+    }
+
+    #[test]
+    fn it_should_refuse_to_write_what_pddl_cannot_express() {
+        let problem = Problem::new(
+            vec![OperationBuilder::new("double".to_owned())
+                .insert_modify_state(Modification::new(
+                    "value".to_owned(),
+                    Box::new(|data| {
+                        if let StateData::Integer(x) = data {
+                            *x *= 2;
+                        }
+                    }),
+                ))
+                .build()],
+            Default::default(),
+            Vec::new(),
+        );
+
+        let error = write_domain("d", &problem).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "operation `double` modifies `value` in a way PDDL cannot express"
+        );
+
+        let problem = Problem::new(
+            vec![
+                OperationBuilder::new("drive t1 depot".to_owned()).build(),
+                OperationBuilder::new("drive_t1_depot".to_owned()).build(),
+            ],
+            Default::default(),
+            Vec::new(),
+        );
+        let error = write_domain("d", &problem).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "operations `drive t1 depot` and `drive_t1_depot` would both be written as the \
+             action `drive_t1_depot`"
+        );
+    }
+}
@@ -0,0 +1,740 @@
+use std::collections::HashMap;
+
+use super::{
+    Action, ArithmeticOperator, Atom, DomainDefinition, Effect, Expression, Fact, Literal,
+    ModifyKind, ProblemDefinition, Term, TrajectoryConstraint, TypedName, SUPPORTED_REQUIREMENTS,
+};
+use crate::gps::condition::CompareOperator;
+use crate::gps::format::sexpr::{self, SExpr};
+use crate::gps::format::{ParseError, Span};
+
+/// Names usable as arguments of atoms, besides the variables in scope.
+struct Scope<'a> {
+    predicates: &'a HashMap<String, usize>,
+    functions: &'a HashMap<String, usize>,
+    variables: &'a [TypedName],
+    objects: &'a [TypedName],
+}
+
+pub fn parse_domain(source: &str) -> Result<DomainDefinition, ParseError> {
+    let (name, sections) = parse_define(source, "domain")?;
+
+    let mut domain = DomainDefinition {
+        name,
+        requirements: Vec::new(),
+        types: HashMap::new(),
+        constants: Vec::new(),
+        predicates: HashMap::new(),
+        functions: HashMap::new(),
+        actions: Vec::new(),
+    };
+
+    for section in &sections {
+        let (keyword, items) = section_of(section)?;
+
+        match keyword.as_str() {
+            ":requirements" => domain.requirements = parse_requirements(items)?,
+            ":types" => {
+                let types = parse_typed_list(items)?;
+                for typed in &types {
+                    domain
+                        .types
+                        .insert(typed.name.clone(), typed.type_name.clone());
+                }
+                for typed in &types {
+                    check_type(&domain.types, &typed.type_name, typed.span)?;
+                }
+            }
+            ":constants" => {
+                domain.constants = parse_typed_list(items)?;
+                for constant in &domain.constants {
+                    check_type(&domain.types, &constant.type_name, constant.span)?;
+                }
+            }
+            ":predicates" => domain.predicates = parse_declarations(items, false)?,
+            ":functions" => domain.functions = parse_declarations(items, true)?,
+            ":action" => {
+                let action = parse_action(items, &domain, section.span())?;
+                domain.actions.push(action);
+            }
+            _ => {
+                return Err(ParseError::new(
+                    section.span(),
+                    format!("unsupported domain section `{keyword}`"),
+                ))
+            }
+        }
+    }
+
+    Ok(domain)
+}
+
+pub fn parse_problem(
+    source: &str,
+    domain: &DomainDefinition,
+) -> Result<ProblemDefinition, ParseError> {
+    let (name, sections) = parse_define(source, "problem")?;
+
+    let mut problem = ProblemDefinition {
+        name,
+        domain_name: String::new(),
+        objects: Vec::new(),
+        init: Vec::new(),
+        goals: Vec::new(),
+        constraints: Vec::new(),
+    };
+
+    for section in &sections {
+        let (keyword, items) = section_of(section)?;
+
+        match keyword.as_str() {
+            ":domain" => {
+                let [name] = items else {
+                    return Err(ParseError::new(section.span(), "expected a domain name"));
+                };
+                problem.domain_name = atom(name, "a domain name")?;
+                if problem.domain_name != domain.name {
+                    return Err(ParseError::new(
+                        name.span(),
+                        format!(
+                            "the problem is for domain `{}` but `{}` was given",
+                            problem.domain_name, domain.name
+                        ),
+                    ));
+                }
+            }
+            ":requirements" => {
+                parse_requirements(items)?;
+            }
+            ":objects" => {
+                problem.objects = parse_typed_list(items)?;
+                for object in &problem.objects {
+                    check_type(&domain.types, &object.type_name, object.span)?;
+                }
+            }
+            ":init" => {
+                let objects = all_objects(domain, &problem);
+                let scope = Scope {
+                    predicates: &domain.predicates,
+                    functions: &domain.functions,
+                    variables: &[],
+                    objects: &objects,
+                };
+                for item in items {
+                    problem.init.push(parse_fact(item, &scope)?);
+                }
+            }
+            ":goal" => {
+                let [goal] = items else {
+                    return Err(ParseError::new(section.span(), "expected one goal"));
+                };
+                let objects = all_objects(domain, &problem);
+                let scope = Scope {
+                    predicates: &domain.predicates,
+                    functions: &domain.functions,
+                    variables: &[],
+                    objects: &objects,
+                };
+                parse_condition(goal, &scope, false, &mut problem.goals)?;
+            }
+            ":constraints" => {
+                let [constraints] = items else {
+                    return Err(ParseError::new(section.span(), "expected one constraint"));
+                };
+                let objects = all_objects(domain, &problem);
+                let scope = Scope {
+                    predicates: &domain.predicates,
+                    functions: &domain.functions,
+                    variables: &[],
+                    objects: &objects,
+                };
+                parse_constraints(constraints, &scope, &mut problem.constraints)?;
+            }
+            // Plans are not optimized for a metric, but problems having one
+            // are still accepted.
+            ":metric" => {}
+            _ => {
+                return Err(ParseError::new(
+                    section.span(),
+                    format!("unsupported problem section `{keyword}`"),
+                ))
+            }
+        }
+    }
+
+    Ok(problem)
+}
+
+/// Read `(define (<kind> <name>) <section>...)` and return the name and the
+/// sections.
+fn parse_define(source: &str, kind: &str) -> Result<(String, Vec<SExpr>), ParseError> {
+    let exprs = sexpr::parse(source)?;
+
+    let define = match exprs.as_slice() {
+        [define] if define.is_form("define") => define,
+        [define] => return Err(ParseError::new(define.span(), "expected `(define ...)`")),
+        [] => return Err(ParseError::new(Span::default(), "expected `(define ...)`")),
+        [_, extra, ..] => {
+            return Err(ParseError::new(
+                extra.span(),
+                "unexpected expression after the definition",
+            ))
+        }
+    };
+
+    let list = define.as_list().unwrap_or_default();
+    let header = list
+        .get(1)
+        .filter(|header| header.is_form(kind))
+        .ok_or_else(|| ParseError::new(define.span(), format!("expected `({kind} <name>)`")))?;
+    let name = match header.as_list().unwrap_or_default() {
+        [_, name] => atom(name, "a name")?,
+        _ => {
+            return Err(ParseError::new(
+                header.span(),
+                format!("expected `({kind} <name>)`"),
+            ))
+        }
+    };
+
+    Ok((name, list[2..].to_vec()))
+}
+
+fn section_of(section: &SExpr) -> Result<(String, &[SExpr]), ParseError> {
+    match section.as_list() {
+        Some([keyword, items @ ..]) => Ok((atom(keyword, "a section keyword")?, items)),
+        _ => Err(ParseError::new(section.span(), "expected a section")),
+    }
+}
+
+/// Read an atom as a lowercase name, since PDDL is case-insensitive.
+fn atom(expr: &SExpr, expected: &str) -> Result<String, ParseError> {
+    expr.as_atom()
+        .map(str::to_ascii_lowercase)
+        .ok_or_else(|| ParseError::new(expr.span(), format!("expected {expected}")))
+}
+
+fn parse_requirements(items: &[SExpr]) -> Result<Vec<String>, ParseError> {
+    items
+        .iter()
+        .map(|item| {
+            let requirement = atom(item, "a requirement")?;
+            if SUPPORTED_REQUIREMENTS.contains(&requirement.as_str()) {
+                Ok(requirement)
+            } else {
+                Err(ParseError::new(
+                    item.span(),
+                    format!("unsupported requirement `{requirement}`"),
+                ))
+            }
+        })
+        .collect()
+}
+
+/// Read a list of names optionally followed by `- <type>`.
+fn parse_typed_list(items: &[SExpr]) -> Result<Vec<TypedName>, ParseError> {
+    let mut typed_names: Vec<TypedName> = Vec::new();
+    let mut untyped = 0;
+    let mut iter = items.iter();
+
+    while let Some(item) = iter.next() {
+        let name = atom(item, "a name")?;
+
+        if name == "-" {
+            let type_name = match iter.next() {
+                Some(t) if t.is_form("either") => {
+                    return Err(ParseError::new(
+                        t.span(),
+                        "`either` types are not supported",
+                    ))
+                }
+                Some(t) => atom(t, "a type")?,
+                None => return Err(ParseError::new(item.span(), "expected a type after `-`")),
+            };
+            for typed in &mut typed_names[untyped..] {
+                typed.type_name = type_name.clone();
+            }
+            untyped = typed_names.len();
+        } else {
+            typed_names.push(TypedName {
+                name,
+                type_name: "object".to_owned(),
+                span: item.span(),
+            });
+        }
+    }
+
+    Ok(typed_names)
+}
+
+fn check_type(
+    types: &HashMap<String, String>,
+    type_name: &str,
+    span: Span,
+) -> Result<(), ParseError> {
+    if type_name == "object" || types.contains_key(type_name) {
+        Ok(())
+    } else {
+        Err(ParseError::new(span, format!("unknown type `{type_name}`")))
+    }
+}
+
+/// Read predicate or function declarations into their arities.
+fn parse_declarations(
+    items: &[SExpr],
+    functions: bool,
+) -> Result<HashMap<String, usize>, ParseError> {
+    let mut declarations = HashMap::new();
+    let mut iter = items.iter();
+
+    while let Some(item) = iter.next() {
+        match item.as_list() {
+            Some([name, parameters @ ..]) => {
+                let name = atom(name, "a name")?;
+                declarations.insert(name, parse_typed_list(parameters)?.len());
+            }
+            // Functions may be followed by their result type.
+            None if functions && item.as_atom() == Some("-") => {
+                let result = iter
+                    .next()
+                    .ok_or_else(|| ParseError::new(item.span(), "expected a type after `-`"))?;
+                if atom(result, "a type")? != "number" {
+                    return Err(ParseError::new(
+                        result.span(),
+                        "only numeric functions are supported",
+                    ));
+                }
+            }
+            _ => return Err(ParseError::new(item.span(), "expected a declaration")),
+        }
+    }
+
+    Ok(declarations)
+}
+
+fn parse_action(
+    items: &[SExpr],
+    domain: &DomainDefinition,
+    span: Span,
+) -> Result<Action, ParseError> {
+    let Some((name, mut rest)) = items.split_first() else {
+        return Err(ParseError::new(span, "expected an action name"));
+    };
+
+    let mut action = Action {
+        name: atom(name, "an action name")?,
+        parameters: Vec::new(),
+        preconditions: Vec::new(),
+        effects: Vec::new(),
+    };
+
+    while let [key, value, tail @ ..] = rest {
+        rest = tail;
+
+        let scope = Scope {
+            predicates: &domain.predicates,
+            functions: &domain.functions,
+            variables: &action.parameters,
+            objects: &domain.constants,
+        };
+
+        match atom(key, "an action keyword")?.as_str() {
+            ":parameters" => {
+                let parameters = value
+                    .as_list()
+                    .ok_or_else(|| ParseError::new(value.span(), "expected a parameter list"))?;
+                let parameters = parse_typed_list(parameters)?;
+                for (position, parameter) in parameters.iter().enumerate() {
+                    if parameters[..position]
+                        .iter()
+                        .any(|p| p.name == parameter.name)
+                    {
+                        return Err(ParseError::new(
+                            parameter.span,
+                            format!("duplicate parameter `{}`", parameter.name),
+                        ));
+                    }
+                    if !parameter.name.starts_with('?') {
+                        return Err(ParseError::new(
+                            parameter.span,
+                            "parameters must start with `?`",
+                        ));
+                    }
+                    check_type(&domain.types, &parameter.type_name, parameter.span)?;
+                }
+                action.parameters = parameters;
+            }
+            ":precondition" => {
+                let mut preconditions = Vec::new();
+                parse_condition(value, &scope, false, &mut preconditions)?;
+                action.preconditions = preconditions;
+            }
+            ":effect" => {
+                let mut effects = Vec::new();
+                parse_effect(value, &scope, &mut effects)?;
+                action.effects = effects;
+            }
+            keyword => {
+                return Err(ParseError::new(
+                    key.span(),
+                    format!("unsupported action keyword `{keyword}`"),
+                ))
+            }
+        }
+    }
+
+    if let [key] = rest {
+        return Err(ParseError::new(
+            key.span(),
+            "expected a value after the keyword",
+        ));
+    }
+
+    Ok(action)
+}
+
+fn parse_condition(
+    expr: &SExpr,
+    scope: &Scope,
+    negated: bool,
+    literals: &mut Vec<Literal>,
+) -> Result<(), ParseError> {
+    let Some([head, arguments @ ..]) = expr.as_list() else {
+        // The empty conjunction `()` is a valid condition.
+        if expr.as_list().is_some_and(|list| list.is_empty()) {
+            return Ok(());
+        }
+        return Err(ParseError::new(expr.span(), "expected a condition"));
+    };
+    let head = atom(head, "a predicate")?;
+
+    match head.as_str() {
+        "and" if !negated => {
+            for argument in arguments {
+                parse_condition(argument, scope, false, literals)?;
+            }
+        }
+        "not" if !negated => {
+            let [inner] = arguments else {
+                return Err(ParseError::new(expr.span(), "`not` takes one condition"));
+            };
+            parse_condition(inner, scope, true, literals)?;
+        }
+        "=" if arguments.iter().all(is_term) => {
+            let [left, right] = arguments else {
+                return Err(ParseError::new(expr.span(), "`=` takes two arguments"));
+            };
+            literals.push(Literal::Equal {
+                left: parse_term(left, scope)?,
+                right: parse_term(right, scope)?,
+                negated,
+                span: expr.span(),
+            });
+        }
+        "<" | "<=" | "=" | ">=" | ">" => {
+            let [left, right] = arguments else {
+                return Err(ParseError::new(
+                    expr.span(),
+                    format!("`{head}` takes two arguments"),
+                ));
+            };
+            let operator = match head.as_str() {
+                "<" => CompareOperator::Less,
+                "<=" => CompareOperator::LessEqual,
+                "=" => CompareOperator::Equal,
+                ">=" => CompareOperator::GreaterEqual,
+                _ => CompareOperator::Greater,
+            };
+            literals.push(Literal::Compare {
+                operator: if negated { operator.negate() } else { operator },
+                left: parse_expression(left, scope)?,
+                right: parse_expression(right, scope)?,
+                span: expr.span(),
+            });
+        }
+        "and" | "not" | "or" | "imply" | "exists" | "forall" | "when" => {
+            return Err(ParseError::new(
+                expr.span(),
+                format!("`{head}` is not supported here"),
+            ))
+        }
+        _ => literals.push(Literal::Atom {
+            atom: parse_atom(expr, scope, scope.predicates, "predicate")?,
+            negated,
+        }),
+    }
+
+    Ok(())
+}
+
+/// Read a conjunction of PDDL3 constraints, each on a single literal.
+fn parse_constraints(
+    expr: &SExpr,
+    scope: &Scope,
+    constraints: &mut Vec<TrajectoryConstraint>,
+) -> Result<(), ParseError> {
+    let Some([head, arguments @ ..]) = expr.as_list() else {
+        return Err(ParseError::new(expr.span(), "expected a constraint"));
+    };
+    let head = atom(head, "a constraint")?;
+    let literal = |argument: &SExpr| {
+        let mut literals = Vec::new();
+        parse_condition(argument, scope, false, &mut literals)?;
+        match <[Literal; 1]>::try_from(literals) {
+            Ok([literal]) => Ok(literal),
+            Err(_) => Err(ParseError::new(
+                argument.span(),
+                "constraints are supported on single literals only",
+            )),
+        }
+    };
+
+    let constraint = match (head.as_str(), arguments) {
+        ("and", _) => {
+            for argument in arguments {
+                parse_constraints(argument, scope, constraints)?;
+            }
+            return Ok(());
+        }
+        ("always", [inner]) => TrajectoryConstraint::Always(literal(inner)?),
+        ("sometime", [inner]) => TrajectoryConstraint::Sometime(literal(inner)?),
+        ("at-most-once", [inner]) => TrajectoryConstraint::AtMostOnce(literal(inner)?),
+        ("sometime-before", [first, second]) => {
+            TrajectoryConstraint::SometimeBefore(literal(first)?, Box::new(literal(second)?))
+        }
+        ("always" | "sometime" | "at-most-once" | "sometime-before", _) => {
+            return Err(ParseError::new(
+                expr.span(),
+                format!("wrong number of arguments to `{head}`"),
+            ))
+        }
+        _ => {
+            return Err(ParseError::new(
+                expr.span(),
+                format!("unsupported constraint `{head}`"),
+            ))
+        }
+    };
+    constraints.push(constraint);
+    Ok(())
+}
+
+fn parse_effect(expr: &SExpr, scope: &Scope, effects: &mut Vec<Effect>) -> Result<(), ParseError> {
+    let Some([head, arguments @ ..]) = expr.as_list() else {
+        if expr.as_list().is_some_and(|list| list.is_empty()) {
+            return Ok(());
+        }
+        return Err(ParseError::new(expr.span(), "expected an effect"));
+    };
+    let head = atom(head, "a predicate")?;
+
+    match head.as_str() {
+        "and" => {
+            for argument in arguments {
+                parse_effect(argument, scope, effects)?;
+            }
+        }
+        "not" => {
+            let [inner] = arguments else {
+                return Err(ParseError::new(expr.span(), "`not` takes one atom"));
+            };
+            effects.push(Effect::Delete(parse_atom(
+                inner,
+                scope,
+                scope.predicates,
+                "predicate",
+            )?));
+        }
+        "increase" | "decrease" | "assign" => {
+            let [fluent, value] = arguments else {
+                return Err(ParseError::new(
+                    expr.span(),
+                    format!("`{head}` takes two arguments"),
+                ));
+            };
+            effects.push(Effect::Modify {
+                kind: match head.as_str() {
+                    "increase" => ModifyKind::Increase,
+                    "decrease" => ModifyKind::Decrease,
+                    _ => ModifyKind::Assign,
+                },
+                fluent: parse_atom(fluent, scope, scope.functions, "function")?,
+                value: parse_expression(value, scope)?,
+            });
+        }
+        "forall" | "when" | "scale-up" | "scale-down" => {
+            return Err(ParseError::new(
+                expr.span(),
+                format!("`{head}` is not supported here"),
+            ))
+        }
+        _ => effects.push(Effect::Add(parse_atom(
+            expr,
+            scope,
+            scope.predicates,
+            "predicate",
+        )?)),
+    }
+
+    Ok(())
+}
+
+fn parse_fact(expr: &SExpr, scope: &Scope) -> Result<Fact, ParseError> {
+    if expr.is_form("=") {
+        let Some([_, fluent, value]) = expr.as_list() else {
+            return Err(ParseError::new(expr.span(), "`=` takes two arguments"));
+        };
+        let fluent = parse_atom(fluent, scope, scope.functions, "function")?;
+        let value = parse_number(value)?;
+        Ok(Fact::Value(fluent, value))
+    } else {
+        Ok(Fact::Atom(parse_atom(
+            expr,
+            scope,
+            scope.predicates,
+            "predicate",
+        )?))
+    }
+}
+
+fn parse_atom(
+    expr: &SExpr,
+    scope: &Scope,
+    declarations: &HashMap<String, usize>,
+    kind: &str,
+) -> Result<Atom, ParseError> {
+    let Some([head, arguments @ ..]) = expr.as_list() else {
+        return Err(ParseError::new(expr.span(), format!("expected a {kind}")));
+    };
+    let predicate = atom(head, kind)?;
+
+    match declarations.get(&predicate) {
+        Some(arity) if *arity == arguments.len() => {}
+        Some(arity) => {
+            return Err(ParseError::new(
+                expr.span(),
+                format!(
+                    "{kind} `{predicate}` takes {arity} arguments but {} were given",
+                    arguments.len()
+                ),
+            ))
+        }
+        None => {
+            return Err(ParseError::new(
+                head.span(),
+                format!("unknown {kind} `{predicate}`"),
+            ))
+        }
+    }
+
+    Ok(Atom {
+        predicate,
+        arguments: arguments
+            .iter()
+            .map(|argument| parse_term(argument, scope))
+            .collect::<Result<_, _>>()?,
+        span: expr.span(),
+    })
+}
+
+fn is_term(expr: &SExpr) -> bool {
+    expr.as_atom()
+        .is_some_and(|atom| atom.parse::<f64>().is_err())
+}
+
+fn parse_term(expr: &SExpr, scope: &Scope) -> Result<Term, ParseError> {
+    let name = atom(expr, "a variable or an object")?;
+
+    if name.starts_with('?') {
+        if scope.variables.iter().any(|v| v.name == name) {
+            Ok(Term::Variable(name))
+        } else {
+            Err(ParseError::new(
+                expr.span(),
+                format!("unknown variable `{name}`"),
+            ))
+        }
+    } else if scope.objects.iter().any(|o| o.name == name) {
+        Ok(Term::Object(name))
+    } else {
+        Err(ParseError::new(
+            expr.span(),
+            format!("unknown object `{name}`"),
+        ))
+    }
+}
+
+fn parse_expression(expr: &SExpr, scope: &Scope) -> Result<Expression, ParseError> {
+    if expr.as_atom().is_some() {
+        return parse_number(expr).map(Expression::Number);
+    }
+
+    let operator = match expr.as_list() {
+        Some([head, ..]) => match head.as_atom() {
+            Some("+") => Some(ArithmeticOperator::Add),
+            Some("-") => Some(ArithmeticOperator::Subtract),
+            Some("*") => Some(ArithmeticOperator::Multiply),
+            Some("/") => Some(ArithmeticOperator::Divide),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    match (operator, expr.as_list().unwrap_or_default()) {
+        (Some(operator), [_, left, right]) => Ok(Expression::Binary(
+            operator,
+            Box::new(parse_expression(left, scope)?),
+            Box::new(parse_expression(right, scope)?),
+            expr.span(),
+        )),
+        // Negation is subtraction from zero.
+        (Some(ArithmeticOperator::Subtract), [_, operand]) => Ok(Expression::Binary(
+            ArithmeticOperator::Subtract,
+            Box::new(Expression::Number(0)),
+            Box::new(parse_expression(operand, scope)?),
+            expr.span(),
+        )),
+        (Some(_), _) => Err(ParseError::new(
+            expr.span(),
+            "arithmetic operators take two arguments",
+        )),
+        (None, _) => Ok(Expression::Fluent(parse_atom(
+            expr,
+            scope,
+            scope.functions,
+            "function",
+        )?)),
+    }
+}
+
+fn parse_number(expr: &SExpr) -> Result<i32, ParseError> {
+    let text = expr
+        .as_atom()
+        .ok_or_else(|| ParseError::new(expr.span(), "expected a number"))?;
+
+    if let Ok(value) = text.parse::<i32>() {
+        return Ok(value);
+    }
+
+    // Integral decimals such as `5.0` are accepted as well.
+    match text.parse::<f64>() {
+        Ok(value) if value.fract() == 0.0 && value.abs() <= i32::MAX as f64 => Ok(value as i32),
+        Ok(_) => Err(ParseError::new(
+            expr.span(),
+            format!("only integers are supported but `{text}` was given"),
+        )),
+        Err(_) => Err(ParseError::new(
+            expr.span(),
+            format!("expected a number but `{text}` was given"),
+        )),
+    }
+}
+
+fn all_objects(domain: &DomainDefinition, problem: &ProblemDefinition) -> Vec<TypedName> {
+    domain
+        .constants
+        .iter()
+        .chain(problem.objects.iter())
+        .cloned()
+        .collect()
+}
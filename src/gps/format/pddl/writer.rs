@@ -0,0 +1,453 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+
+use super::COST_FLUENT;
+use crate::gps::condition::{CompareOperator, Condition, ConditionImpl};
+use crate::gps::format::WriteError;
+use crate::gps::operation::{ModificationEffect, Operation};
+use crate::gps::plan;
+use crate::gps::problem::Problem;
+use crate::gps::state::StateData;
+use crate::gps::trajectory::Constraint;
+
+/// Whether a predicate of PDDL holds facts or numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Fact,
+    Number,
+}
+
+/// The predicates, functions and objects found in the state names of a
+/// problem. A state named `at truck depot` is the atom `(at truck depot)`.
+#[derive(Debug, Default)]
+struct Vocabulary {
+    predicates: BTreeMap<String, (usize, Kind)>,
+    objects: BTreeSet<String>,
+}
+
+/// Write the operations of the problem as a PDDL domain. Every object the
+/// operations refer to is declared as a constant of the domain.
+pub fn write_domain(name: &str, problem: &Problem) -> Result<String, WriteError> {
+    let vocabulary = Vocabulary::of(problem)?;
+    check_name(name)?;
+
+    let mut requirements = vec![":strips"];
+    let mut conditions = problem
+        .operations()
+        .iter()
+        .flat_map(|operation| operation.prerequisites().iter())
+        .chain(problem.goals().iter());
+    if conditions.any(|condition| match condition {
+        ConditionImpl::NotContain(_) => true,
+        ConditionImpl::Compare(compare) => *compare.operator() == CompareOperator::NotEqual,
+        ConditionImpl::Contain(_) => false,
+    }) {
+        requirements.push(":negative-preconditions");
+    }
+    if vocabulary
+        .predicates
+        .values()
+        .any(|(_, kind)| *kind == Kind::Number)
+    {
+        requirements.push(":numeric-fluents");
+    }
+    let costs = has_costs(problem);
+    if costs {
+        requirements.push(":action-costs");
+    }
+    if !problem.constraints().is_empty() {
+        requirements.push(":constraints");
+    }
+
+    let mut out = String::new();
+    writeln!(out, "(define (domain {name})").unwrap();
+    writeln!(out, "  (:requirements {})", requirements.join(" ")).unwrap();
+
+    if !vocabulary.objects.is_empty() {
+        let objects: Vec<_> = vocabulary.objects.iter().map(String::as_str).collect();
+        writeln!(out, "  (:constants {})", objects.join(" ")).unwrap();
+    }
+
+    for (section, kind) in [("predicates", Kind::Fact), ("functions", Kind::Number)] {
+        let declarations: Vec<_> = vocabulary
+            .predicates
+            .iter()
+            .filter(|(_, (_, k))| *k == kind)
+            .map(|(predicate, (arity, _))| {
+                let mut declaration = format!("({predicate}");
+                for i in 0..*arity {
+                    write!(declaration, " ?x{i}").unwrap();
+                }
+                declaration.push(')');
+                declaration
+            })
+            .chain(
+                (costs && kind == Kind::Number && !vocabulary.is_declared(COST_FLUENT))
+                    .then(|| format!("({COST_FLUENT})")),
+            )
+            .collect();
+        if !declarations.is_empty() {
+            writeln!(out, "  (:{section} {})", declarations.join(" ")).unwrap();
+        }
+    }
+
+    let mut actions = HashMap::new();
+    for operation in problem.operations() {
+        let action = action_name(operation);
+        if let Some(other) = actions.insert(action.clone(), operation.name()) {
+            return Err(WriteError::new(format!(
+                "operations `{other}` and `{}` would both be written as the action `{action}`",
+                operation.name()
+            )));
+        }
+        write_action(&mut out, operation, &vocabulary, costs)?;
+    }
+
+    out.push_str(")\n");
+    Ok(out)
+}
+
+/// Write the initial states and the goals of the problem as a PDDL problem of
+/// the domain written by [`write_domain`].
+pub fn write_problem(
+    name: &str,
+    domain_name: &str,
+    problem: &Problem,
+) -> Result<String, WriteError> {
+    let vocabulary = Vocabulary::of(problem)?;
+    check_name(name)?;
+    check_name(domain_name)?;
+    if let Some(state) = problem.states().unknown().next() {
+        return Err(WriteError::new(format!(
+            "the state `{state}` is unknown, which PDDL cannot express"
+        )));
+    }
+
+    let mut init: Vec<_> = problem
+        .states()
+        .iter()
+        .map(|(name, data)| match data {
+            StateData::Symbol => format!("({name})"),
+            StateData::Integer(value) => format!("(= ({name}) {value})"),
+        })
+        .collect();
+    let costs = has_costs(problem);
+    if costs && problem.states().get(COST_FLUENT).is_none() {
+        init.push(format!("(= ({COST_FLUENT}) 0)"));
+    }
+    init.sort();
+
+    let goals = problem
+        .goals()
+        .iter()
+        .map(|goal| write_condition(goal, &vocabulary))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let constraints = problem
+        .constraints()
+        .iter()
+        .map(|constraint| write_constraint(constraint, &vocabulary))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut out = String::new();
+    writeln!(out, "(define (problem {name})").unwrap();
+    writeln!(out, "  (:domain {domain_name})").unwrap();
+    writeln!(out, "  (:init").unwrap();
+    for fact in init {
+        writeln!(out, "    {fact}").unwrap();
+    }
+    writeln!(out, "  )").unwrap();
+    write!(out, "  (:goal (and {}))", goals.join(" ")).unwrap();
+    if !constraints.is_empty() {
+        write!(out, "\n  (:constraints (and {}))", constraints.join(" ")).unwrap();
+    }
+    if costs {
+        write!(out, "\n  (:metric minimize ({COST_FLUENT}))").unwrap();
+    }
+    out.push_str(")\n");
+    Ok(out)
+}
+
+/// Write a plan in the format of the International Planning Competition, one
+/// step per line. Steps keep the names of their operations, so a plan for
+/// operations grounded from PDDL refers to the actions of the original domain.
+pub fn write_plan(plan: &[Operation]) -> String {
+    let mut out = String::new();
+    for operation in plan {
+        writeln!(out, "({})", operation.name()).unwrap();
+    }
+    if plan.iter().all(|operation| operation.cost() == 1) {
+        writeln!(out, "; cost = {} (unit cost)", plan.len()).unwrap();
+    } else {
+        writeln!(out, "; cost = {}", plan::cost(plan)).unwrap();
+    }
+    out
+}
+
+/// Whether some operation costs other than 1, so costs must be written.
+fn has_costs(problem: &Problem) -> bool {
+    problem
+        .operations()
+        .iter()
+        .any(|operation| operation.cost() != 1)
+}
+
+fn write_action(
+    out: &mut String,
+    operation: &Operation,
+    vocabulary: &Vocabulary,
+    costs: bool,
+) -> Result<(), WriteError> {
+    let name = action_name(operation);
+    check_name(&name)?;
+
+    let preconditions = operation
+        .prerequisites()
+        .iter()
+        .map(|condition| write_condition(condition, vocabulary))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let removed: HashSet<_> = operation.remove_states().iter().collect();
+    let mut assigned = HashSet::new();
+    let mut effects = Vec::new();
+
+    for state in operation.add_states() {
+        match state.data() {
+            // Removing wins over adding the same state in an operation, while
+            // it is the other way around in PDDL.
+            StateData::Symbol if removed.contains(&state.symbol()) => {}
+            StateData::Symbol => effects.push(format!("({})", state.name())),
+            StateData::Integer(value) => {
+                assigned.insert(state.symbol());
+                effects.push(format!("(assign ({}) {value})", state.name()))
+            }
+        }
+    }
+
+    for state_name in operation.remove_states() {
+        if vocabulary.kind_of(state_name.as_str()) == Kind::Number {
+            return Err(WriteError::new(format!(
+                "operation `{}` removes the number `{state_name}`, which PDDL cannot express",
+                operation.name()
+            )));
+        }
+        effects.push(format!("(not ({state_name}))"));
+    }
+
+    for modification in operation.modification_states() {
+        let target = modification.target_name();
+        if assigned.contains(&modification.target_symbol()) {
+            return Err(WriteError::new(format!(
+                "operation `{}` both sets and modifies `{target}`",
+                operation.name()
+            )));
+        }
+        effects.push(match modification.effect() {
+            ModificationEffect::Increase(amount) => format!("(increase ({target}) {amount})"),
+            ModificationEffect::Decrease(amount) => format!("(decrease ({target}) {amount})"),
+            ModificationEffect::Assign(StateData::Integer(value)) => {
+                format!("(assign ({target}) {value})")
+            }
+            ModificationEffect::Assign(StateData::Symbol) | ModificationEffect::Custom(_) => {
+                return Err(WriteError::new(format!(
+                    "operation `{}` modifies `{target}` in a way PDDL cannot express",
+                    operation.name()
+                )))
+            }
+        });
+    }
+
+    if costs {
+        effects.push(format!("(increase ({COST_FLUENT}) {})", operation.cost()));
+    }
+
+    writeln!(out, "  (:action {name}").unwrap();
+    writeln!(out, "    :parameters ()").unwrap();
+    writeln!(out, "    :precondition (and {})", preconditions.join(" ")).unwrap();
+    writeln!(out, "    :effect (and {}))", effects.join(" ")).unwrap();
+    Ok(())
+}
+
+/// Ground operations cannot be written as actions with parameters, so their
+/// arguments become part of the action name.
+fn action_name(operation: &Operation) -> String {
+    operation
+        .name()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+fn write_condition(
+    condition: &ConditionImpl,
+    vocabulary: &Vocabulary,
+) -> Result<String, WriteError> {
+    let state_name = condition.state_name();
+
+    match condition {
+        ConditionImpl::Contain(_) | ConditionImpl::NotContain(_)
+            if vocabulary.kind_of(state_name) == Kind::Number =>
+        {
+            Err(WriteError::new(format!(
+                "testing whether the number `{state_name}` exists cannot be expressed in PDDL"
+            )))
+        }
+        ConditionImpl::Contain(_) => Ok(format!("({state_name})")),
+        ConditionImpl::NotContain(_) => Ok(format!("(not ({state_name}))")),
+        ConditionImpl::Compare(compare) => {
+            let StateData::Integer(value) = compare.value() else {
+                return Err(WriteError::new(format!(
+                    "condition `{}` compares `{state_name}` with a symbol, \
+                     which PDDL cannot express",
+                    condition.name()
+                )));
+            };
+            let operator = match compare.operator() {
+                CompareOperator::Equal | CompareOperator::NotEqual => "=",
+                operator => operator.as_str(),
+            };
+            let comparison = format!("({operator} ({state_name}) {value})");
+            if *compare.operator() == CompareOperator::NotEqual {
+                Ok(format!("(not {comparison})"))
+            } else {
+                Ok(comparison)
+            }
+        }
+    }
+}
+
+fn write_constraint(
+    constraint: &Constraint,
+    vocabulary: &Vocabulary,
+) -> Result<String, WriteError> {
+    let propositions = constraint
+        .propositions()
+        .into_iter()
+        .map(|proposition| match proposition.condition() {
+            Some(condition) => write_condition(condition, vocabulary),
+            None => Err(WriteError::new(format!(
+                "constraint `{constraint}` is about operations, which PDDL cannot express"
+            ))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let keyword = match constraint {
+        Constraint::Always(_) => "always",
+        Constraint::Sometime(_) => "sometime",
+        Constraint::AtMostOnce(_) => "at-most-once",
+        Constraint::SometimeBefore(..) => "sometime-before",
+    };
+    Ok(format!("({keyword} {})", propositions.join(" ")))
+}
+
+fn check_name(name: &str) -> Result<(), WriteError> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(WriteError::new(format!(
+            "`{name}` is not a valid PDDL name"
+        )))
+    }
+}
+
+impl Vocabulary {
+    fn of(problem: &Problem) -> Result<Self, WriteError> {
+        let mut vocabulary = Self::default();
+
+        for (name, data) in problem.states().iter() {
+            vocabulary.declare(name.as_str(), Kind::of(data))?;
+        }
+
+        let conditions = problem
+            .operations()
+            .iter()
+            .flat_map(|operation| operation.prerequisites().iter())
+            .chain(problem.goals().iter())
+            .chain(
+                problem
+                    .constraints()
+                    .iter()
+                    .flat_map(Constraint::propositions)
+                    .filter_map(|proposition| proposition.condition()),
+            );
+        for condition in conditions {
+            let kind = match condition {
+                ConditionImpl::Compare(_) => Kind::Number,
+                _ => Kind::Fact,
+            };
+            vocabulary.declare(condition.state_name(), kind)?;
+        }
+
+        for operation in problem.operations() {
+            for state in operation.add_states() {
+                vocabulary.declare(state.name(), Kind::of(state.data()))?;
+            }
+            for modification in operation.modification_states() {
+                vocabulary.declare(modification.target_name(), Kind::Number)?;
+            }
+        }
+
+        // Removed states are facts unless known to be numbers otherwise.
+        for operation in problem.operations() {
+            for state_name in operation.remove_states() {
+                if !vocabulary.is_declared(state_name.as_str()) {
+                    vocabulary.declare(state_name.as_str(), Kind::Fact)?;
+                }
+            }
+        }
+
+        Ok(vocabulary)
+    }
+
+    fn declare(&mut self, state_name: &str, kind: Kind) -> Result<(), WriteError> {
+        let mut parts = state_name.split_whitespace();
+        let predicate = parts.next().unwrap_or_default();
+        let arguments: Vec<_> = parts.collect();
+
+        check_name(predicate)?;
+        for argument in &arguments {
+            check_name(argument)?;
+            self.objects.insert((*argument).to_owned());
+        }
+
+        match self.predicates.get(predicate) {
+            Some((arity, _)) if *arity != arguments.len() => Err(WriteError::new(format!(
+                "`{predicate}` is used with {arity} and {} arguments",
+                arguments.len()
+            ))),
+            Some((_, declared)) if *declared != kind => Err(WriteError::new(format!(
+                "`{predicate}` is used both as a fact and as a number"
+            ))),
+            Some(_) => Ok(()),
+            None => {
+                self.predicates
+                    .insert(predicate.to_owned(), (arguments.len(), kind));
+                Ok(())
+            }
+        }
+    }
+
+    fn is_declared(&self, state_name: &str) -> bool {
+        let predicate = state_name.split_whitespace().next().unwrap_or_default();
+        self.predicates.contains_key(predicate)
+    }
+
+    fn kind_of(&self, state_name: &str) -> Kind {
+        let predicate = state_name.split_whitespace().next().unwrap_or_default();
+        self.predicates
+            .get(predicate)
+            .map_or(Kind::Fact, |(_, kind)| *kind)
+    }
+}
+
+impl Kind {
+    fn of(data: &StateData) -> Self {
+        match data {
+            StateData::Symbol => Kind::Fact,
+            StateData::Integer(_) => Kind::Number,
+        }
+    }
+}
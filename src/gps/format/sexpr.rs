@@ -0,0 +1,174 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use super::{ParseError, Position, Span};
+
+/// An s-expression read from a source text, remembering where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SExpr {
+    Atom(String, Span),
+    List(Vec<SExpr>, Span),
+}
+
+struct Reader<'a> {
+    chars: Peekable<Chars<'a>>,
+    position: Position,
+}
+
+impl SExpr {
+    pub fn span(&self) -> Span {
+        match self {
+            SExpr::Atom(_, span) | SExpr::List(_, span) => *span,
+        }
+    }
+
+    pub fn as_atom(&self) -> Option<&str> {
+        match self {
+            SExpr::Atom(atom, _) => Some(atom),
+            SExpr::List(..) => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[SExpr]> {
+        match self {
+            SExpr::Atom(..) => None,
+            SExpr::List(list, _) => Some(list),
+        }
+    }
+
+    /// Test if this is a list starting with the given atom, ignoring case.
+    pub fn is_form(&self, head: &str) -> bool {
+        self.as_list()
+            .and_then(|list| list.first())
+            .and_then(SExpr::as_atom)
+            .is_some_and(|atom| atom.eq_ignore_ascii_case(head))
+    }
+}
+
+/// Read all s-expressions in the source text. A quote `'x` is read as
+/// `(quote x)`, and `;` starts a comment running to the end of the line.
+pub fn parse(source: &str) -> Result<Vec<SExpr>, ParseError> {
+    let mut reader = Reader {
+        chars: source.chars().peekable(),
+        position: Position::new(),
+    };
+    let mut exprs = Vec::new();
+
+    loop {
+        reader.skip_whitespace();
+        if reader.chars.peek().is_none() {
+            return Ok(exprs);
+        }
+        exprs.push(reader.read()?);
+    }
+}
+
+impl Reader<'_> {
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.position.advance(c);
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            if c == ';' {
+                while self.chars.peek().is_some_and(|c| *c != '\n') {
+                    self.bump();
+                }
+            } else if c.is_whitespace() {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn read(&mut self) -> Result<SExpr, ParseError> {
+        let start = self.position;
+
+        match self.chars.peek() {
+            Some('(') => {
+                self.bump();
+                let mut list = Vec::new();
+
+                loop {
+                    self.skip_whitespace();
+                    match self.chars.peek() {
+                        Some(')') => {
+                            self.bump();
+                            return Ok(SExpr::List(list, Span::new(start, self.position)));
+                        }
+                        Some(_) => list.push(self.read()?),
+                        None => {
+                            return Err(ParseError::new(
+                                Span::new(start, self.position),
+                                "unclosed parenthesis",
+                            ))
+                        }
+                    }
+                }
+            }
+            Some(')') => {
+                self.bump();
+                Err(ParseError::new(
+                    Span::new(start, self.position),
+                    "unexpected closing parenthesis",
+                ))
+            }
+            Some('\'') => {
+                self.bump();
+                let quote = SExpr::Atom("quote".to_owned(), Span::new(start, self.position));
+                self.skip_whitespace();
+                if self.chars.peek().is_none() {
+                    return Err(ParseError::new(
+                        Span::new(start, self.position),
+                        "expected an expression after the quote",
+                    ));
+                }
+                let quoted = self.read()?;
+                Ok(SExpr::List(
+                    vec![quote, quoted],
+                    Span::new(start, self.position),
+                ))
+            }
+            _ => {
+                let mut atom = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | ';' | '\'') {
+                        break;
+                    }
+                    atom.push(c);
+                    self.bump();
+                }
+                Ok(SExpr::Atom(atom, Span::new(start, self.position)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_read_nested_lists_with_quotes_and_comments() {
+        let exprs = parse("; comment\n(a 'b\n  (c))").unwrap();
+        assert_eq!(exprs.len(), 1);
+
+        let list = exprs[0].as_list().unwrap();
+        assert_eq!(list[0].as_atom(), Some("a"));
+        assert!(list[1].is_form("quote"));
+        assert_eq!(list[2].span().start.line, 3);
+        assert_eq!(list[2].span().start.column, 3);
+    }
+
+    #[test]
+    fn it_should_report_the_position_of_unbalanced_parentheses() {
+        let error = parse("(a\n (b)").unwrap_err();
+        assert_eq!((error.line(), error.column()), (1, 1));
+
+        let error = parse("(a)\n  )").unwrap_err();
+        assert_eq!((error.line(), error.column()), (2, 3));
+    }
+}
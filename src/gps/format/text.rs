@@ -0,0 +1,867 @@
+//! The plain-text domain format of this crate.
+//!
+//! ```text
+//! # States holding initially.
+//! state son-at-home
+//! state money = 10
+//! # States which have not been observed yet.
+//! unknown car-broken
+//!
+//! operation drive-son-to-school {
+//!     require son-at-home
+//!     require not car-broken
+//!     require money >= 5 as can-pay-fuel
+//!     add son-at-school
+//!     remove son-at-home
+//!     modify money -= 5
+//!     cost 2
+//! }
+//!
+//! # States derived from the others whenever they change.
+//! rule can-drive {
+//!     require have-key
+//!     require not car-broken
+//! }
+//!
+//! goal son-at-school
+//! # Goals worth their utility, achieved if they pay for their cost.
+//! prefer money >= 8 3
+//!
+//! # Conditions holding in every state on the way.
+//! invariant money >= 0
+//! # Limits an integer state stays within on the way.
+//! bound 0 <= money <= 100
+//!
+//! # Constraints over the states and operations on the way.
+//! always not car-broken
+//! sometime son-at-home
+//! at-most-once drive-son-to-school
+//! sometime-before son-at-school money >= 5
+//! ```
+//!
+//! Tokens are separated by whitespace. Names which would otherwise be read
+//! as something else, such as `at robot room` or `not`, are written in double
+//! quotes. A comparison is named after its state, operator and value unless
+//! `as` gives it another name. Writing a problem and reading it back yields
+//! the same problem, except for modifications made by closures, which cannot
+//! be written. Operations cannot change derived states, and rules can only
+//! require derived states to hold. A bare name in a constraint stands for the
+//! operation of that name if there is one, and for a state otherwise, so a
+//! constraint on a state named like an operation cannot be written.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use super::{ParseError, Position, Span, WriteError};
+use crate::gps::axiom::{AxiomError, Axioms, Rule};
+use crate::gps::condition::{
+    Compare, CompareOperator, Condition, ConditionImpl, Contain, NotContain,
+};
+use crate::gps::numeric::ResourceBound;
+use crate::gps::operation::{Modification, ModificationEffect, Operation, OperationBuilder};
+use crate::gps::problem::Problem;
+use crate::gps::state::{State, StateData, StateSet};
+use crate::gps::trajectory::{Constraint, Proposition};
+use crate::gps::utility::SoftGoal;
+
+const KEYWORDS: [&str; 20] = [
+    "state",
+    "unknown",
+    "operation",
+    "rule",
+    "require",
+    "add",
+    "remove",
+    "modify",
+    "cost",
+    "goal",
+    "prefer",
+    "invariant",
+    "bound",
+    "always",
+    "sometime",
+    "at-most-once",
+    "sometime-before",
+    "not",
+    "as",
+    "symbol",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind {
+    Word(String),
+    Quoted(String),
+    Open,
+    Close,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    span: Span,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    end: Span,
+}
+
+/// Read a whole problem.
+pub fn parse(source: &str) -> Result<Problem, ParseError> {
+    let mut parser = Parser::new(source)?;
+    let mut states = StateSet::new();
+    let mut defined = HashSet::new();
+    let mut operations = Vec::new();
+    let mut operation_spans = Vec::new();
+    let mut rules = Vec::new();
+    let mut rule_spans = Vec::new();
+    let mut goals = Vec::new();
+    let mut soft_goals = Vec::new();
+    let mut invariants = Vec::new();
+    let mut bounds = Vec::new();
+    let mut constraints = Vec::new();
+
+    while let Some(token) = parser.next() {
+        match token.kind.word() {
+            Some("state") => {
+                let (name, span) = parser.name()?;
+                if !defined.insert(name.clone()) {
+                    return Err(ParseError::new(
+                        span,
+                        format!("state `{name}` is defined twice"),
+                    ));
+                }
+                let data = if parser.eat_word("=") {
+                    parser.value()?
+                } else {
+                    StateData::Symbol
+                };
+                states.insert(State::new(name, data));
+            }
+            Some("unknown") => {
+                let (name, span) = parser.name()?;
+                if !defined.insert(name.clone()) {
+                    return Err(ParseError::new(
+                        span,
+                        format!("state `{name}` is defined twice"),
+                    ));
+                }
+                states.insert_unknown(name.into());
+            }
+            Some("operation") => {
+                operation_spans.push(parser.here());
+                operations.push(parser.operation()?);
+            }
+            Some("rule") => {
+                rule_spans.push(parser.here());
+                rules.push(parser.rule()?);
+            }
+            Some("goal") => goals.push(parser.condition()?),
+            Some("prefer") => soft_goals.push(parser.soft_goal()?),
+            Some("invariant") => invariants.push(parser.condition()?),
+            Some("bound") => bounds.push(parser.bound()?),
+            Some("always") => constraints.push(Constraint::Always(parser.proposition()?)),
+            Some("sometime") => constraints.push(Constraint::Sometime(parser.proposition()?)),
+            Some("at-most-once") => constraints.push(Constraint::AtMostOnce(parser.proposition()?)),
+            Some("sometime-before") => constraints.push(Constraint::SometimeBefore(
+                parser.proposition()?,
+                parser.proposition()?,
+            )),
+            _ => {
+                return Err(ParseError::new(
+                    token.span,
+                    "expected a state, an operation, a rule, a goal, a soft goal, an invariant, \
+                     a bound or a constraint",
+                ))
+            }
+        }
+    }
+
+    // Point at the rule or operation the error is about.
+    let heads: Vec<_> = rules.iter().map(Rule::head).collect();
+    let axioms = Axioms::new(rules)
+        .and_then(|axioms| axioms.check(&operations).map(|()| axioms))
+        .map_err(|error| {
+            let span = match &error {
+                AxiomError::NegatedDerivedState { head, .. } => heads
+                    .iter()
+                    .position(|rule_head| rule_head == head)
+                    .map(|position| rule_spans[position]),
+                AxiomError::WrittenByOperation { operation, .. } => operations
+                    .iter()
+                    .position(|candidate| candidate.symbol() == *operation)
+                    .map(|position| operation_spans[position]),
+            };
+            ParseError::new(span.unwrap_or(parser.end), error.to_string())
+        })?;
+    let constraints = constraints
+        .into_iter()
+        .map(|constraint| constraint.resolve(&operations))
+        .collect();
+    Ok(Problem::new(operations, states, goals)
+        .with_invariants(invariants)
+        .with_resource_bounds(bounds)
+        .with_constraints(constraints)
+        .with_axioms(axioms)
+        .with_soft_goals(soft_goals))
+}
+
+/// Read a single condition, such as `not son-at-home` or `money >= 5`.
+pub fn parse_condition(source: &str) -> Result<ConditionImpl, ParseError> {
+    let mut parser = Parser::new(source)?;
+    let condition = parser.condition()?;
+    parser.finish()?;
+    Ok(condition)
+}
+
+/// Read a single state, such as `son-at-home` or `money = 10`.
+pub fn parse_state(source: &str) -> Result<State, ParseError> {
+    let mut parser = Parser::new(source)?;
+    let (name, _) = parser.name()?;
+    let data = if parser.eat_word("=") {
+        parser.value()?
+    } else {
+        StateData::Symbol
+    };
+    parser.finish()?;
+    Ok(State::new(name, data))
+}
+
+/// Write a whole problem. States and unknown states are sorted by name, while
+/// operations, rules, goals, soft goals, invariants, bounds and constraints
+/// keep their order.
+pub fn write(problem: &Problem) -> Result<String, WriteError> {
+    let mut out = String::new();
+
+    let mut states: Vec<_> = problem.states().iter().collect();
+    states.sort_by_key(|(name, _)| name.as_str());
+    for (name, data) in states {
+        writeln!(out, "state {}", write_state(name.as_str(), data)).unwrap();
+    }
+    let mut unknown: Vec<_> = problem.states().unknown().collect();
+    unknown.sort_by_key(|name| name.as_str());
+    for name in unknown {
+        writeln!(out, "unknown {}", write_name(name.as_str())).unwrap();
+    }
+
+    for operation in problem.operations() {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        write_operation(&mut out, operation)?;
+    }
+
+    for rule in problem.axioms().rules() {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        writeln!(out, "rule {} {{", write_name(rule.head().as_str())).unwrap();
+        for condition in rule.body() {
+            writeln!(out, "    require {}", write_condition(condition)).unwrap();
+        }
+        out.push_str("}\n");
+    }
+
+    if !problem.goals().is_empty() && !out.is_empty() {
+        out.push('\n');
+    }
+    for goal in problem.goals() {
+        writeln!(out, "goal {}", write_condition(goal)).unwrap();
+    }
+    for soft_goal in problem.soft_goals() {
+        writeln!(
+            out,
+            "prefer {} {}",
+            write_condition(soft_goal.goal()),
+            soft_goal.utility()
+        )
+        .unwrap();
+    }
+    for invariant in problem.invariants() {
+        writeln!(out, "invariant {}", write_condition(invariant)).unwrap();
+    }
+    for bound in problem.resource_bounds() {
+        out.push_str("bound ");
+        if let Some(min) = bound.min() {
+            write!(out, "{min} <= ").unwrap();
+        }
+        out.push_str(&write_name(bound.state_symbol().as_str()));
+        if let Some(max) = bound.max() {
+            write!(out, " <= {max}").unwrap();
+        }
+        out.push('\n');
+    }
+    for constraint in problem.constraints() {
+        check_propositions(constraint, problem.operations())?;
+        writeln!(out, "{constraint}").unwrap();
+    }
+
+    Ok(out)
+}
+
+/// A bare name in a constraint is read as an operation if there is one of
+/// that name, so states named like operations and operations the problem
+/// does not have would be read back as something else.
+fn check_propositions(constraint: &Constraint, operations: &[Operation]) -> Result<(), WriteError> {
+    let is_operation = |name| {
+        operations
+            .iter()
+            .any(|operation| operation.symbol() == name)
+    };
+    for proposition in constraint.propositions() {
+        let message = match proposition {
+            Proposition::Condition(ConditionImpl::Contain(condition))
+                if is_operation(condition.symbol()) =>
+            {
+                format!(
+                    "the state `{}` is named like an operation",
+                    condition.name()
+                )
+            }
+            Proposition::Operation(name) if !is_operation(*name) => {
+                format!("there is no operation `{name}`")
+            }
+            _ => continue,
+        };
+        return Err(WriteError::new(format!(
+            "constraint `{constraint}` cannot be written: {message}"
+        )));
+    }
+    Ok(())
+}
+
+pub fn write_condition(condition: &ConditionImpl) -> String {
+    let state_name = write_name(condition.state_name());
+
+    match condition {
+        ConditionImpl::Contain(_) => state_name,
+        ConditionImpl::NotContain(_) => format!("not {state_name}"),
+        ConditionImpl::Compare(compare) => {
+            let operator = compare.operator().as_str();
+            let value = write_value(compare.value());
+            let default_name = format!("{} {operator} {value}", condition.state_name());
+            if condition.name() == default_name {
+                format!("{state_name} {operator} {value}")
+            } else {
+                format!(
+                    "{state_name} {operator} {value} as {}",
+                    write_name(condition.name())
+                )
+            }
+        }
+    }
+}
+
+pub fn write_state(name: &str, data: &StateData) -> String {
+    match data {
+        StateData::Symbol => write_name(name),
+        StateData::Integer(_) => format!("{} = {}", write_name(name), write_value(data)),
+    }
+}
+
+fn write_operation(out: &mut String, operation: &Operation) -> Result<(), WriteError> {
+    writeln!(out, "operation {} {{", write_name(operation.name())).unwrap();
+
+    for condition in operation.prerequisites() {
+        writeln!(out, "    require {}", write_condition(condition)).unwrap();
+    }
+    for state in operation.add_states() {
+        writeln!(out, "    add {}", write_state(state.name(), state.data())).unwrap();
+    }
+    for state_name in operation.remove_states() {
+        writeln!(out, "    remove {}", write_name(state_name.as_str())).unwrap();
+    }
+    for modification in operation.modification_states() {
+        let target = write_name(modification.target_name());
+        match modification.effect() {
+            ModificationEffect::Increase(amount) => {
+                writeln!(out, "    modify {target} += {amount}")
+            }
+            ModificationEffect::Decrease(amount) => {
+                writeln!(out, "    modify {target} -= {amount}")
+            }
+            ModificationEffect::Assign(value) => {
+                writeln!(out, "    modify {target} = {}", write_value(value))
+            }
+            ModificationEffect::Custom(_) => {
+                return Err(WriteError::new(format!(
+                    "operation `{}` modifies `{}` with a closure, which cannot be written",
+                    operation.name(),
+                    modification.target_name()
+                )))
+            }
+        }
+        .unwrap();
+    }
+
+    if operation.cost() != 1 {
+        writeln!(out, "    cost {}", operation.cost()).unwrap();
+    }
+
+    out.push_str("}\n");
+    Ok(())
+}
+
+fn write_value(data: &StateData) -> String {
+    match data {
+        StateData::Symbol => "symbol".to_owned(),
+        StateData::Integer(value) => value.to_string(),
+    }
+}
+
+/// Write a name, quoting it when it would be read as something else.
+pub fn write_name(name: &str) -> String {
+    let plain = !name.is_empty()
+        && !KEYWORDS.contains(&name)
+        && CompareOperator::try_from(name).is_err()
+        && !matches!(name, "=" | "+=" | "-=")
+        && name.parse::<i32>().is_err()
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '{' | '}' | '"' | '\\' | '#'));
+
+    if plain {
+        return name.to_owned();
+    }
+
+    let mut quoted = String::from('"');
+    for c in name.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl TokenKind {
+    fn word(&self) -> Option<&str> {
+        match self {
+            TokenKind::Word(word) => Some(word),
+            _ => None,
+        }
+    }
+}
+
+impl Parser {
+    fn new(source: &str) -> Result<Self, ParseError> {
+        let mut tokens = Vec::new();
+        let mut chars = source.chars().peekable();
+        let mut position = Position::new();
+
+        while let Some(&c) = chars.peek() {
+            let start = position;
+
+            if c.is_whitespace() {
+                chars.next();
+                position.advance(c);
+            } else if c == '#' {
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    chars.next();
+                    position.advance(c);
+                }
+            } else if c == '{' || c == '}' {
+                chars.next();
+                position.advance(c);
+                tokens.push(Token {
+                    kind: if c == '{' {
+                        TokenKind::Open
+                    } else {
+                        TokenKind::Close
+                    },
+                    span: Span::new(start, position),
+                });
+            } else if c == '"' {
+                chars.next();
+                position.advance(c);
+                let mut text = String::new();
+                loop {
+                    let Some(c) = chars.next() else {
+                        return Err(ParseError::new(
+                            Span::new(start, position),
+                            "unclosed quote",
+                        ));
+                    };
+                    position.advance(c);
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            let Some(escaped) = chars.next() else {
+                                continue;
+                            };
+                            position.advance(escaped);
+                            text.push(escaped);
+                        }
+                        _ => text.push(c),
+                    }
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Quoted(text),
+                    span: Span::new(start, position),
+                });
+            } else {
+                let mut text = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '{' | '}' | '"' | '#') {
+                        break;
+                    }
+                    chars.next();
+                    position.advance(c);
+                    text.push(c);
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Word(text),
+                    span: Span::new(start, position),
+                });
+            }
+        }
+
+        Ok(Self {
+            tokens,
+            position: 0,
+            end: Span::new(position, position),
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    /// The span of the next token, or of the end of the source.
+    fn here(&self) -> Span {
+        self.peek().map_or(self.end, |token| token.span)
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let found = self
+            .peek()
+            .is_some_and(|token| token.kind.word() == Some(word));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, kind: TokenKind, expected: &str) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(token) if token.kind == kind => {
+                self.position += 1;
+                Ok(())
+            }
+            _ => Err(ParseError::new(self.here(), format!("expected {expected}"))),
+        }
+    }
+
+    fn finish(&self) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(token) => Err(ParseError::new(token.span, "unexpected trailing input")),
+            None => Ok(()),
+        }
+    }
+
+    fn name(&mut self) -> Result<(String, Span), ParseError> {
+        let span = self.here();
+        match self.peek().map(|token| &token.kind) {
+            Some(TokenKind::Quoted(name)) => {
+                let name = name.clone();
+                self.position += 1;
+                Ok((name, span))
+            }
+            Some(TokenKind::Word(word))
+                if CompareOperator::try_from(word.as_str()).is_err()
+                    && !matches!(word.as_str(), "=" | "+=" | "-=") =>
+            {
+                let name = word.clone();
+                self.position += 1;
+                Ok((name, span))
+            }
+            _ => Err(ParseError::new(span, "expected a name")),
+        }
+    }
+
+    fn value(&mut self) -> Result<StateData, ParseError> {
+        let span = self.here();
+        let value = match self.peek().and_then(|token| token.kind.word()) {
+            Some("symbol") => Some(StateData::Symbol),
+            Some(word) => word.parse().ok().map(StateData::Integer),
+            None => None,
+        };
+
+        match value {
+            Some(value) => {
+                self.position += 1;
+                Ok(value)
+            }
+            None => Err(ParseError::new(span, "expected an integer or `symbol`")),
+        }
+    }
+
+    fn integer(&mut self) -> Result<i32, ParseError> {
+        let span = self.here();
+        match self.value()? {
+            StateData::Integer(value) => Ok(value),
+            StateData::Symbol => Err(ParseError::new(span, "expected an integer")),
+        }
+    }
+
+    /// A condition, which is turned into an operation later if it names one.
+    fn proposition(&mut self) -> Result<Proposition, ParseError> {
+        Ok(Proposition::Condition(self.condition()?))
+    }
+
+    fn condition(&mut self) -> Result<ConditionImpl, ParseError> {
+        if self.eat_word("not") {
+            let (name, _) = self.name()?;
+            return Ok(NotContain::new(name).into());
+        }
+
+        let (state_name, _) = self.name()?;
+        let operator = self
+            .peek()
+            .and_then(|token| token.kind.word())
+            .and_then(|word| CompareOperator::try_from(word).ok());
+        let Some(operator) = operator else {
+            return Ok(Contain::new(state_name).into());
+        };
+        self.position += 1;
+
+        let value = self.value()?;
+        let name = if self.eat_word("as") {
+            self.name()?.0
+        } else {
+            format!(
+                "{} {} {}",
+                state_name,
+                operator.as_str(),
+                write_value(&value)
+            )
+        };
+
+        Ok(Compare::new(name, state_name, operator, value).into())
+    }
+
+    /// A condition followed by its utility, such as `money >= 8 3`.
+    fn soft_goal(&mut self) -> Result<SoftGoal, ParseError> {
+        let goal = self.condition()?;
+        let span = self.here();
+        let utility = u32::try_from(self.integer()?)
+            .map_err(|_| ParseError::new(span, "a utility cannot be negative"))?;
+        Ok(SoftGoal::new(goal, utility))
+    }
+
+    /// A bound such as `0 <= money <= 100`, either limit of which may be
+    /// left out.
+    fn bound(&mut self) -> Result<ResourceBound, ParseError> {
+        let min = match self.peek().and_then(|token| token.kind.word()) {
+            Some(word) if word.parse::<i32>().is_ok() => {
+                let min = self.integer()?;
+                self.expect(TokenKind::Word("<=".to_owned()), "`<=`")?;
+                Some(min)
+            }
+            _ => None,
+        };
+        let (state, span) = self.name()?;
+        let mut bound = ResourceBound::new(state);
+        if let Some(min) = min {
+            bound = bound.with_min(min);
+        }
+        if self.eat_word("<=") {
+            bound = bound.with_max(self.integer()?);
+        } else if min.is_none() {
+            return Err(ParseError::new(
+                span,
+                "a bound needs a minimum or a maximum",
+            ));
+        }
+        Ok(bound)
+    }
+
+    fn rule(&mut self) -> Result<Rule, ParseError> {
+        let (head, _) = self.name()?;
+        self.expect(TokenKind::Open, "`{`")?;
+        let mut body = Vec::new();
+        loop {
+            let Some(token) = self.next() else {
+                return Err(ParseError::new(self.end, "expected `}`"));
+            };
+            match (&token.kind, token.kind.word()) {
+                (TokenKind::Close, _) => return Ok(Rule::new(head, body)),
+                (_, Some("require")) => body.push(self.condition()?),
+                _ => return Err(ParseError::new(token.span, "expected `require` or `}`")),
+            }
+        }
+    }
+
+    fn operation(&mut self) -> Result<Operation, ParseError> {
+        let (name, _) = self.name()?;
+        let mut builder = OperationBuilder::new(name);
+        self.expect(TokenKind::Open, "`{`")?;
+
+        loop {
+            let Some(token) = self.next() else {
+                return Err(ParseError::new(self.end, "expected `}`"));
+            };
+
+            builder = match (&token.kind, token.kind.word()) {
+                (TokenKind::Close, _) => return Ok(builder.build()),
+                (_, Some("require")) => builder.insert_prerequisite(self.condition()?),
+                (_, Some("add")) => {
+                    let (name, _) = self.name()?;
+                    let data = if self.eat_word("=") {
+                        self.value()?
+                    } else {
+                        StateData::Symbol
+                    };
+                    builder.insert_add_state(State::new(name, data))
+                }
+                (_, Some("remove")) => builder.insert_remove_state(self.name()?.0),
+                (_, Some("modify")) => {
+                    let (target, _) = self.name()?;
+                    let modification = if self.eat_word("+=") {
+                        Modification::increase(target, self.integer()?)
+                    } else if self.eat_word("-=") {
+                        Modification::decrease(target, self.integer()?)
+                    } else if self.eat_word("=") {
+                        Modification::assign(target, self.value()?)
+                    } else {
+                        return Err(ParseError::new(self.here(), "expected `+=`, `-=` or `=`"));
+                    };
+                    builder.insert_modify_state(modification)
+                }
+                (_, Some("cost")) => {
+                    let span = self.here();
+                    let cost = u32::try_from(self.integer()?)
+                        .map_err(|_| ParseError::new(span, "a cost cannot be negative"))?;
+                    builder.set_cost(cost)
+                }
+                _ => {
+                    return Err(ParseError::new(
+                        token.span,
+                        "expected `require`, `add`, `remove`, `modify`, `cost` or `}`",
+                    ))
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+state \"at robot room\"
+state money = 10
+state son-at-home
+unknown door-open
+
+operation drive-son-to-school {
+    require son-at-home
+    require not car-broken
+    require money >= 5
+    require money != symbol as \"money is a number\"
+    add son-at-school
+    add fuel = 3
+    remove son-at-home
+    modify money -= 5
+    modify fuel += 1
+    modify \"not\" = symbol
+    cost 4
+}
+
+operation \"not\" {
+}
+
+rule can-drive {
+    require have-key
+    require not car-broken
+}
+
+goal son-at-school
+goal money < 20 as affordable
+prefer fuel > 2 5
+prefer son-at-home 1
+invariant money >= 0
+invariant not car-broken
+bound 0 <= money <= 100
+bound fuel <= 10
+always money < 100
+at-most-once drive-son-to-school
+sometime-before son-at-school \"at robot room\"
+";
+
+    #[test]
+    fn it_should_write_what_it_reads() {
+        let problem = parse(SOURCE).unwrap();
+        assert_eq!(write(&problem).unwrap(), SOURCE);
+
+        let operation = &problem.operations()[0];
+        assert_eq!(operation.prerequisites()[2].name(), "money >= 5");
+        assert_eq!(operation.prerequisites()[3].name(), "money is a number");
+        assert!(problem.states().get("at robot room").is_some());
+        assert!(problem.states().is_unknown("door-open".into()));
+        assert_eq!(problem.operations()[1].name(), "not");
+        assert_eq!(operation.cost(), 4);
+        assert_eq!(problem.operations()[1].cost(), 1);
+        assert_eq!(problem.invariants()[0].name(), "money >= 0");
+        assert_eq!(problem.soft_goals()[0].goal().name(), "fuel > 2");
+        assert_eq!(problem.soft_goals()[0].utility(), 5);
+        assert_eq!(
+            problem.resource_bounds()[1],
+            ResourceBound::new("fuel".to_owned()).with_max(10)
+        );
+        assert!(problem.axioms().is_derived("can-drive".into()));
+        assert_eq!(
+            problem.constraints()[1],
+            Constraint::AtMostOnce(Proposition::Operation(operation.symbol()))
+        );
+
+        let state = Proposition::Condition(Contain::new(operation.name().to_owned()).into());
+        let ambiguous = problem.with_constraints(vec![Constraint::Always(state)]);
+        assert_eq!(
+            write(&ambiguous).unwrap_err().to_string(),
+            "constraint `always drive-son-to-school` cannot be written: \
+             the state `drive-son-to-school` is named like an operation"
+        );
+    }
+
+    #[test]
+    fn it_should_report_errors_with_spans() {
+        let error = parse("state a\noperation b {\n    require c >=\n}").unwrap_err();
+        assert_eq!((error.line(), error.column()), (4, 1));
+        assert_eq!(error.message(), "expected an integer or `symbol`");
+
+        let error = parse("goal \"unclosed").unwrap_err();
+        assert_eq!(error.span().start.column, 6);
+        assert_eq!(error.span().end.column, 15);
+
+        let error = parse("operation a {\n    add b\n}\nrule b {\n}").unwrap_err();
+        assert_eq!((error.line(), error.column()), (1, 11));
+        assert_eq!(error.message(), "operation `a` changes derived state `b`");
+    }
+
+    #[test]
+    fn it_should_parse_single_conditions_and_states() {
+        let condition = parse_condition("value <= 3").unwrap();
+        assert_eq!(condition.name(), "value <= 3");
+        assert!(parse_condition("value <= 3 extra").is_err());
+
+        let state = parse_state("value = 3").unwrap();
+        assert_eq!(state.data(), &StateData::Integer(3));
+
+        // A `#` anywhere in a name would start a comment.
+        let state = parse_state(&write_state("a#b", &StateData::Symbol)).unwrap();
+        assert_eq!(state.name(), "a#b");
+    }
+}
@@ -0,0 +1,299 @@
+//! Random problems for stress testing planners.
+//!
+//! A problem is generated around a hidden plan: starting from random initial
+//! states, each step of the plan only requires states holding at that point,
+//! and the goals are taken from the states holding at the end. The plan is
+//! returned as a witness, so every generated problem is solvable, although
+//! an incomplete planner like GPS may still fail on it. Operations not in
+//! the plan are added at random to distract the planner. The same seed and
+//! settings always yield the same problem.
+
+use super::condition::{ConditionImpl, Contain, NotContain};
+use super::operation::{Operation, OperationBuilder};
+use super::problem::Problem;
+use super::state::{State, StateSet};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Generator {
+    seed: u64,
+    states: usize,
+    operations: usize,
+    plan_length: usize,
+    prerequisites: usize,
+    add_effects: usize,
+    remove_effects: usize,
+    goals: usize,
+    negative_goals: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratedProblem {
+    problem: Problem,
+    witness: Vec<Operation>,
+}
+
+/// SplitMix64, which is small and good enough for generating test data.
+struct Random(u64);
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            states: 20,
+            operations: 30,
+            plan_length: 5,
+            prerequisites: 3,
+            add_effects: 2,
+            remove_effects: 1,
+            goals: 3,
+            negative_goals: 1,
+        }
+    }
+
+    /// The number of distinct states, named `s0`, `s1` and so on.
+    pub fn with_states(mut self, states: usize) -> Self {
+        self.states = states.max(1);
+        self
+    }
+
+    /// The number of operations, including those of the hidden plan.
+    pub fn with_operations(mut self, operations: usize) -> Self {
+        self.operations = operations;
+        self
+    }
+
+    pub fn with_plan_length(mut self, plan_length: usize) -> Self {
+        self.plan_length = plan_length;
+        self
+    }
+
+    /// The largest number of prerequisites of an operation.
+    pub fn with_prerequisites(mut self, prerequisites: usize) -> Self {
+        self.prerequisites = prerequisites;
+        self
+    }
+
+    /// The largest number of states an operation adds, which is at least 1.
+    pub fn with_add_effects(mut self, add_effects: usize) -> Self {
+        self.add_effects = add_effects.max(1);
+        self
+    }
+
+    /// The largest number of states an operation removes.
+    pub fn with_remove_effects(mut self, remove_effects: usize) -> Self {
+        self.remove_effects = remove_effects;
+        self
+    }
+
+    /// The largest number of states which must hold at the end.
+    pub fn with_goals(mut self, goals: usize) -> Self {
+        self.goals = goals;
+        self
+    }
+
+    /// The largest number of states which must not hold at the end.
+    pub fn with_negative_goals(mut self, negative_goals: usize) -> Self {
+        self.negative_goals = negative_goals;
+        self
+    }
+
+    pub fn generate(&self) -> GeneratedProblem {
+        let mut random = Random(self.seed);
+        let all: Vec<usize> = (0..self.states).collect();
+
+        let initial: Vec<usize> = all.iter().copied().filter(|_| random.chance(2)).collect();
+        let mut current = initial.clone();
+
+        let mut operations = Vec::new();
+        for step in 0..self.plan_length {
+            let prerequisites = random.sample(&current, self.prerequisites);
+            let absent: Vec<_> = all
+                .iter()
+                .copied()
+                .filter(|s| !current.contains(s))
+                .collect();
+            let adds = random.sample(
+                if absent.is_empty() { &all } else { &absent },
+                self.add_effects,
+            );
+            let removable: Vec<_> = current
+                .iter()
+                .copied()
+                .filter(|s| !adds.contains(s))
+                .collect();
+            let removes = random.sample(&removable, self.remove_effects);
+
+            current.retain(|s| !removes.contains(s));
+            for state in &adds {
+                if !current.contains(state) {
+                    current.push(*state);
+                }
+            }
+            operations.push((prerequisites, adds, removes, Some(step)));
+        }
+
+        for _ in self.plan_length..self.operations {
+            let prerequisites = random.sample(&all, self.prerequisites);
+            let adds = random.sample(&all, self.add_effects);
+            let removable: Vec<_> = all.iter().copied().filter(|s| !adds.contains(s)).collect();
+            let removes = random.sample(&removable, self.remove_effects);
+            operations.push((prerequisites, adds, removes, None));
+        }
+
+        // Hide the plan among the other operations, then name them in order.
+        random.shuffle(&mut operations);
+        let mut witness = vec![None; self.plan_length];
+        let mut built = Vec::new();
+        for (position, (prerequisites, adds, removes, step)) in operations.into_iter().enumerate() {
+            let mut builder = OperationBuilder::new(format!("op{position}"));
+            for state in prerequisites {
+                builder = builder.insert_prerequisite(Contain::new(state_name(state)).into());
+            }
+            for state in adds {
+                builder = builder.insert_add_state(State::new_symbol(state_name(state)));
+            }
+            for state in removes {
+                builder = builder.insert_remove_state(state_name(state));
+            }
+            let operation = builder.build();
+            if let Some(step) = step {
+                witness[step] = Some(operation.clone());
+            }
+            built.push(operation);
+        }
+        let witness = witness.into_iter().flatten().collect();
+
+        let mut states = StateSet::new();
+        for state in &initial {
+            states.insert(State::new_symbol(state_name(*state)));
+        }
+
+        // Prefer goals the plan has to work for.
+        let changed: Vec<_> = current
+            .iter()
+            .copied()
+            .filter(|s| !initial.contains(s))
+            .collect();
+        let goal_pool = if changed.is_empty() {
+            &current
+        } else {
+            &changed
+        };
+        let removed: Vec<_> = initial
+            .iter()
+            .copied()
+            .filter(|s| !current.contains(s))
+            .collect();
+        let mut goals: Vec<ConditionImpl> = random
+            .sample_exactly(goal_pool, self.goals)
+            .into_iter()
+            .map(|state| Contain::new(state_name(state)).into())
+            .collect();
+        goals.extend(
+            random
+                .sample_exactly(&removed, self.negative_goals)
+                .into_iter()
+                .map(|state| ConditionImpl::from(NotContain::new(state_name(state)))),
+        );
+
+        GeneratedProblem {
+            problem: Problem::new(built, states, goals),
+            witness,
+        }
+    }
+}
+
+impl GeneratedProblem {
+    pub fn problem(&self) -> &Problem {
+        &self.problem
+    }
+
+    /// A plan achieving the goals.
+    pub fn witness(&self) -> &Vec<Operation> {
+        &self.witness
+    }
+
+    pub fn into_inner(self) -> (Problem, Vec<Operation>) {
+        (self.problem, self.witness)
+    }
+}
+
+fn state_name(state: usize) -> String {
+    format!("s{state}")
+}
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number below the bound, which must not be 0.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    /// True once in `n` times.
+    fn chance(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+
+    /// Between 1 and `max` distinct items, or none when there are none.
+    fn sample(&mut self, items: &[usize], max: usize) -> Vec<usize> {
+        if items.is_empty() || max == 0 {
+            return Vec::new();
+        }
+        let count = 1 + self.below(max.min(items.len()));
+        self.sample_exactly(items, count)
+    }
+
+    /// `count` distinct items, or all of them when there are fewer.
+    fn sample_exactly(&mut self, items: &[usize], count: usize) -> Vec<usize> {
+        let mut items = items.to_vec();
+        self.shuffle(&mut items);
+        items.truncate(count);
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps::format::text;
+
+    #[test]
+    fn it_should_generate_the_same_problem_from_the_same_seed() {
+        let generator = Generator::new(42).with_states(10).with_operations(12);
+        let first = text::write(generator.generate().problem()).unwrap();
+        assert_eq!(first, text::write(generator.generate().problem()).unwrap());
+
+        let other = Generator::new(43).with_states(10).with_operations(12);
+        assert_ne!(first, text::write(other.generate().problem()).unwrap());
+    }
+
+    #[test]
+    fn it_should_generate_solvable_problems() {
+        for seed in 0..50 {
+            let generated = Generator::new(seed).generate();
+            let problem = generated.problem();
+            assert_eq!(problem.operations().len(), 30);
+            assert_eq!(generated.witness().len(), 5);
+
+            let mut states = problem.states().clone();
+            for operation in generated.witness() {
+                assert!(operation.is_applicable(&states), "seed {seed}");
+                operation.apply(&mut states);
+            }
+            assert!(states.has_reached(problem.goals()), "seed {seed}");
+        }
+    }
+}
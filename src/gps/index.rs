@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use super::operation::Operation;
+use super::symbol::Symbol;
+
+/// Maps each state to the operations that add, remove or modify it. The
+/// operations are referred to by their position in the operation list and
+/// kept in ascending order, so lookups yield candidates in the same order a
+/// linear scan would.
+#[derive(Debug, Clone, Default)]
+pub struct AchieverIndex {
+    adders: HashMap<Symbol, Vec<usize>>,
+    deleters: HashMap<Symbol, Vec<usize>>,
+    modifiers: HashMap<Symbol, Vec<usize>>,
+}
+
+impl AchieverIndex {
+    pub fn new(operations: &[Operation]) -> Self {
+        let mut index = Self::default();
+
+        for (position, operation) in operations.iter().enumerate() {
+            index.push(position, operation);
+        }
+
+        index
+    }
+
+    /// Index an operation appended at the end of the operation list.
+    pub fn push(&mut self, position: usize, operation: &Operation) {
+        for state in operation.add_states() {
+            Self::insert(&mut self.adders, state.symbol(), position);
+        }
+
+        for state_name in operation.remove_states() {
+            Self::insert(&mut self.deleters, *state_name, position);
+        }
+
+        for modification in operation.modification_states() {
+            Self::insert(&mut self.modifiers, modification.target_symbol(), position);
+        }
+    }
+
+    /// Forget the operation at the given position and shift the positions
+    /// of the operations after it.
+    pub fn remove(&mut self, position: usize) {
+        for map in [&mut self.adders, &mut self.deleters, &mut self.modifiers] {
+            map.retain(|_, positions| {
+                positions.retain(|p| *p != position);
+                positions
+                    .iter_mut()
+                    .filter(|p| **p > position)
+                    .for_each(|p| *p -= 1);
+                !positions.is_empty()
+            });
+        }
+    }
+
+    pub fn adders(&self, state_name: Symbol) -> &[usize] {
+        Self::get(&self.adders, state_name)
+    }
+
+    pub fn deleters(&self, state_name: Symbol) -> &[usize] {
+        Self::get(&self.deleters, state_name)
+    }
+
+    pub fn modifiers(&self, state_name: Symbol) -> &[usize] {
+        Self::get(&self.modifiers, state_name)
+    }
+
+    fn insert(map: &mut HashMap<Symbol, Vec<usize>>, state_name: Symbol, position: usize) {
+        let positions = map.entry(state_name).or_default();
+
+        // An operation touching the same state twice is only listed once.
+        if positions.last() != Some(&position) {
+            positions.push(position);
+        }
+    }
+
+    fn get(map: &HashMap<Symbol, Vec<usize>>, state_name: Symbol) -> &[usize] {
+        map.get(&state_name).map_or(&[], Vec::as_slice)
+    }
+}
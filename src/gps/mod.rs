@@ -1,68 +1,278 @@
+pub mod axiom;
 pub mod condition;
+pub mod domains;
+pub mod format;
+pub mod generator;
+pub mod index;
+pub mod numeric;
 pub mod operation;
+pub mod plan;
+pub mod portfolio;
+pub mod problem;
+pub mod repl;
+pub mod search;
 pub mod state;
+pub mod symbol;
+pub mod temporal;
+pub mod trajectory;
+pub mod tree;
+pub mod utility;
 
-use condition::{Condition, ConditionImpl};
+use axiom::Axioms;
+use condition::{Compare, Condition, ConditionImpl, Truth};
+use index::AchieverIndex;
+use numeric::ResourceBound;
 use operation::Operation;
+use portfolio::{CancellationToken, Planner};
+use search::{Limits, Outcome, Statistics};
 use state::StateSet;
+use trajectory::{Constraint, Monitor};
+use tree::{Failure, NodeKind, NodeStatus, SearchTree, TreeBuilder};
 
 use self::condition::ConditionSet;
 
+#[derive(Debug, Clone)]
 pub struct GeneralProblemSolver {
     operations: Vec<Operation>,
+    index: AchieverIndex,
     goals: Vec<ConditionImpl>,
     states: StateSet,
+    limits: Limits,
+    resource_bounds: Vec<ResourceBound>,
+    invariants: Vec<ConditionImpl>,
+    constraints: Vec<Constraint>,
+    axioms: Axioms,
+}
+
+impl Default for GeneralProblemSolver {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GeneralProblemSolver {
     pub fn new() -> Self {
         Self {
             operations: Vec::new(),
+            index: AchieverIndex::default(),
             goals: Vec::new(),
             states: StateSet::new(),
+            limits: Limits::new(),
+            resource_bounds: Vec::new(),
+            invariants: Vec::new(),
+            constraints: Vec::new(),
+            axioms: Axioms::default(),
         }
     }
 
     pub fn set_operations(&mut self, operation: Vec<Operation>) -> &mut Self {
+        self.index = AchieverIndex::new(&operation);
         self.operations = operation;
         self
     }
 
+    /// Append an operation, which is tried after all existing ones.
+    pub fn add_operation(&mut self, operation: Operation) -> &mut Self {
+        self.index.push(self.operations.len(), &operation);
+        self.operations.push(operation);
+        self
+    }
+
+    /// Remove the first operation with the given name and return it.
+    pub fn remove_operation(&mut self, name: &str) -> Option<Operation> {
+        let position = self
+            .operations
+            .iter()
+            .position(|operation| operation.name() == name)?;
+        self.index.remove(position);
+        Some(self.operations.remove(position))
+    }
+
+    pub fn operations(&self) -> &Vec<Operation> {
+        &self.operations
+    }
+
     pub fn set_goals(&mut self, goals: Vec<ConditionImpl>) -> &mut Self {
         self.goals = goals;
         self
     }
 
+    pub fn goals(&self) -> &Vec<ConditionImpl> {
+        &self.goals
+    }
+
     pub fn set_states(&mut self, states: StateSet) -> &mut Self {
         self.states = states;
         self
     }
 
+    pub fn states(&self) -> &StateSet {
+        &self.states
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) -> &mut Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Bounds every state reached during search must respect. Operations
+    /// leaving a state out of bounds are not applied.
+    pub fn set_resource_bounds(&mut self, resource_bounds: Vec<ResourceBound>) -> &mut Self {
+        self.resource_bounds = resource_bounds;
+        self
+    }
+
+    pub fn resource_bounds(&self) -> &Vec<ResourceBound> {
+        &self.resource_bounds
+    }
+
+    /// Conditions which must hold in the initial states and after every
+    /// operation applied during search.
+    pub fn set_invariants(&mut self, invariants: Vec<ConditionImpl>) -> &mut Self {
+        self.invariants = invariants;
+        self
+    }
+
+    pub fn invariants(&self) -> &Vec<ConditionImpl> {
+        &self.invariants
+    }
+
+    /// Constraints over the trajectory of the plan. Operations breaking one
+    /// are not applied, and conditions which must hold sometime are reached
+    /// before the goals if the plan would miss them otherwise.
+    pub fn set_constraints(&mut self, constraints: Vec<Constraint>) -> &mut Self {
+        self.constraints = constraints;
+        self
+    }
+
+    pub fn constraints(&self) -> &Vec<Constraint> {
+        &self.constraints
+    }
+
+    /// Rules deriving states from the others after every operation. Goals on
+    /// derived states are achieved through the bodies of their rules.
+    pub fn set_axioms(&mut self, axioms: Axioms) -> &mut Self {
+        self.axioms = axioms;
+        self
+    }
+
+    pub fn axioms(&self) -> &Axioms {
+        &self.axioms
+    }
+
     /// Solve the given problem and return the solution.
     pub fn solve(&self) -> Option<Vec<Operation>> {
-        let mut goal_stack = Vec::new();
-        let mut protected_goals = ConditionSet::new();
-
-        self.solve_all(
-            &self.goals,
-            &self.states,
-            &mut goal_stack,
-            &mut protected_goals,
-        )
-        .map(|(_, operations)| operations)
+        self.solve_with(&CancellationToken::new())
+    }
+
+    /// Solve the given problem, giving up as soon as the token is cancelled.
+    pub fn solve_with(&self, cancellation: &CancellationToken) -> Option<Vec<Operation>> {
+        self.search(cancellation).0.into_operations()
+    }
+
+    /// Solve the given problem within the limits, telling apart problems
+    /// without a solution from searches which were cut short.
+    pub fn search(&self, cancellation: &CancellationToken) -> (Outcome, Statistics) {
+        let (outcome, statistics, _) = self.run(cancellation, None);
+        (outcome, statistics)
+    }
+
+    /// Solve the given problem like [`search`](Self::search) and record
+    /// every goal and operation tried on the way.
+    pub fn search_tree(
+        &self,
+        cancellation: &CancellationToken,
+    ) -> (Outcome, Statistics, SearchTree) {
+        let (outcome, statistics, tree) = self.run(cancellation, Some(TreeBuilder::default()));
+        (outcome, statistics, tree.unwrap_or_default())
+    }
+
+    fn run(
+        &self,
+        cancellation: &CancellationToken,
+        tree: Option<TreeBuilder>,
+    ) -> (Outcome, Statistics, Option<SearchTree>) {
+        let mut states = self.states.clone();
+        self.axioms.derive(&mut states);
+        let monitor = match Monitor::new(self.constraints.clone(), &states) {
+            Ok(monitor)
+                if states.has_reached(&self.invariants)
+                    && self
+                        .resource_bounds
+                        .iter()
+                        .all(|bound| bound.allows(&states)) =>
+            {
+                monitor
+            }
+            _ => return (Outcome::Unsolvable, Statistics::default(), None),
+        };
+        let mut context = SearchContext {
+            goal_stack: Vec::new(),
+            protected_goals: ConditionSet::new(),
+            cancellation,
+            statistics: Statistics::default(),
+            limit_exceeded: false,
+            monitor: monitor.clone(),
+            tree,
+        };
+
+        let mut solution = self.solve_all(&self.goals, &states, &mut context);
+        if solution.is_some() && context.monitor.finish().is_err() {
+            // Search again, reaching the conditions the plan missed first.
+            let pending = context.monitor.pending_conditions();
+            context.monitor = monitor;
+            context.tree = context.tree.map(|_| TreeBuilder::default());
+            solution = None;
+            if !pending.is_empty() {
+                solution = self
+                    .solve_all(&pending, &states, &mut context)
+                    .and_then(|(states, mut operations)| {
+                        let (states, mut rest) =
+                            self.solve_all(&self.goals, &states, &mut context)?;
+                        operations.append(&mut rest);
+                        Some((states, operations))
+                    })
+                    .filter(|_| context.monitor.finish().is_ok());
+            }
+        }
+
+        let outcome = match solution {
+            Some((_, operations)) => Outcome::Solved(operations),
+            None if context.limit_exceeded => Outcome::LimitExceeded,
+            None => Outcome::Unsolvable,
+        };
+        let tree = context.tree.map(TreeBuilder::finish);
+        (outcome, context.statistics, tree)
     }
 
     /// Achieve a set of goals and return operations required and states
     /// after this procdure.
     fn solve_all(
         &self,
-        goals: &Vec<ConditionImpl>,
+        goals: &[ConditionImpl],
         current_states: &StateSet,
-        goal_stack: &mut Vec<ConditionImpl>,
-        protected_goals: &mut ConditionSet,
+        context: &mut SearchContext,
     ) -> Option<(StateSet, Vec<Operation>)> {
-        if current_states.has_reached(&goals) {
-            return Some((current_states.clone(), Vec::new()));
+        context.enter(|| NodeKind::Goals(goals.to_vec()));
+        let attempt = self.try_solve_all(goals, current_states, context);
+        context.leave(attempt)
+    }
+
+    fn try_solve_all(
+        &self,
+        goals: &[ConditionImpl],
+        current_states: &StateSet,
+        context: &mut SearchContext,
+    ) -> Attempt {
+        if current_states.has_reached(goals) {
+            for goal in goals {
+                context.record(|| NodeKind::Goal(goal.clone()), NodeStatus::Holds);
+            }
+            return Ok((current_states.clone(), Vec::new()));
         }
 
         let mut new_states = current_states.clone();
@@ -71,7 +281,10 @@ impl GeneralProblemSolver {
         for goal in goals {
             if goal.check(current_states) {
                 // Already achieved goals shouldn't be destoryed by other operations.
-                protected_goals.insert(goal.state_name(), goal.clone());
+                context
+                    .protected_goals
+                    .insert(goal.state_symbol(), goal.clone());
+                context.record(|| NodeKind::Goal(goal.clone()), NodeStatus::Holds);
             } else {
                 unachieved_goals.push(goal.clone());
             }
@@ -81,22 +294,24 @@ impl GeneralProblemSolver {
 
         // Achieve each unachieved goal.
         for goal in &unachieved_goals {
-            let (next_states, mut next_operations) =
-                self.solve_one(goal, &new_states, goal_stack, protected_goals)?;
-            protected_goals.insert(goal.state_name(), goal.clone());
+            let (next_states, mut next_operations) = self
+                .solve_one(goal, &new_states, context)
+                .ok_or_else(|| Failure::SubgoalFailed(goal.clone()))?;
+            context
+                .protected_goals
+                .insert(goal.state_symbol(), goal.clone());
             operations.append(&mut next_operations);
             new_states = next_states;
         }
 
         // Ensure all goals have been achieved.
-        if goals.iter().all(|condition| condition.check(&new_states)) {
-            goals.iter().for_each(|goal| {
-                protected_goals.remove(goal.state_name(), goal);
-            });
-            Some((new_states, operations))
-        } else {
-            None
+        if let Some(undone) = goals.iter().find(|condition| !condition.check(&new_states)) {
+            return Err(Failure::Undone(undone.clone()));
         }
+        goals.iter().for_each(|goal| {
+            context.protected_goals.remove(goal.state_symbol(), goal);
+        });
+        Ok((new_states, operations))
     }
 
     /// Achieve one individual goal and return operations required and states
@@ -105,36 +320,103 @@ impl GeneralProblemSolver {
         &self,
         goal: &ConditionImpl,
         current_states: &StateSet,
-        goal_stack: &mut Vec<ConditionImpl>,
-        protected_goals: &mut ConditionSet,
+        context: &mut SearchContext,
     ) -> Option<(StateSet, Vec<Operation>)> {
+        context.enter(|| NodeKind::Goal(goal.clone()));
+        let attempt = self.try_solve_one(goal, current_states, context);
+        context.leave(attempt)
+    }
+
+    fn try_solve_one(
+        &self,
+        goal: &ConditionImpl,
+        current_states: &StateSet,
+        context: &mut SearchContext,
+    ) -> Attempt {
         if goal.check(current_states) {
-            return Some((current_states.clone(), Vec::new()));
+            return Ok((current_states.clone(), Vec::new()));
         }
 
-        if goal_stack.contains(&goal) {
-            return None;
+        if context.goal_stack.contains(goal) {
+            return Err(Failure::Recursive);
         }
 
-        let valid_operations = self.find_valid_operations(goal, current_states, protected_goals);
-        goal_stack.push(goal.clone());
+        let depth = context.goal_stack.len() + 1;
+        if context.cancellation.is_cancelled()
+            || self.limits.max_depth().is_some_and(|max| depth > max)
+        {
+            context.limit_exceeded = true;
+            return Err(Failure::LimitExceeded);
+        }
+        context.statistics.record_goal(depth);
+
+        if matches!(goal, ConditionImpl::Contain(_)) && self.axioms.is_derived(goal.state_symbol())
+        {
+            return self.try_derive(goal, current_states, context);
+        }
+
+        let valid_operations =
+            self.find_valid_operations(goal, current_states, &context.protected_goals);
+        if context.tree.is_some() {
+            for operation in self.candidate_operations(goal) {
+                if operation.has_affect(current_states, &context.protected_goals) {
+                    context.record(
+                        || NodeKind::Operation(operation.clone()),
+                        NodeStatus::Failed(Failure::UndoesProtectedGoal),
+                    );
+                }
+            }
+        }
+        if valid_operations.is_empty() {
+            return Err(match goal.evaluate(current_states) {
+                Truth::Unknown => Failure::UnknownState,
+                _ => Failure::NoOperations,
+            });
+        }
+        context.goal_stack.push(goal.clone());
 
         for valid_operation in valid_operations.iter() {
-            let res = self.apply_operation(
-                valid_operation.clone(),
-                current_states,
-                goal_stack,
-                protected_goals,
-            );
+            // The trajectory of a failed attempt is forgotten.
+            let monitor = context.monitor.clone();
+            let res = match goal {
+                ConditionImpl::Compare(goal) => {
+                    self.apply_repeatedly(goal, valid_operation, current_states, context)
+                }
+                _ => self.apply_operation(valid_operation.clone(), current_states, context),
+            };
 
-            if res.is_some() {
-                goal_stack.pop();
-                return res;
+            if let Some(res) = res {
+                context.goal_stack.pop();
+                return Ok(res);
             }
+            context.monitor = monitor;
         }
 
-        goal_stack.pop();
-        None
+        context.goal_stack.pop();
+        Err(Failure::AllOperationsFailed)
+    }
+
+    /// Achieve a derived goal by achieving the body of one of its rules.
+    fn try_derive(
+        &self,
+        goal: &ConditionImpl,
+        current_states: &StateSet,
+        context: &mut SearchContext,
+    ) -> Attempt {
+        context.goal_stack.push(goal.clone());
+        for rule in self.axioms.rules_for(goal.state_symbol()) {
+            let monitor = context.monitor.clone();
+            let res = self
+                .solve_all(rule.body(), current_states, context)
+                .filter(|(states, _)| goal.check(states));
+            if let Some(res) = res {
+                context.goal_stack.pop();
+                return Ok(res);
+            }
+            context.monitor = monitor;
+        }
+        context.goal_stack.pop();
+        Err(Failure::AllRulesFailed)
     }
 
     /// Find out all operations capable of achieving the given goal.
@@ -144,77 +426,211 @@ impl GeneralProblemSolver {
         current_states: &StateSet,
         protected_goals: &ConditionSet,
     ) -> Vec<Operation> {
-        match goal {
-            ConditionImpl::Contain(_) => self
-                .operations
-                .iter()
-                .filter(|operation| {
-                    // Check if this operation will add the needed state.
-                    operation
-                        .add_states()
-                        .iter()
-                        .find(|state| state.name() == goal.name())
-                        .is_some()
-                })
-                // Ensure that protects goals will be conserved.
-                .filter(|operation| !operation.has_affect(current_states, protected_goals))
-                .cloned()
-                .collect(),
-            ConditionImpl::NotContain(_) => self
-                .operations
-                .iter()
-                .filter(|operation| {
-                    // Check if this operation will remove the target state.
-                    operation
-                        .remove_states()
-                        .iter()
-                        .find(|state_name| state_name.as_str() == goal.name())
-                        .is_some()
-                })
-                .filter(|operation| !operation.has_affect(current_states, protected_goals))
-                .cloned()
-                .collect(),
-            ConditionImpl::Compare(_) => self
-                .operations
-                .iter()
-                .filter(|operation| {
-                    // Check if this operation will modify the target state.
-                    operation
-                        .modification_states()
-                        .iter()
-                        .find(|modification| modification.target_name() == goal.state_name())
-                        .is_some()
-                })
-                .filter(|operation| !operation.has_affect(current_states, protected_goals))
-                .cloned()
-                .collect(),
+        let operations = self
+            .candidate_operations(goal)
+            // Ensure that protects goals will be conserved.
+            .filter(|operation| !operation.has_affect(current_states, protected_goals));
+        let ConditionImpl::Compare(goal) = goal else {
+            return operations.cloned().collect();
+        };
+
+        // Try the operations closing the gap in the fewest applications first,
+        // and those without an estimate last.
+        let mut estimated: Vec<_> = operations
+            .map(|operation| {
+                let applications = numeric::applications_needed(operation, goal, current_states);
+                (applications.unwrap_or(u32::MAX), operation.clone())
+            })
+            .collect();
+        estimated.sort_by_key(|(applications, _)| *applications);
+        estimated
+            .into_iter()
+            .map(|(_, operation)| operation)
+            .collect()
+    }
+
+    /// The operations changing the state of the goal in the right way,
+    /// whether or not they would undo protected goals.
+    fn candidate_operations(&self, goal: &ConditionImpl) -> impl Iterator<Item = &Operation> {
+        let candidates = match goal {
+            // Operations that will add the needed state.
+            ConditionImpl::Contain(_) => self.index.adders(goal.state_symbol()),
+            // Operations that will remove the target state.
+            ConditionImpl::NotContain(_) => self.index.deleters(goal.state_symbol()),
+            // Operations that will modify the target state.
+            ConditionImpl::Compare(_) => self.index.modifiers(goal.state_symbol()),
+        };
+
+        candidates
+            .iter()
+            .map(|position| &self.operations[*position])
+    }
+
+    /// Apply the operation as many times as it takes to satisfy the numeric
+    /// goal, achieving its prerequisites again before each application. Gives
+    /// up as soon as an application does not get closer to the goal. Once the
+    /// operation alone would step over the value, the rest of the gap is
+    /// closed as a goal of its own, so other operations can finish the work.
+    fn apply_repeatedly(
+        &self,
+        goal: &Compare,
+        operation: &Operation,
+        current_states: &StateSet,
+        context: &mut SearchContext,
+    ) -> Option<(StateSet, Vec<Operation>)> {
+        let (mut states, mut operations) =
+            self.apply_operation(operation.clone(), current_states, context)?;
+        let closer = |before: &StateSet, after: &StateSet| match (
+            numeric::gap(goal, before),
+            numeric::gap(goal, after),
+        ) {
+            (Some(before), Some(after)) => after < before,
+            _ => true,
+        };
+        if !closer(current_states, &states) {
+            return None;
         }
+        while !goal.check(&states) {
+            if numeric::applications_needed(operation, goal, &states).is_none() {
+                // Not a recursion: the gap shrinks with every call, so this ends.
+                let rest = context.goal_stack.pop();
+                let attempt = self.solve_one(&goal.clone().into(), &states, context);
+                context.goal_stack.extend(rest);
+                let (next_states, mut next_operations) = attempt?;
+                operations.append(&mut next_operations);
+                return Some((next_states, operations));
+            }
+            let before = numeric::gap(goal, &states)?;
+            if operation.has_affect(&states, &context.protected_goals) {
+                return None;
+            }
+            let (next_states, mut next_operations) =
+                self.apply_operation(operation.clone(), &states, context)?;
+            if numeric::gap(goal, &next_states).is_none_or(|after| after >= before) {
+                return None;
+            }
+            states = next_states;
+            operations.append(&mut next_operations);
+        }
+        Some((states, operations))
     }
 
     fn apply_operation(
         &self,
         target_operation: Operation,
         current_states: &StateSet,
-        goal_stack: &mut Vec<ConditionImpl>,
-        protected_goals: &mut ConditionSet,
+        context: &mut SearchContext,
     ) -> Option<(StateSet, Vec<Operation>)> {
+        context.enter(|| NodeKind::Operation(target_operation.clone()));
+        let attempt = self.try_apply_operation(target_operation, current_states, context);
+        context.leave(attempt)
+    }
+
+    fn try_apply_operation(
+        &self,
+        target_operation: Operation,
+        current_states: &StateSet,
+        context: &mut SearchContext,
+    ) -> Attempt {
+        if self
+            .limits
+            .max_expansions()
+            .is_some_and(|max| context.statistics.expansions() >= max)
+        {
+            context.limit_exceeded = true;
+            return Err(Failure::LimitExceeded);
+        }
+        context.statistics.record_expansion();
+
         // Achieve all the target operation's prerequisites first.
-        match self.solve_all(
-            target_operation.prerequisites(),
-            current_states,
-            goal_stack,
-            protected_goals,
-        ) {
-            Some((mut next_states, mut operations)) => {
-                target_operation.apply(&mut next_states);
-                operations.push(target_operation);
-                Some((next_states, operations))
-            }
-            None => None,
+        let (mut next_states, mut operations) = self
+            .solve_all(target_operation.prerequisites(), current_states, context)
+            .ok_or(Failure::PrerequisitesFailed)?;
+        target_operation.apply(&mut next_states);
+        self.axioms.derive(&mut next_states);
+        if let Some(bound) = self
+            .resource_bounds
+            .iter()
+            .find(|bound| !bound.allows(&next_states))
+        {
+            return Err(Failure::OutOfBounds(bound.clone()));
+        }
+        if let Some(invariant) = self
+            .invariants
+            .iter()
+            .find(|invariant| !invariant.check(&next_states))
+        {
+            return Err(Failure::BreaksInvariant(invariant.clone()));
+        }
+        if let Err(violation) = context.monitor.progress(&target_operation, &next_states) {
+            return Err(Failure::BreaksConstraint(violation.constraint().clone()));
         }
+        operations.push(target_operation);
+        Ok((next_states, operations))
     }
 }
 
+impl Planner for GeneralProblemSolver {
+    fn name(&self) -> String {
+        "gps".to_owned()
+    }
+
+    fn plan(&self, cancellation: &CancellationToken) -> (Outcome, Statistics) {
+        self.search(cancellation)
+    }
+}
+
+/// The states and operations after achieving something, or why it failed.
+type Attempt = Result<(StateSet, Vec<Operation>), Failure>;
+
+/// Bookkeeping shared by the recursive steps of one search.
+struct SearchContext<'a> {
+    goal_stack: Vec<ConditionImpl>,
+    protected_goals: ConditionSet,
+    cancellation: &'a CancellationToken,
+    statistics: Statistics,
+    /// Whether some branch was cut short by a limit or by cancellation.
+    limit_exceeded: bool,
+    /// Follows the operations applied on the way to the current states.
+    monitor: Monitor,
+    /// Only built when asked for.
+    tree: Option<TreeBuilder>,
+}
+
+impl SearchContext<'_> {
+    fn enter(&mut self, kind: impl FnOnce() -> NodeKind) {
+        if let Some(tree) = &mut self.tree {
+            tree.enter(kind());
+        }
+    }
+
+    fn leave(&mut self, attempt: Attempt) -> Option<(StateSet, Vec<Operation>)> {
+        if let Some(tree) = &mut self.tree {
+            tree.leave(match &attempt {
+                Ok((_, operations)) if operations.is_empty() => NodeStatus::Holds,
+                Ok(_) => NodeStatus::Achieved,
+                Err(failure) => NodeStatus::Failed(failure.clone()),
+            });
+        }
+        attempt.ok()
+    }
+
+    fn record(&mut self, kind: impl FnOnce() -> NodeKind, status: NodeStatus) {
+        if let Some(tree) = &mut self.tree {
+            tree.record(kind(), status);
+        }
+    }
+}
+
+/// The names of the operations in order, to compare plans in tests.
+#[cfg(test)]
+pub(crate) fn names<'a>(operations: impl IntoIterator<Item = &'a Operation>) -> Vec<&'a str> {
+    operations
+        .into_iter()
+        .map(|operation| operation.name())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -243,8 +659,7 @@ mod tests {
                 .build(),
         ]);
 
-        let operations =
-            gps.find_valid_operations(&goal, &StateSet::new(), &mut ConditionSet::new());
+        let operations = gps.find_valid_operations(&goal, &StateSet::new(), &ConditionSet::new());
         assert!(operations
             .iter()
             .find(|operation| operation.name() == "add-state")
@@ -411,6 +826,180 @@ mod tests {
         }
     }
 
+    #[test]
+    fn it_should_repeat_increments_within_resource_bounds() {
+        let mut gps: GeneralProblemSolver = format::text::parse(
+            "state money = 10
+             operation buy-car-on-credit { add have-car modify money -= 30 }
+             operation buy-car { require money >= 30 add have-car modify money -= 30 }
+             operation work { modify money += 5 }
+             goal have-car",
+        )
+        .unwrap()
+        .into();
+        assert_eq!(names(&gps.solve().unwrap()), vec!["buy-car-on-credit"]);
+
+        gps.set_resource_bounds(vec![
+            numeric::ResourceBound::new("money".to_owned()).with_min(0)
+        ]);
+        assert_eq!(
+            names(&gps.solve().unwrap()),
+            vec!["work", "work", "work", "work", "buy-car"]
+        );
+    }
+
+    #[test]
+    fn it_should_combine_modifiers_to_reach_a_value() {
+        let source = "state money = 3
+                      operation five { modify money += 5 }
+                      operation three { modify money += 3 }
+                      goal money == 11";
+        let gps: GeneralProblemSolver = format::text::parse(source).unwrap().into();
+        assert_eq!(names(&gps.solve().unwrap()), vec!["five", "three"]);
+    }
+
+    #[test]
+    fn it_should_not_break_invariants_on_the_way() {
+        let source = "state alarm-armed
+                      operation force-door { add door-open add alarm-ringing }
+                      operation disarm { require alarm-armed remove alarm-armed }
+                      operation open-door { require not alarm-armed add door-open }
+                      operation leave { require door-open add outside }
+                      goal outside";
+        let gps: GeneralProblemSolver = format::text::parse(source).unwrap().into();
+        assert_eq!(names(&gps.solve().unwrap()), vec!["force-door", "leave"]);
+
+        let source = format!("{source}\ninvariant not alarm-ringing");
+        let mut gps: GeneralProblemSolver = format::text::parse(&source).unwrap().into();
+        assert_eq!(
+            names(&gps.solve().unwrap()),
+            vec!["disarm", "open-door", "leave"]
+        );
+
+        let mut states = gps.states().clone();
+        states.insert(State::new_symbol("alarm-ringing".to_owned()));
+        gps.set_states(states);
+        assert!(gps.solve().is_none());
+    }
+
+    #[test]
+    fn it_should_prune_operations_breaking_trajectory_constraints() {
+        let source = "state at-home
+                      operation drive { require at-home add at-work remove at-home }
+                      operation take-bus { require at-home add at-work remove at-home }
+                      operation buy-coffee { require at-home add have-coffee }
+                      goal at-work
+                      sometime-before drive buy-coffee";
+        let gps: GeneralProblemSolver = format::text::parse(source).unwrap().into();
+        assert_eq!(names(&gps.solve().unwrap()), vec!["take-bus"]);
+
+        // Having coffee at some point makes driving fine.
+        let source = format!("{source}\nsometime have-coffee");
+        let gps: GeneralProblemSolver = format::text::parse(&source).unwrap().into();
+        assert_eq!(names(&gps.solve().unwrap()), vec!["buy-coffee", "drive"]);
+    }
+
+    #[test]
+    fn it_should_plan_through_the_rules_of_derived_states() {
+        let source = "state tires-ok
+                      operation charge-battery { add battery-ok }
+                      operation drive { require car-works add at-school }
+                      rule car-works { require new-car }
+                      rule car-works { require battery-ok require tires-ok }
+                      goal at-school";
+        let gps: GeneralProblemSolver = format::text::parse(source).unwrap().into();
+        let plan = gps.solve().unwrap();
+        assert_eq!(names(&plan), vec!["charge-battery", "drive"]);
+        let states =
+            plan::validate(gps.states(), &plan, gps.goals(), &[], &[], gps.axioms()).unwrap();
+        assert!(states.get("car-works").is_some());
+    }
+
+    #[test]
+    fn it_should_not_rely_on_unknown_prerequisites() {
+        let source = "operation enter { require not door-locked add inside }
+                      goal inside";
+        let gps: GeneralProblemSolver = format::text::parse(source).unwrap().into();
+        assert_eq!(names(&gps.solve().unwrap()), vec!["enter"]);
+
+        let source = format!("unknown door-locked\n{source}");
+        let mut gps: GeneralProblemSolver = format::text::parse(&source).unwrap().into();
+        let prerequisite = &gps.operations()[0].prerequisites()[0];
+        assert_eq!(prerequisite.evaluate(gps.states()), Truth::Unknown);
+        let (outcome, _, tree) = gps.search_tree(&CancellationToken::new());
+        assert!(outcome.into_operations().is_none());
+        assert!(tree
+            .nodes()
+            .any(|(_, node)| node.status() == &NodeStatus::Failed(Failure::UnknownState)));
+
+        gps.add_operation(
+            format::text::parse("operation unlock { remove door-locked }")
+                .unwrap()
+                .operations()[0]
+                .clone(),
+        );
+        assert_eq!(names(&gps.solve().unwrap()), vec!["unlock", "enter"]);
+    }
+
+    #[test]
+    fn it_should_keep_the_index_up_to_date_after_adding_and_removing_operations() {
+        let mut gps = GeneralProblemSolver::new();
+        let goal: ConditionImpl = Contain::new("son-at-school".to_owned()).into();
+
+        gps.set_operations(test_operations()).add_operation(
+            OperationBuilder::new("walk-son-to-school".to_owned())
+                .insert_add_state(State::new_symbol("son-at-school".to_owned()))
+                .build(),
+        );
+
+        let operations = gps.find_valid_operations(&goal, &StateSet::new(), &ConditionSet::new());
+        assert_eq!(
+            names(&operations),
+            vec!["drive-son-to-school", "walk-son-to-school"]
+        );
+
+        assert!(gps.remove_operation("drive-son-to-school").is_some());
+        assert!(gps.remove_operation("drive-son-to-school").is_none());
+
+        let operations = gps.find_valid_operations(&goal, &StateSet::new(), &ConditionSet::new());
+        assert_eq!(names(&operations), vec!["walk-son-to-school"]);
+
+        // Positions of the operations after the removed one must be shifted.
+        let goal: ConditionImpl = Contain::new("shop-has-money".to_owned()).into();
+        let operations = gps.find_valid_operations(&goal, &StateSet::new(), &ConditionSet::new());
+        assert_eq!(operations.first().unwrap().name(), "give-shop-money");
+    }
+
+    #[test]
+    fn it_should_tell_unsolvable_problems_from_exceeded_limits() {
+        let mut gps = GeneralProblemSolver::new();
+        gps.set_operations(test_operations())
+            .set_goals(vec![Contain::new("son-at-school".to_owned()).into()])
+            .set_states({
+                let mut states = StateSet::new();
+                states.insert(State::new_symbol("son-at-home".to_owned()));
+                states.insert(State::new_symbol("car-needs-battery".to_owned()));
+                states.insert(State::new_symbol("have-money".to_owned()));
+                states.insert(State::new_symbol("have-phone-book".to_owned()));
+                states
+            });
+
+        let (outcome, statistics) = gps.search(&CancellationToken::new());
+        assert!(outcome.is_solved());
+        assert_eq!(statistics.expansions(), 6);
+        assert_eq!(statistics.max_depth(), 5);
+
+        gps.set_limits(Limits::new().with_max_expansions(3));
+        let (outcome, statistics) = gps.search(&CancellationToken::new());
+        assert!(matches!(outcome, Outcome::LimitExceeded));
+        assert_eq!(statistics.expansions(), 3);
+
+        gps.set_limits(Limits::new())
+            .set_goals(vec![Contain::new("son-at-work".to_owned()).into()]);
+        let (outcome, _) = gps.search(&CancellationToken::new());
+        assert!(matches!(outcome, Outcome::Unsolvable));
+    }
+
     fn test_operations() -> Vec<Operation> {
         vec![
             OperationBuilder::new("drive-son-to-school".to_owned())
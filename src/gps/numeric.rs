@@ -0,0 +1,211 @@
+//! Numeric goals and resources.
+//!
+//! A comparison goal is usually reached by applying an operation which
+//! increases or decreases a state several times. [`applications_needed`]
+//! estimates how many times, ignoring prerequisites and every other operation,
+//! so the solver can try the operations closing the gap fastest first.
+//! [`ResourceBound`]s keep states such as money within limits on every step of
+//! a plan.
+//!
+//! The estimate looks at one operation at a time, so it only orders the
+//! operations and never rules one out. An equality reached by combining
+//! operations, such as going from 3 to 11 by adding 5 and then 3, has no
+//! estimate for either operation, and the solver closes what is left of the
+//! gap after one of them with the others.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use super::condition::{Compare, CompareOperator, Condition};
+use super::operation::Operation;
+use super::state::{StateData, StateSet};
+use super::symbol::Symbol;
+
+/// Limits an integer state must stay within after every step.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResourceBound {
+    state: Symbol,
+    min: Option<i32>,
+    max: Option<i32>,
+}
+
+/// How far the state of the comparison is from satisfying it, 0 if it does.
+/// `None` if the state or the value compared with is not an integer.
+pub fn gap(condition: &Compare, states: &StateSet) -> Option<u32> {
+    let Some(StateData::Integer(value)) = states.get_by_symbol(condition.state_symbol()) else {
+        return None;
+    };
+    let StateData::Integer(target) = condition.value() else {
+        return None;
+    };
+    let (value, target) = (i64::from(*value), i64::from(*target));
+    let gap = match condition.operator() {
+        CompareOperator::Equal => (target - value).abs(),
+        CompareOperator::NotEqual => i64::from(value == target),
+        CompareOperator::Greater => target + 1 - value,
+        CompareOperator::GreaterEqual => target - value,
+        CompareOperator::Less => value - target + 1,
+        CompareOperator::LessEqual => value - target,
+    };
+    Some(u32::try_from(gap.max(0)).unwrap_or(u32::MAX))
+}
+
+/// How many times the operation must be applied in a row for the comparison
+/// to hold, assuming its prerequisites always do and every application
+/// changes the state as much as the first. `None` if applying it does not
+/// get the state any closer, or would step over the value of an equality.
+///
+/// Nothing is known about states which are not integers yet, so operations
+/// modifying them are assumed to take one application.
+pub fn applications_needed(
+    operation: &Operation,
+    condition: &Compare,
+    states: &StateSet,
+) -> Option<u32> {
+    let Some(before) = gap(condition, states) else {
+        return Some(1);
+    };
+    if before == 0 {
+        return Some(0);
+    }
+
+    let mut next_states = states.clone();
+    operation.apply(&mut next_states);
+    let after = gap(condition, &next_states)?;
+    if after == 0 {
+        return Some(1);
+    }
+    let progress = before.checked_sub(after).filter(|progress| *progress > 0)?;
+    if condition.operator() == &CompareOperator::Equal {
+        let value = |states: &StateSet| match states.get_by_symbol(condition.state_symbol()) {
+            Some(StateData::Integer(value)) => Some(i64::from(*value)),
+            _ => None,
+        };
+        let step = u32::try_from((value(&next_states)? - value(states)?).abs()).ok()?;
+        if before % step != 0 {
+            // The state would step over the value.
+            return None;
+        }
+        return Some(before / step);
+    }
+    Some(before.div_ceil(progress))
+}
+
+impl ResourceBound {
+    /// A bound without limits on the state.
+    pub fn new(state: String) -> Self {
+        Self {
+            state: state.into(),
+            min: None,
+            max: None,
+        }
+    }
+
+    /// Keep the state at or above the value.
+    pub fn with_min(mut self, min: i32) -> Self {
+        self.min = Some(min);
+        self
+    }
+
+    /// Keep the state at or below the value.
+    pub fn with_max(mut self, max: i32) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    pub fn state_symbol(&self) -> Symbol {
+        self.state
+    }
+
+    pub fn min(&self) -> Option<i32> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<i32> {
+        self.max
+    }
+
+    /// Whether the state is within the limits. States which do not hold or
+    /// are not integers are not limited.
+    pub fn allows(&self, states: &StateSet) -> bool {
+        match states.get_by_symbol(self.state) {
+            Some(StateData::Integer(value)) => {
+                self.min.is_none_or(|min| min <= *value) && self.max.is_none_or(|max| *value <= max)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Written like `0 <= money <= 100`.
+impl Display for ResourceBound {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        if let Some(min) = self.min {
+            write!(f, "{min} <= ")?;
+        }
+        write!(f, "{}", self.state)?;
+        if let Some(max) = self.max {
+            write!(f, " <= {max}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps::operation::{Modification, OperationBuilder};
+    use crate::gps::state::State;
+
+    #[test]
+    fn it_should_count_the_applications_closing_the_gap() {
+        let states = {
+            let mut states = StateSet::new();
+            states.insert(State::new_integer("money".to_owned(), 3));
+            states
+        };
+        let goal = |operator, value| {
+            Compare::new(
+                "goal".to_owned(),
+                "money".to_owned(),
+                operator,
+                StateData::Integer(value),
+            )
+        };
+        let earn = OperationBuilder::new("earn".to_owned())
+            .insert_modify_state(Modification::increase("money".to_owned(), 5))
+            .build();
+        let spend = OperationBuilder::new("spend".to_owned())
+            .insert_modify_state(Modification::decrease("money".to_owned(), 2))
+            .build();
+
+        assert_eq!(
+            gap(&goal(CompareOperator::GreaterEqual, 20), &states),
+            Some(17)
+        );
+        assert_eq!(gap(&goal(CompareOperator::Less, 0), &states), Some(4));
+        assert_eq!(
+            applications_needed(&earn, &goal(CompareOperator::GreaterEqual, 20), &states),
+            Some(4)
+        );
+        assert_eq!(
+            applications_needed(&spend, &goal(CompareOperator::GreaterEqual, 20), &states),
+            None
+        );
+        assert_eq!(
+            applications_needed(&spend, &goal(CompareOperator::Equal, 0), &states),
+            None
+        );
+        assert_eq!(
+            applications_needed(&earn, &goal(CompareOperator::Equal, 13), &states),
+            Some(2)
+        );
+        assert_eq!(
+            applications_needed(&earn, &goal(CompareOperator::Equal, 6), &states),
+            None
+        );
+
+        let bound = ResourceBound::new("money".to_owned()).with_min(0);
+        assert!(bound.allows(&states));
+        assert_eq!(bound.to_string(), "0 <= money");
+    }
+}
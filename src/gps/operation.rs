@@ -1,26 +1,39 @@
 use std::fmt::{Debug, Formatter, Result as FmtResult};
-use std::rc::Rc;
+use std::sync::Arc;
 
 use super::condition::{Condition, ConditionImpl, ConditionSet};
 use super::state::{State, StateData, StateSet};
+use super::symbol::Symbol;
 
 #[derive(Debug, Clone)]
 pub struct Operation {
-    inner: Rc<OperationInner>,
+    inner: Arc<OperationInner>,
 }
 
 #[derive(Debug)]
 struct OperationInner {
-    name: String,
+    name: Symbol,
     prerequisites: Vec<ConditionImpl>,
     add_states: Vec<State>,
-    remove_states: Vec<String>,
+    remove_states: Vec<Symbol>,
     modify_states: Vec<Modification>,
+    cost: u32,
 }
 
+#[derive(Clone)]
 pub struct Modification {
-    target_name: String,
-    modification: Box<dyn Fn(&mut StateData)>,
+    target_name: Symbol,
+    effect: ModificationEffect,
+}
+
+/// How a modification changes its target state. The declarative variants can
+/// be inspected and written out, while custom closures are opaque.
+#[derive(Clone)]
+pub enum ModificationEffect {
+    Increase(i32),
+    Decrease(i32),
+    Assign(StateData),
+    Custom(Arc<dyn Fn(&mut StateData) + Send + Sync>),
 }
 
 pub struct OperationBuilder {
@@ -29,7 +42,11 @@ pub struct OperationBuilder {
 
 impl Operation {
     pub fn name(&self) -> &str {
-        &self.inner.name
+        self.inner.name.as_str()
+    }
+
+    pub fn symbol(&self) -> Symbol {
+        self.inner.name
     }
 
     pub fn prerequisites(&self) -> &Vec<ConditionImpl> {
@@ -40,7 +57,7 @@ impl Operation {
         &self.inner.add_states
     }
 
-    pub fn remove_states(&self) -> &Vec<String> {
+    pub fn remove_states(&self) -> &Vec<Symbol> {
         &self.inner.remove_states
     }
 
@@ -48,23 +65,39 @@ impl Operation {
         &self.inner.modify_states
     }
 
+    /// What applying the operation costs, 1 unless set otherwise.
+    pub fn cost(&self) -> u32 {
+        self.inner.cost
+    }
+
+    /// Test if all prerequisites hold in the given states.
+    pub fn is_applicable(&self, state_set: &StateSet) -> bool {
+        self.prerequisites()
+            .iter()
+            .all(|condition| condition.check(state_set))
+    }
+
+    /// The prerequisites which do not hold in the given states.
+    pub fn unsatisfied_prerequisites(&self, state_set: &StateSet) -> Vec<&ConditionImpl> {
+        self.prerequisites()
+            .iter()
+            .filter(|condition| !condition.check(state_set))
+            .collect()
+    }
+
     pub fn apply(&self, state_set: &mut StateSet) {
         for s in &self.inner.add_states {
             state_set.insert(s.clone());
         }
 
         for s in &self.inner.remove_states {
-            state_set.remove(s);
+            state_set.remove_by_symbol(*s);
         }
 
         for s in &self.inner.modify_states {
-            let Modification {
-                target_name: name,
-                modification,
-            } = s;
-            state_set
-                .get_mut(&name)
-                .and_then(|state| Some(modification(state)));
+            if let Some(state) = state_set.get_mut_by_symbol(s.target_name) {
+                s.apply(state);
+            }
         }
     }
 
@@ -72,12 +105,12 @@ impl Operation {
     /// goals.
     pub fn has_affect(&self, current_states: &StateSet, goals: &ConditionSet) -> bool {
         for state in self.add_states() {
-            let Some(conds) = goals.get(state.name()) else {
+            let Some(conds) = goals.get(state.symbol()) else {
                 continue;
             };
 
             if conds.iter().any(|cond| {
-                if cond.state_name() != state.name() {
+                if cond.state_symbol() != state.symbol() {
                     return false;
                 }
                 matches!(cond, ConditionImpl::NotContain(_))
@@ -87,72 +120,126 @@ impl Operation {
         }
 
         for state_name in self.remove_states() {
-            let Some(conds) = goals.get(state_name) else {
+            let Some(conds) = goals.get(*state_name) else {
                 continue;
             };
 
             if conds.iter().any(|cond| {
-                if cond.state_name() != state_name {
+                if cond.state_symbol() != *state_name {
                     return false;
                 }
-                if matches!(cond, ConditionImpl::Contain(_)) {
-                    true
-                } else if matches!(cond, ConditionImpl::Compare(_)) {
-                    true
-                } else {
-                    false
-                }
+                matches!(cond, ConditionImpl::Contain(_) | ConditionImpl::Compare(_))
             }) {
                 return true;
             }
         }
 
         for modification in self.modification_states() {
-            let Some(conds) = goals.get(modification.target_name()) else {
+            let Some(conds) = goals.get(modification.target_symbol()) else {
                 continue;
             };
 
             if conds.iter().any(|cond| {
-                let Some(state_data) = current_states.get(modification.target_name()) else {
+                let Some(state_data) = current_states.get_by_symbol(modification.target_symbol())
+                else {
                     return false;
                 };
 
                 let mut tmp = state_data.clone();
-                (modification.modification)(&mut tmp);
+                modification.apply(&mut tmp);
                 !cond.check_data(&tmp)
             }) {
                 return true;
             }
         }
 
-        return false;
+        false
     }
 }
 
 impl From<OperationInner> for Operation {
     fn from(value: OperationInner) -> Self {
         Self {
-            inner: Rc::new(value),
+            inner: Arc::new(value),
         }
     }
 }
 
 impl Modification {
-    pub fn new(target_name: String, modification: Box<dyn Fn(&mut StateData)>) -> Self {
+    pub fn new(
+        target_name: String,
+        modification: Box<dyn Fn(&mut StateData) + Send + Sync>,
+    ) -> Self {
+        Self::with_effect(target_name, ModificationEffect::Custom(modification.into()))
+    }
+
+    pub fn with_effect(target_name: String, effect: ModificationEffect) -> Self {
         Self {
-            target_name,
-            modification,
+            target_name: target_name.into(),
+            effect,
+        }
+    }
+
+    pub fn increase(target_name: String, amount: i32) -> Self {
+        Self::with_effect(target_name, ModificationEffect::Increase(amount))
+    }
+
+    pub fn decrease(target_name: String, amount: i32) -> Self {
+        Self::with_effect(target_name, ModificationEffect::Decrease(amount))
+    }
+
+    pub fn assign(target_name: String, value: StateData) -> Self {
+        Self::with_effect(target_name, ModificationEffect::Assign(value))
+    }
+
+    pub fn effect(&self) -> &ModificationEffect {
+        &self.effect
+    }
+
+    pub fn apply(&self, state_data: &mut StateData) {
+        match &self.effect {
+            ModificationEffect::Increase(amount) => {
+                if let StateData::Integer(x) = state_data {
+                    *x = x.saturating_add(*amount);
+                }
+            }
+            ModificationEffect::Decrease(amount) => {
+                if let StateData::Integer(x) = state_data {
+                    *x = x.saturating_sub(*amount);
+                }
+            }
+            ModificationEffect::Assign(value) => *state_data = value.clone(),
+            ModificationEffect::Custom(modification) => modification(state_data),
         }
     }
 
     pub fn target_name(&self) -> &str {
-        &self.target_name
+        self.target_name.as_str()
+    }
+
+    pub fn target_symbol(&self) -> Symbol {
+        self.target_name
     }
 }
 
 impl Debug for Modification {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        write!(f, "Modification {{ name: {} }}", self.target_name)
+        write!(
+            f,
+            "Modification {{ name: {}, effect: {:?} }}",
+            self.target_name, self.effect
+        )
+    }
+}
+
+impl Debug for ModificationEffect {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            ModificationEffect::Increase(amount) => write!(f, "Increase({amount})"),
+            ModificationEffect::Decrease(amount) => write!(f, "Decrease({amount})"),
+            ModificationEffect::Assign(value) => write!(f, "Assign({value:?})"),
+            ModificationEffect::Custom(_) => write!(f, "Custom"),
+        }
     }
 }
 
@@ -160,11 +247,12 @@ impl OperationBuilder {
     pub fn new(name: String) -> Self {
         Self {
             object: OperationInner {
-                name,
+                name: name.into(),
                 prerequisites: Vec::new(),
                 add_states: Vec::new(),
                 remove_states: Vec::new(),
                 modify_states: Vec::new(),
+                cost: 1,
             },
         }
     }
@@ -180,7 +268,7 @@ impl OperationBuilder {
     }
 
     pub fn insert_remove_state(mut self, remove_state: String) -> Self {
-        self.object.remove_states.push(remove_state);
+        self.object.remove_states.push(remove_state.into());
         self
     }
 
@@ -189,6 +277,11 @@ impl OperationBuilder {
         self
     }
 
+    pub fn set_cost(mut self, cost: u32) -> Self {
+        self.object.cost = cost;
+        self
+    }
+
     pub fn build(self) -> Operation {
         self.object.into()
     }
@@ -0,0 +1,444 @@
+//! Carrying out plans in a world which may not behave as the operations say.
+//!
+//! An [`Executor`] hands each step of a plan to an [`ActionHandler`], which
+//! performs it and reports the states it observes afterwards. Whenever a step
+//! fails, or the observed states no longer let the rest of the plan reach the
+//! goals, the executor plans again from the observed states.
+
+use std::collections::{HashMap, HashSet};
+
+use super::validate;
+use crate::gps::operation::Operation;
+use crate::gps::state::{State, StateData, StateSet};
+use crate::gps::symbol::Symbol;
+use crate::gps::GeneralProblemSolver;
+
+/// Performs operations in the world and observes it.
+pub trait ActionHandler {
+    /// Carry out the operation, or tell why it could not be done.
+    fn perform(&mut self, operation: &Operation) -> Result<(), String>;
+
+    /// The states holding in the world right now.
+    fn observe(&mut self) -> StateSet;
+}
+
+pub struct Executor<H> {
+    solver: GeneralProblemSolver,
+    handler: H,
+    max_replans: usize,
+}
+
+/// What happened while executing a plan.
+#[derive(Debug, Clone)]
+pub struct Execution {
+    outcome: ExecutionOutcome,
+    events: Vec<Event>,
+    states: StateSet,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionOutcome {
+    /// The goals hold in the observed states.
+    Reached,
+    /// No plan reaches the goals from the observed states.
+    Unreachable,
+    /// The world kept diverging until the executor stopped planning again.
+    ReplanLimitExceeded,
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// The step was performed. The divergences tell where the observed
+    /// states differ from those the operation should have produced.
+    Performed {
+        operation: Operation,
+        divergences: Vec<Divergence>,
+    },
+    Failed {
+        operation: Operation,
+        reason: String,
+    },
+    /// A new plan replaced the rest of the previous one.
+    Replanned { plan: Vec<Operation> },
+}
+
+/// A state whose observed value is not the expected one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    state: Symbol,
+    expected: Knowledge,
+    observed: Knowledge,
+}
+
+/// What is known about a state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Knowledge {
+    Holds(StateData),
+    DoesNotHold,
+    /// Whether the state holds and its value are not known.
+    Unknown,
+}
+
+/// A handler which applies operations to states of its own, for testing
+/// executors. Failures and unexpected changes can be injected per operation.
+#[derive(Debug, Clone)]
+pub struct MockHandler {
+    states: StateSet,
+    failures: HashMap<Symbol, usize>,
+    disturbances: HashMap<Symbol, Vec<Disturbance>>,
+    performed: Vec<Operation>,
+}
+
+#[derive(Debug, Clone)]
+enum Disturbance {
+    Add(State),
+    Remove(Symbol),
+}
+
+impl<H: ActionHandler> Executor<H> {
+    /// An executor planning again with the operations and goals of the
+    /// solver. The states of the solver are not used, since the handler
+    /// observes the real ones.
+    pub fn new(solver: GeneralProblemSolver, handler: H) -> Self {
+        Self {
+            solver,
+            handler,
+            max_replans: 10,
+        }
+    }
+
+    /// Stop after planning again this many times.
+    pub fn with_max_replans(mut self, max_replans: usize) -> Self {
+        self.max_replans = max_replans;
+        self
+    }
+
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    pub fn into_handler(self) -> H {
+        self.handler
+    }
+
+    /// Execute the plan until the goals hold or no plan reaches them.
+    pub fn execute(&mut self, plan: Vec<Operation>) -> Execution {
+        let goals = self.solver.goals().clone();
+        let invariants = self.solver.invariants().clone();
+        let bounds = self.solver.resource_bounds().clone();
+        let axioms = self.solver.axioms().clone();
+        let mut events = Vec::new();
+        let mut states = self.handler.observe();
+        axioms.derive(&mut states);
+        let mut plan = plan;
+        let mut step = 0;
+        let mut failed = false;
+        let mut replans = 0;
+
+        loop {
+            let on_track = !failed
+                && validate(
+                    &states,
+                    &plan[step..],
+                    &goals,
+                    &invariants,
+                    &bounds,
+                    &axioms,
+                )
+                .is_ok();
+            if !on_track {
+                if states.has_reached(&goals) {
+                    break ExecutionOutcome::Reached.into_execution(events, states);
+                }
+                if replans == self.max_replans {
+                    break ExecutionOutcome::ReplanLimitExceeded.into_execution(events, states);
+                }
+                replans += 1;
+
+                self.solver.set_states(states.clone());
+                let Some(new_plan) = self.solver.solve() else {
+                    break ExecutionOutcome::Unreachable.into_execution(events, states);
+                };
+                events.push(Event::Replanned {
+                    plan: new_plan.clone(),
+                });
+                plan = new_plan;
+                step = 0;
+                failed = false;
+                continue;
+            }
+
+            let Some(operation) = plan.get(step).cloned() else {
+                break ExecutionOutcome::Reached.into_execution(events, states);
+            };
+            let mut expected = states.clone();
+            operation.apply(&mut expected);
+            axioms.derive(&mut expected);
+
+            let result = self.handler.perform(&operation);
+            states = self.handler.observe();
+            axioms.derive(&mut states);
+            match result {
+                Ok(()) => {
+                    events.push(Event::Performed {
+                        operation,
+                        divergences: divergences(&expected, &states),
+                    });
+                    step += 1;
+                }
+                Err(reason) => {
+                    events.push(Event::Failed { operation, reason });
+                    failed = true;
+                }
+            }
+        }
+    }
+}
+
+impl ExecutionOutcome {
+    fn into_execution(self, events: Vec<Event>, states: StateSet) -> Execution {
+        Execution {
+            outcome: self,
+            events,
+            states,
+        }
+    }
+}
+
+impl Execution {
+    pub fn outcome(&self) -> ExecutionOutcome {
+        self.outcome
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// The states observed last.
+    pub fn states(&self) -> &StateSet {
+        &self.states
+    }
+
+    /// The operations performed successfully, in order.
+    pub fn performed(&self) -> impl Iterator<Item = &Operation> {
+        self.events.iter().filter_map(|event| match event {
+            Event::Performed { operation, .. } => Some(operation),
+            _ => None,
+        })
+    }
+
+    pub fn replans(&self) -> usize {
+        self.events
+            .iter()
+            .filter(|event| matches!(event, Event::Replanned { .. }))
+            .count()
+    }
+}
+
+impl Divergence {
+    pub fn state(&self) -> Symbol {
+        self.state
+    }
+
+    pub fn expected(&self) -> &Knowledge {
+        &self.expected
+    }
+
+    pub fn observed(&self) -> &Knowledge {
+        &self.observed
+    }
+}
+
+impl Knowledge {
+    fn of(states: &StateSet, state: Symbol) -> Self {
+        match states.get_by_symbol(state) {
+            Some(data) => Knowledge::Holds(data.clone()),
+            None if states.is_unknown(state) => Knowledge::Unknown,
+            None => Knowledge::DoesNotHold,
+        }
+    }
+}
+
+/// The states differing between the expected and the observed ones, sorted
+/// by name. States becoming known or unknown count as differing.
+fn divergences(expected: &StateSet, observed: &StateSet) -> Vec<Divergence> {
+    let names: HashSet<_> = expected
+        .iter()
+        .chain(observed.iter())
+        .map(|(name, _)| name)
+        .chain(expected.unknown())
+        .chain(observed.unknown())
+        .collect();
+    let mut divergences: Vec<_> = names
+        .into_iter()
+        .map(|name| Divergence {
+            state: name,
+            expected: Knowledge::of(expected, name),
+            observed: Knowledge::of(observed, name),
+        })
+        .filter(|divergence| divergence.expected != divergence.observed)
+        .collect();
+    divergences.sort_by_key(|divergence| divergence.state.as_str());
+    divergences
+}
+
+impl MockHandler {
+    /// A handler whose world starts in the given states.
+    pub fn new(states: StateSet) -> Self {
+        Self {
+            states,
+            failures: HashMap::new(),
+            disturbances: HashMap::new(),
+            performed: Vec::new(),
+        }
+    }
+
+    /// Fail the next `times` attempts to perform the operation, leaving the
+    /// states as they are.
+    pub fn with_failures(mut self, operation: &str, times: usize) -> Self {
+        self.failures.insert(operation.into(), times);
+        self
+    }
+
+    /// Make the state hold after performing the operation, as if something
+    /// else had happened at the same time.
+    pub fn with_addition(mut self, operation: &str, state: State) -> Self {
+        self.disturbances
+            .entry(operation.into())
+            .or_default()
+            .push(Disturbance::Add(state));
+        self
+    }
+
+    /// Remove the state after performing the operation.
+    pub fn with_removal(mut self, operation: &str, state: &str) -> Self {
+        self.disturbances
+            .entry(operation.into())
+            .or_default()
+            .push(Disturbance::Remove(state.into()));
+        self
+    }
+
+    /// The operations performed successfully, in order.
+    pub fn performed(&self) -> &[Operation] {
+        &self.performed
+    }
+}
+
+impl ActionHandler for MockHandler {
+    fn perform(&mut self, operation: &Operation) -> Result<(), String> {
+        if let Some(failures) = self.failures.get_mut(&operation.symbol()) {
+            if *failures > 0 {
+                *failures -= 1;
+                return Err(format!("`{}` failed", operation.name()));
+            }
+        }
+        if !operation.is_applicable(&self.states) {
+            return Err(format!("`{}` is not applicable", operation.name()));
+        }
+
+        operation.apply(&mut self.states);
+        for disturbance in self
+            .disturbances
+            .get(&operation.symbol())
+            .into_iter()
+            .flatten()
+        {
+            match disturbance {
+                Disturbance::Add(state) => {
+                    self.states.insert(state.clone());
+                }
+                Disturbance::Remove(name) => {
+                    self.states.remove_by_symbol(*name);
+                }
+            }
+        }
+        self.performed.push(operation.clone());
+        Ok(())
+    }
+
+    fn observe(&mut self) -> StateSet {
+        self.states.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps::domains::school;
+    use crate::gps::names;
+
+    #[test]
+    fn it_should_execute_a_plan_as_is_when_nothing_goes_wrong() {
+        let solver: GeneralProblemSolver = school::problem_with_battery().into();
+        let plan = solver.solve().unwrap();
+        let handler = MockHandler::new(solver.states().clone());
+        let mut executor = Executor::new(solver, handler);
+
+        let execution = executor.execute(plan.clone());
+        assert_eq!(execution.outcome(), ExecutionOutcome::Reached);
+        assert_eq!(execution.replans(), 0);
+        assert_eq!(names(execution.performed()), names(&plan));
+        assert!(execution.events().iter().all(|event| matches!(
+            event,
+            Event::Performed { divergences, .. } if divergences.is_empty()
+        )));
+    }
+
+    #[test]
+    fn it_should_replan_after_failures_and_divergences() {
+        let solver: GeneralProblemSolver = school::problem_with_battery().into();
+        let plan = solver.solve().unwrap();
+        assert_eq!(plan[0].name(), "look-up-number");
+
+        // Looking up the number fails once, and the phone book gets lost
+        // after the call, but the number is still known.
+        let handler = MockHandler::new(solver.states().clone())
+            .with_failures("look-up-number", 1)
+            .with_removal("telephone-shop", "have-phone-book");
+        let mut executor = Executor::new(solver.clone(), handler);
+        let execution = executor.execute(plan.clone());
+
+        assert_eq!(execution.outcome(), ExecutionOutcome::Reached);
+        assert!(matches!(&execution.events()[0], Event::Failed { .. }));
+        assert!(matches!(&execution.events()[1], Event::Replanned { .. }));
+        let divergences: Vec<_> = execution
+            .events()
+            .iter()
+            .flat_map(|event| match event {
+                Event::Performed { divergences, .. } => divergences.clone(),
+                _ => Vec::new(),
+            })
+            .collect();
+        assert_eq!(divergences.len(), 1);
+        assert_eq!(divergences[0].state().as_str(), "have-phone-book");
+        assert_eq!(
+            divergences[0].expected(),
+            &Knowledge::Holds(StateData::Symbol)
+        );
+        assert_eq!(divergences[0].observed(), &Knowledge::DoesNotHold);
+        // The lost phone book does not matter to the rest of the plan.
+        assert_eq!(execution.replans(), 1);
+        assert_eq!(names(executor.handler().performed()), names(&plan));
+
+        // Without money nothing can get the son to school.
+        let handler =
+            MockHandler::new(solver.states().clone()).with_removal("look-up-number", "have-money");
+        let mut executor = Executor::new(solver, handler);
+        let execution = executor.execute(plan);
+        assert_eq!(execution.outcome(), ExecutionOutcome::Unreachable);
+        assert_eq!(names(execution.performed()), vec!["look-up-number"]);
+
+        // A state nobody can observe any more diverges as well.
+        let expected = school::problem_with_battery().states().clone();
+        let mut observed = expected.clone();
+        observed.insert_unknown("have-money".into());
+        let forgotten = super::divergences(&expected, &observed);
+        assert_eq!(forgotten.len(), 1);
+        assert_eq!(
+            forgotten[0].expected(),
+            &Knowledge::Holds(StateData::Symbol)
+        );
+        assert_eq!(forgotten[0].observed(), &Knowledge::Unknown);
+    }
+}
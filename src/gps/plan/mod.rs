@@ -0,0 +1,411 @@
+//! Analysis and execution of plans found by a solver.
+
+pub mod execute;
+pub mod optimize;
+pub mod repair;
+pub mod schedule;
+
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use super::axiom::Axioms;
+use super::condition::{Condition, ConditionImpl};
+use super::format::text::write_condition;
+use super::numeric::ResourceBound;
+use super::operation::Operation;
+use super::state::StateSet;
+use super::symbol::Symbol;
+use super::trajectory::{Constraint, Proposition};
+
+/// Where a causal link starts or ends: the initial states, a step of the
+/// plan given by its position, or the goals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Step {
+    Initial,
+    Operation(usize),
+    Goal,
+}
+
+/// A condition needed by one step and provided by an earlier one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CausalLink {
+    producer: Step,
+    consumer: Step,
+    condition: ConditionImpl,
+}
+
+impl CausalLink {
+    pub fn producer(&self) -> Step {
+        self.producer
+    }
+
+    pub fn consumer(&self) -> Step {
+        self.consumer
+    }
+
+    pub fn condition(&self) -> &ConditionImpl {
+        &self.condition
+    }
+}
+
+/// Why a plan does not work.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanError {
+    /// A prerequisite of the step at the position does not hold when its
+    /// turn comes.
+    NotApplicable {
+        step: usize,
+        prerequisite: ConditionImpl,
+    },
+    /// The invariant does not hold initially or after the step.
+    InvariantBroken {
+        step: Step,
+        invariant: ConditionImpl,
+    },
+    /// The resource is out of its bounds initially or after the step.
+    OutOfBounds {
+        step: Step,
+        bound: ResourceBound,
+    },
+    GoalFailed(ConditionImpl),
+}
+
+/// The total cost of the steps of a plan, summed in `u64` so that no plan
+/// overflows it.
+pub fn cost(plan: &[Operation]) -> u64 {
+    plan.iter()
+        .map(|operation| u64::from(operation.cost()))
+        .sum()
+}
+
+/// Apply the steps of a plan in order and return the resulting states, or
+/// `None` if some step is not applicable when its turn comes. Derived states
+/// are recomputed from the axioms initially and after every step.
+pub fn simulate(states: &StateSet, plan: &[Operation], axioms: &Axioms) -> Option<StateSet> {
+    let mut states = states.clone();
+    axioms.derive(&mut states);
+    for operation in plan {
+        if !operation.is_applicable(&states) {
+            return None;
+        }
+        operation.apply(&mut states);
+        axioms.derive(&mut states);
+    }
+    Some(states)
+}
+
+/// Apply the steps of a plan in order and return the resulting states, or
+/// tell the first step which is not applicable or breaks an invariant or a
+/// resource bound, or the first goal which does not hold at the end. Derived
+/// states are recomputed from the axioms initially and after every step.
+pub fn validate(
+    states: &StateSet,
+    plan: &[Operation],
+    goals: &[ConditionImpl],
+    invariants: &[ConditionImpl],
+    bounds: &[ResourceBound],
+    axioms: &Axioms,
+) -> Result<StateSet, PlanError> {
+    let broken = |states: &StateSet, step| {
+        let invariant = invariants
+            .iter()
+            .find(|invariant| !invariant.check(states))
+            .map(|invariant| PlanError::InvariantBroken {
+                step,
+                invariant: invariant.clone(),
+            });
+        invariant.or_else(|| {
+            bounds
+                .iter()
+                .find(|bound| !bound.allows(states))
+                .map(|bound| PlanError::OutOfBounds {
+                    step,
+                    bound: bound.clone(),
+                })
+        })
+    };
+
+    let mut states = states.clone();
+    axioms.derive(&mut states);
+    if let Some(error) = broken(&states, Step::Initial) {
+        return Err(error);
+    }
+    for (position, operation) in plan.iter().enumerate() {
+        if let Some(prerequisite) = operation.unsatisfied_prerequisites(&states).first() {
+            return Err(PlanError::NotApplicable {
+                step: position,
+                prerequisite: (*prerequisite).clone(),
+            });
+        }
+        operation.apply(&mut states);
+        axioms.derive(&mut states);
+        if let Some(error) = broken(&states, Step::Operation(position)) {
+            return Err(error);
+        }
+    }
+    match goals.iter().find(|goal| !goal.check(&states)) {
+        Some(goal) => Err(PlanError::GoalFailed(goal.clone())),
+        None => Ok(states),
+    }
+}
+
+/// Find the causal links of a plan by simulating it from the given states.
+/// Each prerequisite and goal which holds when needed is linked to the last
+/// step adding, removing or modifying its state before then, or for a derived
+/// state the last step changing it through the axioms. Conditions that do not
+/// hold get no link.
+pub fn causal_links(
+    states: &StateSet,
+    plan: &[Operation],
+    goals: &[ConditionImpl],
+    axioms: &Axioms,
+) -> Vec<CausalLink> {
+    let mut states = states.clone();
+    axioms.derive(&mut states);
+    let mut last_writers: HashMap<Symbol, Step> = HashMap::new();
+    let mut links = Vec::new();
+
+    let mut link = |conditions: &[ConditionImpl],
+                    consumer: Step,
+                    states: &StateSet,
+                    last_writers: &HashMap<Symbol, Step>| {
+        for condition in conditions {
+            if condition.check(states) {
+                links.push(CausalLink {
+                    producer: last_writers
+                        .get(&condition.state_symbol())
+                        .copied()
+                        .unwrap_or(Step::Initial),
+                    consumer,
+                    condition: condition.clone(),
+                });
+            }
+        }
+    };
+
+    for (position, operation) in plan.iter().enumerate() {
+        let step = Step::Operation(position);
+        link(operation.prerequisites(), step, &states, &last_writers);
+
+        let before = states.clone();
+        operation.apply(&mut states);
+        axioms.derive(&mut states);
+        for state_name in written_states(operation) {
+            last_writers.insert(state_name, step);
+        }
+        for rule in axioms.rules() {
+            let head = rule.head();
+            if before.get_by_symbol(head) != states.get_by_symbol(head)
+                || before.is_unknown(head) != states.is_unknown(head)
+            {
+                last_writers.insert(head, step);
+            }
+        }
+    }
+
+    link(goals, Step::Goal, &states, &last_writers);
+    links
+}
+
+/// The states an operation adds, removes or modifies.
+fn written_states(operation: &Operation) -> BTreeSet<Symbol> {
+    operation
+        .add_states()
+        .iter()
+        .map(|state| state.symbol())
+        .chain(operation.remove_states().iter().copied())
+        .chain(
+            operation
+                .modification_states()
+                .iter()
+                .map(|modification| modification.target_symbol()),
+        )
+        .collect()
+}
+
+/// For each step of the plan and each constraint, whether the step could
+/// change whether one of the propositions of the constraint holds.
+fn touched_constraints(
+    plan: &[Operation],
+    constraints: &[Constraint],
+    axioms: &Axioms,
+) -> Vec<Vec<bool>> {
+    let touches = |operation: &Operation, proposition: &Proposition| match proposition {
+        Proposition::Condition(condition) => {
+            let dependencies = axioms.dependencies(condition.state_symbol());
+            written_states(operation)
+                .iter()
+                .any(|state| dependencies.contains(state))
+        }
+        Proposition::Operation(name) => operation.symbol() == *name,
+    };
+    plan.iter()
+        .map(|operation| {
+            constraints
+                .iter()
+                .map(|constraint| {
+                    constraint
+                        .propositions()
+                        .into_iter()
+                        .any(|proposition| touches(operation, proposition))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Steps are counted from 1.
+impl Display for PlanError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            PlanError::NotApplicable { step, prerequisite } => write!(
+                f,
+                "step {}: prerequisite `{}` does not hold",
+                step + 1,
+                write_condition(prerequisite)
+            ),
+            PlanError::InvariantBroken {
+                step: Step::Operation(position),
+                invariant,
+            } => write!(
+                f,
+                "step {}: invariant `{}` does not hold afterwards",
+                position + 1,
+                write_condition(invariant)
+            ),
+            PlanError::InvariantBroken { invariant, .. } => {
+                write!(
+                    f,
+                    "invariant `{}` does not hold initially",
+                    write_condition(invariant)
+                )
+            }
+            PlanError::OutOfBounds {
+                step: Step::Operation(position),
+                bound,
+            } => write!(
+                f,
+                "step {}: resource bound `{bound}` does not hold afterwards",
+                position + 1
+            ),
+            PlanError::OutOfBounds { bound, .. } => {
+                write!(f, "resource bound `{bound}` does not hold initially")
+            }
+            PlanError::GoalFailed(goal) => {
+                write!(
+                    f,
+                    "goal `{}` does not hold at the end",
+                    write_condition(goal)
+                )
+            }
+        }
+    }
+}
+
+impl Error for PlanError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps::format::text;
+
+    #[test]
+    fn it_should_link_each_condition_to_its_last_writer() {
+        let problem = text::parse(
+            "state have-money
+             state car-needs-battery
+             operation give-shop-money { require have-money add shop-has-money remove have-money }
+             operation install-battery { require shop-has-money add car-works }
+             operation drive { require car-works require not have-money add at-school }
+             goal at-school
+             goal car-works",
+        )
+        .unwrap();
+
+        let links = causal_links(
+            problem.states(),
+            problem.operations(),
+            problem.goals(),
+            problem.axioms(),
+        );
+        let summary: Vec<_> = links
+            .iter()
+            .map(|link| (link.producer(), link.consumer(), link.condition().name()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Step::Initial, Step::Operation(0), "have-money"),
+                (Step::Operation(0), Step::Operation(1), "shop-has-money"),
+                (Step::Operation(1), Step::Operation(2), "car-works"),
+                (Step::Operation(0), Step::Operation(2), "have-money"),
+                (Step::Operation(2), Step::Goal, "at-school"),
+                (Step::Operation(1), Step::Goal, "car-works"),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_report_the_step_breaking_an_invariant() {
+        let problem = text::parse(
+            "operation force-door { add door-open add alarm-ringing }
+             operation leave { require door-open add outside }
+             goal outside
+             invariant not alarm-ringing",
+        )
+        .unwrap();
+        let (operations, goals) = (problem.operations(), problem.goals());
+        let (invariants, axioms) = (problem.invariants(), problem.axioms());
+
+        let error = validate(problem.states(), operations, goals, invariants, &[], axioms);
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "step 1: invariant `not alarm-ringing` does not hold afterwards"
+        );
+        let error = validate(problem.states(), &operations[1..], goals, &[], &[], axioms);
+        assert_eq!(
+            error.unwrap_err(),
+            PlanError::NotApplicable {
+                step: 0,
+                prerequisite: operations[1].prerequisites()[0].clone(),
+            }
+        );
+        assert!(validate(problem.states(), operations, goals, &[], &[], axioms).is_ok());
+
+        let problem = text::parse(
+            "state money = 3
+             operation spend { modify money -= 5 }
+             bound 0 <= money",
+        )
+        .unwrap();
+        let bounds = problem.resource_bounds();
+        let error = validate(
+            problem.states(),
+            problem.operations(),
+            &[],
+            &[],
+            bounds,
+            axioms,
+        );
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "step 1: resource bound `0 <= money` does not hold afterwards"
+        );
+        let error = validate(
+            problem.states(),
+            &[],
+            &[],
+            &[],
+            &[bounds[0].clone().with_min(4)],
+            axioms,
+        );
+        assert_eq!(
+            error.unwrap_err(),
+            PlanError::OutOfBounds {
+                step: Step::Initial,
+                bound: bounds[0].clone().with_min(4),
+            }
+        );
+    }
+}
@@ -0,0 +1,431 @@
+//! Post-processing of plans from any planner.
+//!
+//! [`remove_redundant_steps`] drops steps the goals do not need, checking each
+//! removal by validating the plan again. [`deorder`] then keeps only the
+//! orderings between steps which the causal links, the steps threatening
+//! them, the invariants and the trajectory constraints demand, so
+//! independent steps may run in any order or in parallel.
+
+use std::collections::{BTreeSet, HashSet};
+
+use super::{causal_links, simulate, touched_constraints, validate, written_states, Step};
+use crate::gps::axiom::Axioms;
+use crate::gps::condition::{Condition, ConditionImpl};
+use crate::gps::operation::{ModificationEffect, Operation};
+use crate::gps::state::StateData;
+use crate::gps::symbol::Symbol;
+use crate::gps::trajectory;
+use crate::gps::GeneralProblemSolver;
+
+/// Steps with the orderings between them. Every order of the steps
+/// respecting the orderings is a valid plan.
+#[derive(Debug, Clone)]
+pub struct PartialOrderPlan {
+    steps: Vec<Operation>,
+    /// Pairs of positions `(before, after)`, none of them implied by others.
+    orderings: BTreeSet<(usize, usize)>,
+}
+
+/// Remove the steps which are not needed to reach the goals of the solver.
+///
+/// Removing a step may make later steps inapplicable, and those are removed
+/// with it, so steps which only undo each other go away together. Removals
+/// are tried from the last step to the first, and only kept when the plan
+/// still reaches the goals without breaking an invariant, a resource bound
+/// or a trajectory constraint.
+pub fn remove_redundant_steps(solver: &GeneralProblemSolver, plan: &[Operation]) -> Vec<Operation> {
+    let (states, axioms) = (solver.states(), solver.axioms());
+    let mut plan = plan.to_vec();
+    let mut position = plan.len();
+    while position > 0 {
+        position -= 1;
+        if let Some(shorter) = eliminate(solver, &plan, position) {
+            let (goals, invariants) = (solver.goals(), solver.invariants());
+            let bounds = solver.resource_bounds();
+            if validate(states, &shorter, goals, invariants, bounds, axioms).is_ok()
+                && trajectory::check(solver.constraints(), states, &shorter, axioms).is_ok()
+            {
+                plan = shorter;
+                position = position.min(plan.len());
+            }
+        }
+    }
+    plan
+}
+
+/// The plan without the step at the position and without every later step
+/// left inapplicable, or `None` if the plan did not work to begin with.
+fn eliminate(
+    solver: &GeneralProblemSolver,
+    plan: &[Operation],
+    position: usize,
+) -> Option<Vec<Operation>> {
+    let axioms = solver.axioms();
+    let mut states = simulate(solver.states(), &plan[..position], axioms)?;
+    let mut shorter = plan[..position].to_vec();
+    for operation in &plan[position + 1..] {
+        if operation.is_applicable(&states) {
+            operation.apply(&mut states);
+            axioms.derive(&mut states);
+            shorter.push(operation.clone());
+        }
+    }
+    Some(shorter)
+}
+
+/// Turn a valid plan into the least ordered plan with the same causal links.
+///
+/// A step must come after the steps providing its prerequisites. A step
+/// which could break a condition between its provider and its consumer must
+/// stay on the side of that link it was on. Steps writing the same state
+/// keep their order, unless their effects commute. A step writing a state a
+/// derived state depends on counts as writing the derived state. Steps
+/// writing the states an invariant or a resource bound depends on keep their
+/// order as well, so no other order can break it, and so do steps which
+/// could change whether a proposition of the same trajectory constraint
+/// holds.
+pub fn deorder(solver: &GeneralProblemSolver, plan: &[Operation]) -> PartialOrderPlan {
+    let axioms = solver.axioms();
+    let mut orderings = BTreeSet::new();
+
+    for link in causal_links(solver.states(), plan, solver.goals(), axioms) {
+        let producer = match link.producer() {
+            Step::Operation(position) => Some(position),
+            _ => None,
+        };
+        let consumer = match link.consumer() {
+            Step::Operation(position) => Some(position),
+            _ => None,
+        };
+        if let (Some(producer), Some(consumer)) = (producer, consumer) {
+            orderings.insert((producer, consumer));
+        }
+
+        for (position, operation) in plan.iter().enumerate() {
+            if Some(position) == producer
+                || Some(position) == consumer
+                || !threatens(operation, link.condition(), axioms)
+            {
+                continue;
+            }
+            // Keep the threat on its side of both ends of the link.
+            for end in [producer, consumer].into_iter().flatten() {
+                orderings.insert((position.min(end), position.max(end)));
+            }
+        }
+    }
+
+    let guarded: Vec<_> = solver
+        .invariants()
+        .iter()
+        .map(|invariant| axioms.dependencies(invariant.state_symbol()))
+        .chain(
+            solver
+                .resource_bounds()
+                .iter()
+                .map(|bound| HashSet::from([bound.state_symbol()])),
+        )
+        .collect();
+    let writes: Vec<_> = plan.iter().map(written_states).collect();
+    let touched = touched_constraints(plan, solver.constraints(), axioms);
+    let guards = |position: usize| -> Vec<bool> {
+        guarded
+            .iter()
+            .map(|states| writes[position].iter().any(|state| states.contains(state)))
+            .collect()
+    };
+    for (later, operation) in plan.iter().enumerate() {
+        for (earlier, other) in plan[..later].iter().enumerate() {
+            let same_invariant = guards(earlier)
+                .into_iter()
+                .zip(guards(later))
+                .any(|(first, second)| first && second);
+            let same_constraint = touched[earlier]
+                .iter()
+                .zip(&touched[later])
+                .any(|(first, second)| *first && *second);
+            if same_invariant || same_constraint || interferes(other, operation) {
+                orderings.insert((earlier, later));
+            }
+        }
+    }
+
+    PartialOrderPlan::new(plan.to_vec(), orderings)
+}
+
+/// Whether applying the operation could make the condition false.
+fn threatens(operation: &Operation, condition: &ConditionImpl, axioms: &Axioms) -> bool {
+    let state = condition.state_symbol();
+    if axioms.is_derived(state) {
+        let dependencies = axioms.dependencies(state);
+        return written_states(operation)
+            .iter()
+            .any(|written| dependencies.contains(written));
+    }
+    let adds = operation.add_states().iter().any(|s| s.symbol() == state);
+    let removes = operation.remove_states().contains(&state);
+    let modifies = operation
+        .modification_states()
+        .iter()
+        .any(|modification| modification.target_symbol() == state);
+    match condition {
+        ConditionImpl::Contain(_) => removes,
+        ConditionImpl::NotContain(_) => adds,
+        ConditionImpl::Compare(_) => adds || removes || modifies,
+    }
+}
+
+/// Whether two operations write the same state in ways which do not commute.
+fn interferes(first: &Operation, second: &Operation) -> bool {
+    let additive = |effect: &&ModificationEffect| {
+        matches!(
+            effect,
+            ModificationEffect::Increase(_) | ModificationEffect::Decrease(_)
+        )
+    };
+    let commute = |state: Symbol| match (effect_on(first, state), effect_on(second, state)) {
+        // Adding the same value twice or removing twice.
+        ((Some(a), false, m), (Some(b), false, n)) => a == b && m.is_empty() && n.is_empty(),
+        ((None, true, m), (None, true, n)) => m.is_empty() && n.is_empty(),
+        // Increments and decrements add up in any order.
+        ((None, false, m), (None, false, n)) => m.iter().chain(&n).all(additive),
+        _ => false,
+    };
+    written_states(first)
+        .intersection(&written_states(second))
+        .any(|state| !commute(*state))
+}
+
+/// The value the operation adds to the state, whether it removes the state
+/// and how it modifies it.
+fn effect_on(
+    operation: &Operation,
+    state: Symbol,
+) -> (Option<&StateData>, bool, Vec<&ModificationEffect>) {
+    let added = operation
+        .add_states()
+        .iter()
+        .find(|s| s.symbol() == state)
+        .map(|s| s.data());
+    let removed = operation.remove_states().contains(&state);
+    let modifications = operation
+        .modification_states()
+        .iter()
+        .filter(|modification| modification.target_symbol() == state)
+        .map(|modification| modification.effect())
+        .collect();
+    (added, removed, modifications)
+}
+
+/// Remove redundant steps, then deorder what is left.
+pub fn optimize(solver: &GeneralProblemSolver, plan: &[Operation]) -> PartialOrderPlan {
+    let plan = remove_redundant_steps(solver, plan);
+    deorder(solver, &plan)
+}
+
+impl PartialOrderPlan {
+    /// Steps with orderings between positions, where every ordering goes
+    /// from an earlier to a later position. Orderings implied by others are
+    /// dropped.
+    pub fn new(steps: Vec<Operation>, orderings: BTreeSet<(usize, usize)>) -> Self {
+        let count = steps.len();
+        // reachable[i][j] tells if step i must come before step j.
+        let mut reachable = vec![vec![false; count]; count];
+        for &(before, after) in &orderings {
+            reachable[before][after] = true;
+        }
+        for middle in 0..count {
+            for before in 0..count {
+                if reachable[before][middle] {
+                    let through = reachable[middle].clone();
+                    for (after, reached) in through.into_iter().enumerate() {
+                        reachable[before][after] |= reached;
+                    }
+                }
+            }
+        }
+        let orderings = orderings
+            .into_iter()
+            .filter(|&(before, after)| {
+                !(0..count).any(|middle| reachable[before][middle] && reachable[middle][after])
+            })
+            .collect();
+        Self { steps, orderings }
+    }
+
+    pub fn steps(&self) -> &[Operation] {
+        &self.steps
+    }
+
+    /// The orderings as pairs of positions `(before, after)`.
+    pub fn orderings(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.orderings.iter().copied()
+    }
+
+    /// The steps which must come right before the step at the position.
+    pub fn predecessors(&self, position: usize) -> Vec<usize> {
+        self.orderings
+            .iter()
+            .filter(|(_, after)| *after == position)
+            .map(|(before, _)| *before)
+            .collect()
+    }
+
+    /// The positions of the steps grouped so that each step comes after
+    /// every step it must follow. Steps in one layer may run in parallel.
+    pub fn layers(&self) -> Vec<Vec<usize>> {
+        let mut depths = vec![0; self.steps.len()];
+        // Orderings go forward, so predecessors are done first.
+        for position in 0..self.steps.len() {
+            depths[position] = self
+                .predecessors(position)
+                .into_iter()
+                .map(|before| depths[before] + 1)
+                .max()
+                .unwrap_or(0);
+        }
+        let mut layers = vec![Vec::new(); depths.iter().max().map_or(0, |depth| depth + 1)];
+        for (position, depth) in depths.into_iter().enumerate() {
+            layers[depth].push(position);
+        }
+        layers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps::format::text;
+    use crate::gps::names;
+
+    #[test]
+    fn it_should_remove_unused_and_undone_steps() {
+        let problem = text::parse(
+            "state at-home
+             operation buy-milk { require at-home add have-milk }
+             operation open-door { require at-home add door-open }
+             operation close-door { require door-open remove door-open }
+             operation read-paper { add informed }
+             operation make-tea { require have-milk add have-tea }
+             goal have-tea",
+        )
+        .unwrap();
+        let solver: GeneralProblemSolver = problem.clone().into();
+        let plan = remove_redundant_steps(&solver, problem.operations());
+        assert_eq!(names(&plan), vec!["buy-milk", "make-tea"]);
+    }
+
+    #[test]
+    fn it_should_not_break_invariants_when_optimizing() {
+        let problem = text::parse(
+            "state money = 0
+             operation earn { modify money += 5 }
+             operation spend { modify money -= 5 add bought }
+             operation earn2 { modify money += 5 }
+             operation spend2 { modify money -= 5 add bought2 }
+             goal bought2
+             invariant money >= 0",
+        )
+        .unwrap();
+        let solver: GeneralProblemSolver = problem.clone().into();
+        let plan = optimize(&solver, problem.operations());
+        assert_eq!(names(plan.steps()), vec!["earn2", "spend2"]);
+        assert_eq!(plan.orderings().collect::<Vec<_>>(), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn it_should_keep_only_the_orderings_needed() {
+        let problem = text::parse(
+            "state son-at-home
+             state have-money
+             state have-phone-book
+             operation look-up-number { require have-phone-book add know-phone-number }
+             operation telephone-shop { require know-phone-number add shop-knows-problem }
+             operation give-shop-money { require have-money add shop-has-money remove have-money }
+             operation shop-installs-battery {
+                 require shop-knows-problem require shop-has-money add car-works
+             }
+             operation drive-son-to-school {
+                 require son-at-home require car-works add son-at-school remove son-at-home
+             }
+             goal son-at-school",
+        )
+        .unwrap();
+        let solver: GeneralProblemSolver = problem.clone().into();
+        let plan = optimize(&solver, problem.operations());
+
+        assert_eq!(plan.steps().len(), 5);
+        assert_eq!(
+            plan.orderings().collect::<Vec<_>>(),
+            vec![(0, 1), (1, 3), (2, 3), (3, 4)]
+        );
+        assert_eq!(plan.predecessors(3), vec![1, 2]);
+        assert_eq!(plan.layers(), vec![vec![0, 2], vec![1], vec![3], vec![4]]);
+    }
+
+    #[test]
+    fn it_should_keep_steps_threatening_a_link_on_their_side() {
+        let problem = text::parse(
+            "state door-open
+             operation walk-in { require door-open add inside }
+             operation close-door { require door-open remove door-open add door-closed }
+             goal inside
+             goal door-closed",
+        )
+        .unwrap();
+        let solver: GeneralProblemSolver = problem.clone().into();
+        let plan = deorder(&solver, problem.operations());
+        assert_eq!(plan.orderings().collect::<Vec<_>>(), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn it_should_order_steps_before_the_derived_states_they_enable() {
+        let problem = text::parse(
+            "state battery-ok
+             operation fix-tires { add tires-ok }
+             operation drive { require car-works add at-school }
+             rule car-works { require battery-ok require tires-ok }
+             goal at-school",
+        )
+        .unwrap();
+        let solver: GeneralProblemSolver = problem.clone().into();
+        let plan = deorder(&solver, problem.operations());
+        assert_eq!(plan.orderings().collect::<Vec<_>>(), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn it_should_not_remove_steps_trajectory_constraints_need() {
+        let problem = text::parse(
+            "state at-home
+             operation buy-coffee { require at-home add have-coffee }
+             operation drive { require at-home add at-work remove at-home }
+             goal at-work
+             sometime have-coffee",
+        )
+        .unwrap();
+        let solver: GeneralProblemSolver = problem.clone().into();
+        let plan = remove_redundant_steps(&solver, problem.operations());
+        assert_eq!(names(&plan), vec!["buy-coffee", "drive"]);
+    }
+
+    #[test]
+    fn it_should_keep_the_order_trajectory_constraints_need() {
+        let source = "operation buy-coffee { add have-coffee }
+                      operation drive { add at-work }
+                      goal have-coffee
+                      goal at-work";
+        let solver: GeneralProblemSolver = text::parse(source).unwrap().into();
+        let plan = deorder(&solver, solver.operations());
+        assert_eq!(plan.orderings().count(), 0);
+
+        for constraint in [
+            "sometime-before drive buy-coffee",
+            "sometime-before at-work have-coffee",
+        ] {
+            let source = format!("{source}\n{constraint}");
+            let solver: GeneralProblemSolver = text::parse(&source).unwrap().into();
+            let plan = deorder(&solver, solver.operations());
+            assert_eq!(plan.orderings().collect::<Vec<_>>(), vec![(0, 1)]);
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//! Repairing a plan after its problem changed.
+//!
+//! Solving the changed problem from scratch may give a plan with little in
+//! common with the old one. [`repair`] keeps the steps of the old plan which
+//! still work, drops those which cannot be made to work and inserts steps
+//! only where the old plan falls short, then reports the result as a diff.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use super::validate;
+use crate::gps::axiom::Axioms;
+use crate::gps::operation::Operation;
+use crate::gps::state::StateSet;
+use crate::gps::trajectory;
+use crate::gps::GeneralProblemSolver;
+
+/// A new plan as edits of an old one.
+#[derive(Debug, Clone)]
+pub struct Repair {
+    edits: Vec<Edit>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Edit {
+    Keep(Operation),
+    Insert(Operation),
+    Remove(Operation),
+}
+
+/// Find a plan for the problem of the solver which differs from the old plan
+/// in as few steps as possible, or `None` if no plan is found at all.
+///
+/// Steps are matched with the operations of the solver by name, so steps of
+/// withdrawn operations are removed and changed operations are used as they
+/// are now. Each step whose prerequisites no longer hold is either preceded
+/// by a plan achieving them or removed, and a plan for the goals still
+/// missing is appended. That repair is compared with a plan solved from
+/// scratch, and the one with fewer edits wins.
+pub fn repair(solver: &GeneralProblemSolver, plan: &[Operation]) -> Option<Repair> {
+    let candidates = [patch(solver, plan), solver.solve()];
+    candidates
+        .into_iter()
+        .flatten()
+        .filter(|candidate| {
+            let states = solver.states();
+            let (goals, invariants) = (solver.goals(), solver.invariants());
+            let (bounds, axioms) = (solver.resource_bounds(), solver.axioms());
+            validate(states, candidate, goals, invariants, bounds, axioms).is_ok()
+                && trajectory::check(solver.constraints(), states, candidate, axioms).is_ok()
+        })
+        .map(|candidate| Repair::between(plan, &candidate))
+        .min_by_key(Repair::edit_count)
+}
+
+/// Walk the old plan, bridging the gaps it now has and dropping the steps
+/// which would break an invariant or a resource bound where they now run.
+fn patch(solver: &GeneralProblemSolver, plan: &[Operation]) -> Option<Vec<Operation>> {
+    let axioms = solver.axioms();
+    let mut states = solver.states().clone();
+    axioms.derive(&mut states);
+    let mut patched = Vec::new();
+
+    for old in plan {
+        let Some(operation) = solver
+            .operations()
+            .iter()
+            .find(|operation| operation.symbol() == old.symbol())
+        else {
+            continue;
+        };
+        if !operation.is_applicable(&states) {
+            let mut bridge = solver.clone();
+            bridge
+                .set_states(states.clone())
+                .set_goals(operation.prerequisites().clone());
+            match bridge.solve() {
+                Some(steps) => extend(&mut patched, &mut states, steps, axioms),
+                None => continue,
+            }
+        }
+        let mut next_states = states.clone();
+        operation.apply(&mut next_states);
+        axioms.derive(&mut next_states);
+        let bounded = solver
+            .resource_bounds()
+            .iter()
+            .all(|bound| bound.allows(&next_states));
+        if bounded && next_states.has_reached(solver.invariants()) {
+            extend(&mut patched, &mut states, vec![operation.clone()], axioms);
+        }
+    }
+
+    let mut rest = solver.clone();
+    rest.set_states(states.clone());
+    let steps = rest.solve()?;
+    extend(&mut patched, &mut states, steps, axioms);
+    Some(patched)
+}
+
+fn extend(
+    plan: &mut Vec<Operation>,
+    states: &mut StateSet,
+    steps: Vec<Operation>,
+    axioms: &Axioms,
+) {
+    for step in steps {
+        step.apply(states);
+        axioms.derive(states);
+        plan.push(step);
+    }
+}
+
+impl Repair {
+    /// The shortest diff turning one plan into the other, matching steps by
+    /// the names of their operations.
+    pub fn between(old: &[Operation], new: &[Operation]) -> Self {
+        // lengths[i][j] is the longest common subsequence of old[i..], new[j..].
+        let mut lengths = vec![vec![0; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                lengths[i][j] = if old[i].symbol() == new[j].symbol() {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        let mut edits = Vec::new();
+        while i < old.len() || j < new.len() {
+            if i < old.len() && j < new.len() && old[i].symbol() == new[j].symbol() {
+                edits.push(Edit::Keep(new[j].clone()));
+                i += 1;
+                j += 1;
+            } else if i < old.len() && (j == new.len() || lengths[i + 1][j] >= lengths[i][j + 1]) {
+                edits.push(Edit::Remove(old[i].clone()));
+                i += 1;
+            } else {
+                edits.push(Edit::Insert(new[j].clone()));
+                j += 1;
+            }
+        }
+        Self { edits }
+    }
+
+    pub fn edits(&self) -> &[Edit] {
+        &self.edits
+    }
+
+    /// The repaired plan.
+    pub fn plan(&self) -> Vec<Operation> {
+        self.edits
+            .iter()
+            .filter_map(|edit| match edit {
+                Edit::Keep(operation) | Edit::Insert(operation) => Some(operation.clone()),
+                Edit::Remove(_) => None,
+            })
+            .collect()
+    }
+
+    pub fn kept(&self) -> usize {
+        self.count(|edit| matches!(edit, Edit::Keep(_)))
+    }
+
+    pub fn inserted(&self) -> usize {
+        self.count(|edit| matches!(edit, Edit::Insert(_)))
+    }
+
+    pub fn removed(&self) -> usize {
+        self.count(|edit| matches!(edit, Edit::Remove(_)))
+    }
+
+    /// The number of steps inserted or removed.
+    pub fn edit_count(&self) -> usize {
+        self.inserted() + self.removed()
+    }
+
+    fn count(&self, predicate: impl Fn(&Edit) -> bool) -> usize {
+        self.edits.iter().filter(|edit| predicate(edit)).count()
+    }
+}
+
+/// One step per line, marked `+` when inserted and `-` when removed.
+impl Display for Repair {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        for edit in &self.edits {
+            match edit {
+                Edit::Keep(operation) => writeln!(f, "  {}", operation.name())?,
+                Edit::Insert(operation) => writeln!(f, "+ {}", operation.name())?,
+                Edit::Remove(operation) => writeln!(f, "- {}", operation.name())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps::format::text;
+
+    const PROBLEM: &str = "
+        state son-at-home
+        state car-needs-battery
+        state have-money
+        state have-phone-book
+        operation drive-son-to-school {
+            require son-at-home require car-works
+            add son-at-school remove son-at-home
+        }
+        operation shop-installs-battery {
+            require car-needs-battery require shop-knows-problem require shop-has-money
+            add car-works
+        }
+        operation tell-shop-problem { require in-communication-with-shop add shop-knows-problem }
+        operation telephone-shop { require know-phone-number add in-communication-with-shop }
+        operation look-up-number { require have-phone-book add know-phone-number }
+        operation ask-neighbour { require neighbour-at-home add know-phone-number }
+        operation give-shop-money { require have-money add shop-has-money remove have-money }
+        goal son-at-school";
+
+    #[test]
+    fn it_should_keep_what_still_works_and_report_the_diff() {
+        let mut solver: GeneralProblemSolver = text::parse(PROBLEM).unwrap().into();
+        let plan = solver.solve().unwrap();
+        assert_eq!(plan[0].name(), "look-up-number");
+
+        let unchanged = repair(&solver, &plan).unwrap();
+        assert_eq!((unchanged.kept(), unchanged.edit_count()), (plan.len(), 0));
+
+        // The phone book is lost, but the neighbour is at home.
+        let mut states = solver.states().clone();
+        states.remove("have-phone-book");
+        states.insert(text::parse_state("neighbour-at-home").unwrap());
+        solver.set_states(states);
+
+        let repaired = repair(&solver, &plan).unwrap();
+        assert_eq!(
+            repaired.to_string(),
+            "- look-up-number\n+ ask-neighbour\n  telephone-shop\n  tell-shop-problem\n  \
+             give-shop-money\n  shop-installs-battery\n  drive-son-to-school\n"
+        );
+        assert_eq!(
+            (repaired.kept(), repaired.inserted(), repaired.removed()),
+            (5, 1, 1)
+        );
+        assert_eq!(repaired.plan().len(), plan.len());
+
+        // Without a way to pay the shop, nothing works.
+        solver.remove_operation("give-shop-money");
+        assert!(repair(&solver, &plan).is_none());
+    }
+}
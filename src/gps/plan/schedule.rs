@@ -0,0 +1,298 @@
+//! Running the steps of a plan in parallel.
+//!
+//! Two steps interfere when one of them adds, removes or modifies a state the
+//! other requires or writes. A step starts as soon as every earlier step it
+//! interferes with has finished, so steps which do not interfere overlap.
+//!
+//! A derived state depends on several states, so a step requiring one reads
+//! all of them. A step writing a state an invariant depends on reads the
+//! other states of the invariant too, so the steps which could break it
+//! together keep their order. So do the steps which could change whether a
+//! proposition of the same trajectory constraint holds.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use super::{touched_constraints, written_states};
+use crate::gps::axiom::Axioms;
+use crate::gps::condition::{Condition, ConditionImpl};
+use crate::gps::operation::Operation;
+use crate::gps::symbol::Symbol;
+use crate::gps::trajectory::Constraint;
+
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    /// In the order of the plan.
+    slots: Vec<Slot>,
+}
+
+/// When one step of a plan runs.
+#[derive(Debug, Clone)]
+pub struct Slot {
+    operation: Operation,
+    start: u32,
+    duration: u32,
+    /// The positions of the earlier steps the step interferes with.
+    predecessors: Vec<usize>,
+}
+
+impl Schedule {
+    /// Schedule a plan whose steps all take one unit of time.
+    pub fn new(plan: &[Operation]) -> Self {
+        Self::with_durations(plan, |_| 1)
+    }
+
+    /// Schedule a plan with the duration of each step given by a function.
+    pub fn with_durations(plan: &[Operation], duration: impl Fn(&Operation) -> u32) -> Self {
+        Self::with_rules(plan, duration, &[], &[], &Axioms::default())
+    }
+
+    /// Schedule a plan like [`with_durations`](Self::with_durations), keeping
+    /// the invariants, trajectory constraints and derived states of the
+    /// problem as they are in the plan.
+    pub fn with_rules(
+        plan: &[Operation],
+        duration: impl Fn(&Operation) -> u32,
+        invariants: &[ConditionImpl],
+        constraints: &[Constraint],
+        axioms: &Axioms,
+    ) -> Self {
+        let dependencies =
+            |condition: &ConditionImpl| axioms.dependencies(condition.state_symbol());
+        let guarded: Vec<_> = invariants.iter().map(dependencies).collect();
+        let writes: Vec<_> = plan.iter().map(written_states).collect();
+        let reads: Vec<BTreeSet<Symbol>> = plan
+            .iter()
+            .zip(&writes)
+            .map(|(operation, written)| {
+                let guarded = guarded
+                    .iter()
+                    .filter(|states| written.iter().any(|state| states.contains(state)));
+                operation
+                    .prerequisites()
+                    .iter()
+                    .flat_map(dependencies)
+                    .chain(guarded.flatten().copied())
+                    .collect()
+            })
+            .collect();
+        let touched = touched_constraints(plan, constraints, axioms);
+
+        let mut slots: Vec<Slot> = Vec::with_capacity(plan.len());
+        for (position, operation) in plan.iter().enumerate() {
+            let predecessors: Vec<_> = (0..position)
+                .filter(|&earlier| {
+                    !writes[earlier].is_disjoint(&writes[position])
+                        || !writes[earlier].is_disjoint(&reads[position])
+                        || !reads[earlier].is_disjoint(&writes[position])
+                        || touched[earlier]
+                            .iter()
+                            .zip(&touched[position])
+                            .any(|(first, second)| *first && *second)
+                })
+                .collect();
+            let start = predecessors
+                .iter()
+                .map(|&earlier| slots[earlier].end())
+                .max()
+                .unwrap_or(0);
+            slots.push(Slot {
+                operation: operation.clone(),
+                start,
+                duration: duration(operation),
+                predecessors,
+            });
+        }
+        Self { slots }
+    }
+
+    pub fn slots(&self) -> &[Slot] {
+        &self.slots
+    }
+
+    /// When the last step finishes.
+    pub fn makespan(&self) -> u32 {
+        self.slots.iter().map(Slot::end).max().unwrap_or(0)
+    }
+
+    /// The positions of the steps running at the given time.
+    pub fn running_at(&self, time: u32) -> Vec<usize> {
+        (0..self.slots.len())
+            .filter(|&position| {
+                let slot = &self.slots[position];
+                slot.start <= time && time < slot.end()
+            })
+            .collect()
+    }
+
+    /// The positions of a chain of steps, each starting when the one before
+    /// it ends, which takes the whole makespan. Delaying any of them delays
+    /// the end of the plan.
+    pub fn critical_path(&self) -> Vec<usize> {
+        let makespan = self.makespan();
+        let Some(mut current) = (0..self.slots.len())
+            .rev()
+            .find(|&position| self.slots[position].end() == makespan)
+        else {
+            return Vec::new();
+        };
+
+        let mut path = vec![current];
+        while let Some(&earlier) = self.slots[current]
+            .predecessors
+            .iter()
+            .rev()
+            .find(|&&earlier| self.slots[earlier].end() == self.slots[current].start)
+        {
+            path.push(earlier);
+            current = earlier;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Draw the schedule with one row per step and one column per unit of
+    /// time. Steps on the critical path are drawn with `#` and marked with
+    /// `*`, the others with `=`.
+    pub fn gantt_chart(&self) -> String {
+        let critical = self.critical_path();
+        let width = self
+            .slots
+            .iter()
+            .map(|slot| slot.operation.name().len())
+            .max()
+            .unwrap_or(0);
+        let makespan = self.makespan() as usize;
+
+        let mut out = String::new();
+        for (position, slot) in self.slots.iter().enumerate() {
+            let is_critical = critical.contains(&position);
+            let bar = if is_critical { "#" } else { "=" };
+            let bar = format!(
+                "{}{}",
+                " ".repeat(slot.start as usize),
+                bar.repeat(slot.duration as usize)
+            );
+            writeln!(
+                out,
+                "{:width$} |{bar:makespan$}| {}-{}{}",
+                slot.operation.name(),
+                slot.start,
+                slot.end(),
+                if is_critical { " *" } else { "" }
+            )
+            .unwrap();
+        }
+        writeln!(out, "makespan: {makespan}").unwrap();
+        out
+    }
+}
+
+impl Slot {
+    pub fn operation(&self) -> &Operation {
+        &self.operation
+    }
+
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    pub fn duration(&self) -> u32 {
+        self.duration
+    }
+
+    pub fn end(&self) -> u32 {
+        self.start + self.duration
+    }
+
+    /// The positions of the earlier steps which must finish first.
+    pub fn predecessors(&self) -> &[usize] {
+        &self.predecessors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps::domains::school;
+    use crate::gps::format::text;
+    use crate::gps::trajectory::Proposition;
+    use crate::gps::GeneralProblemSolver;
+
+    #[test]
+    fn it_should_overlap_steps_which_do_not_interfere() {
+        let solver: GeneralProblemSolver = school::problem_with_battery().into();
+        let plan = solver.solve().unwrap();
+
+        let schedule = Schedule::new(&plan);
+        let starts: Vec<_> = schedule
+            .slots()
+            .iter()
+            .map(|slot| (slot.operation().name(), slot.start()))
+            .collect();
+        assert_eq!(
+            starts,
+            vec![
+                ("look-up-number", 0),
+                ("telephone-shop", 1),
+                ("tell-shop-problem", 2),
+                ("give-shop-money", 0),
+                ("shop-installs-battery", 3),
+                ("drive-son-to-school", 4),
+            ]
+        );
+        assert_eq!(schedule.makespan(), 5);
+        assert_eq!(schedule.running_at(0), vec![0, 3]);
+
+        // Paying the shop takes longest, so the calls are not critical.
+        let schedule = Schedule::with_durations(&plan, |operation| match operation.name() {
+            "telephone-shop" => 3,
+            "give-shop-money" => 6,
+            _ => 1,
+        });
+        assert_eq!(schedule.critical_path(), vec![3, 4, 5]);
+        assert_eq!(
+            schedule.gantt_chart(),
+            "\
+look-up-number        |=       | 0-1
+telephone-shop        | ===    | 1-4
+tell-shop-problem     |    =   | 4-5
+give-shop-money       |######  | 0-6 *
+shop-installs-battery |      # | 6-7 *
+drive-son-to-school   |       #| 7-8 *
+makespan: 8
+"
+        );
+    }
+
+    #[test]
+    fn it_should_keep_derived_states_and_invariants_safe() {
+        let problem = text::parse(
+            "operation fix-tires { add tires-ok }
+             operation drive { require car-works add at-school }
+             operation arm-alarm { add alarm-armed }
+             operation disarm-alarm { remove alarm-armed }
+             operation open-door { add door-open }
+             rule car-works { require tires-ok }
+             rule danger { require alarm-armed require door-open }
+             invariant not danger",
+        )
+        .unwrap();
+        let starts =
+            |schedule: Schedule| -> Vec<_> { schedule.slots().iter().map(Slot::start).collect() };
+        let plan = problem.operations();
+        assert_eq!(starts(Schedule::new(plan)), vec![0, 0, 0, 1, 0]);
+
+        let (invariants, axioms) = (problem.invariants(), problem.axioms());
+        let schedule = Schedule::with_rules(plan, |_| 1, invariants, &[], axioms);
+        assert_eq!(starts(schedule), vec![0, 1, 0, 1, 2]);
+
+        // The door is opened after the alarm was armed, and stays so.
+        let armed_first = Constraint::SometimeBefore(
+            Proposition::Condition(text::parse_condition("door-open").unwrap()),
+            Proposition::Condition(text::parse_condition("alarm-armed").unwrap()),
+        );
+        let schedule = Schedule::with_rules(plan, |_| 1, &[], &[armed_first], axioms);
+        assert_eq!(starts(schedule), vec![0, 1, 0, 1, 2]);
+    }
+}
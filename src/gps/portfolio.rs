@@ -0,0 +1,343 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+use super::operation::Operation;
+use super::search::{Outcome, Statistics};
+use super::GeneralProblemSolver;
+
+/// Something able to produce a plan for the problem it has been set up with.
+pub trait Planner: Send + Sync {
+    fn name(&self) -> String;
+
+    /// Search for a plan, giving up as soon as the token is cancelled.
+    fn plan(&self, cancellation: &CancellationToken) -> (Outcome, Statistics);
+}
+
+/// A flag shared between threads telling searches to stop, which is raised
+/// either explicitly or once the deadline has passed.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+/// Runs several planners on their own threads and keeps the first or the
+/// best plan found.
+#[derive(Default)]
+pub struct Portfolio {
+    planners: Vec<Box<dyn Planner>>,
+}
+
+/// The plan kept by a portfolio, or why none was found, along with the
+/// statistics of all its planners added up.
+#[derive(Debug, Clone)]
+pub struct PortfolioOutcome {
+    planner: Option<String>,
+    outcome: Outcome,
+    statistics: Statistics,
+}
+
+/// Solves the problem of a wrapped solver with its goals rotated, since GPS
+/// is sensitive to the order in which goals are achieved.
+#[derive(Debug, Clone)]
+pub struct GoalOrdering {
+    solver: GeneralProblemSolver,
+    rotation: usize,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            deadline: Some(deadline),
+        }
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+impl Portfolio {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a portfolio running the given solver once per rotation of its
+    /// goals.
+    pub fn with_goal_orderings(solver: &GeneralProblemSolver) -> Self {
+        let mut portfolio = Self::new();
+
+        for rotation in 0..solver.goals().len().max(1) {
+            portfolio.add_planner(GoalOrdering::new(solver.clone(), rotation));
+        }
+
+        portfolio
+    }
+
+    pub fn add_planner(&mut self, planner: impl Planner + 'static) -> &mut Self {
+        self.planners.push(Box::new(planner));
+        self
+    }
+
+    pub fn planners(&self) -> impl Iterator<Item = &dyn Planner> {
+        self.planners.iter().map(Box::as_ref)
+    }
+
+    /// Return the first plan found by any planner and cancel the others.
+    pub fn solve_first(&self) -> PortfolioOutcome {
+        self.run(CancellationToken::new(), true)
+    }
+
+    /// Return the shortest plan found before the deadline, or as soon as
+    /// every planner has finished.
+    pub fn solve_best(&self, deadline: Instant) -> PortfolioOutcome {
+        self.run(CancellationToken::with_deadline(deadline), false)
+    }
+
+    /// Without a plan the outcome is [`Outcome::LimitExceeded`] if any planner
+    /// hit a limit or did not finish in time, and [`Outcome::Unsolvable`] if
+    /// every planner proved there is no plan.
+    fn run(&self, cancellation: CancellationToken, first: bool) -> PortfolioOutcome {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::scope(|scope| {
+            for (position, planner) in self.planners.iter().enumerate() {
+                let sender = sender.clone();
+                let cancellation = cancellation.clone();
+
+                scope.spawn(move || {
+                    let (outcome, statistics) = planner.plan(&cancellation);
+                    // The receiver is gone once a result has been chosen.
+                    let _ = sender.send((position, outcome, statistics));
+                });
+            }
+            drop(sender);
+
+            let mut best: Option<(usize, Vec<Operation>)> = None;
+            let mut statistics = Statistics::default();
+            let mut finished = 0;
+            let mut limit_exceeded = false;
+
+            loop {
+                let received = match cancellation.deadline() {
+                    Some(deadline) => receiver
+                        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                        .ok(),
+                    None => receiver.recv().ok(),
+                };
+
+                // Either every planner has finished or the deadline passed.
+                let Some((position, outcome, planner_statistics)) = received else {
+                    break;
+                };
+                finished += 1;
+                statistics.add(&planner_statistics);
+                let operations = match outcome {
+                    Outcome::Solved(operations) => operations,
+                    Outcome::Unsolvable => continue,
+                    Outcome::LimitExceeded => {
+                        limit_exceeded = true;
+                        continue;
+                    }
+                };
+
+                // Prefer shorter plans, then planners added earlier.
+                let better = match &best {
+                    Some((best_position, best_operations)) => {
+                        (operations.len(), position) < (best_operations.len(), *best_position)
+                    }
+                    None => true,
+                };
+                if better {
+                    best = Some((position, operations));
+                }
+
+                if first {
+                    break;
+                }
+            }
+
+            cancellation.cancel();
+            // The planners still running stop now, so wait for their
+            // statistics, but not for their plans.
+            let unfinished = finished < self.planners.len();
+            for (_, _, planner_statistics) in receiver {
+                statistics.add(&planner_statistics);
+            }
+
+            match best {
+                Some((position, operations)) => PortfolioOutcome {
+                    planner: Some(self.planners[position].name()),
+                    outcome: Outcome::Solved(operations),
+                    statistics,
+                },
+                None => PortfolioOutcome {
+                    planner: None,
+                    outcome: if limit_exceeded || unfinished {
+                        Outcome::LimitExceeded
+                    } else {
+                        Outcome::Unsolvable
+                    },
+                    statistics,
+                },
+            }
+        })
+    }
+}
+
+impl PortfolioOutcome {
+    /// The planner which found the plan, if any did.
+    pub fn planner(&self) -> Option<&str> {
+        self.planner.as_deref()
+    }
+
+    pub fn outcome(&self) -> &Outcome {
+        &self.outcome
+    }
+
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    pub fn into_outcome(self) -> Outcome {
+        self.outcome
+    }
+}
+
+impl GoalOrdering {
+    pub fn new(mut solver: GeneralProblemSolver, rotation: usize) -> Self {
+        let mut goals = solver.goals().clone();
+        let len = goals.len();
+        if len > 0 {
+            goals.rotate_left(rotation % len);
+        }
+        solver.set_goals(goals);
+
+        Self { solver, rotation }
+    }
+}
+
+impl Planner for GoalOrdering {
+    fn name(&self) -> String {
+        format!("gps (goals rotated by {})", self.rotation)
+    }
+
+    fn plan(&self, cancellation: &CancellationToken) -> (Outcome, Statistics) {
+        self.solver.search(cancellation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::gps::condition::{ConditionImpl, Contain};
+    use crate::gps::operation::OperationBuilder;
+    use crate::gps::search::Limits;
+    use crate::gps::state::{State, StateSet};
+
+    /// A planner that never finds a plan until it is cancelled.
+    struct Stuck;
+
+    impl Planner for Stuck {
+        fn name(&self) -> String {
+            "stuck".to_owned()
+        }
+
+        fn plan(&self, cancellation: &CancellationToken) -> (Outcome, Statistics) {
+            while !cancellation.is_cancelled() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            (Outcome::LimitExceeded, Statistics::default())
+        }
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn it_should_be_able_to_share_core_types_between_threads() {
+        assert_send_sync::<Operation>();
+        assert_send_sync::<ConditionImpl>();
+        assert_send_sync::<StateSet>();
+        assert_send_sync::<GeneralProblemSolver>();
+    }
+
+    #[test]
+    fn it_should_return_the_first_plan_and_cancel_the_other_planners() {
+        let mut gps = GeneralProblemSolver::new();
+        gps.set_operations(vec![OperationBuilder::new("add-state".to_owned())
+            .insert_add_state(State::new_symbol("state".to_owned()))
+            .build()])
+            .set_goals(vec![Contain::new("state".to_owned()).into()]);
+
+        let mut portfolio = Portfolio::new();
+        portfolio.add_planner(Stuck).add_planner(gps);
+
+        let solution = portfolio.solve_first();
+        assert_eq!(solution.planner(), Some("gps"));
+        assert_eq!(solution.into_outcome().into_operations().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn it_should_return_the_shortest_plan_within_the_deadline() {
+        let mut gps = GeneralProblemSolver::new();
+        gps.set_operations(vec![
+            OperationBuilder::new("add-a".to_owned())
+                .insert_add_state(State::new_symbol("a".to_owned()))
+                .build(),
+            OperationBuilder::new("add-b-with-a".to_owned())
+                .insert_prerequisite(Contain::new("a".to_owned()).into())
+                .insert_add_state(State::new_symbol("b".to_owned()))
+                .build(),
+        ])
+        .set_goals(vec![
+            Contain::new("a".to_owned()).into(),
+            Contain::new("b".to_owned()).into(),
+        ]);
+
+        let mut portfolio = Portfolio::with_goal_orderings(&gps);
+        portfolio.add_planner(Stuck);
+
+        let solution = portfolio.solve_best(Instant::now() + Duration::from_millis(200));
+        assert_eq!(solution.planner(), Some("gps (goals rotated by 0)"));
+        assert_eq!(solution.into_outcome().into_operations().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn it_should_tell_exceeded_limits_from_unsolvable_problems() {
+        let mut gps = GeneralProblemSolver::new();
+        gps.set_operations(vec![OperationBuilder::new("add-state".to_owned())
+            .insert_add_state(State::new_symbol("state".to_owned()))
+            .build()])
+            .set_goals(vec![Contain::new("state".to_owned()).into()])
+            .set_limits(Limits::new().with_max_expansions(0));
+
+        let solution = Portfolio::with_goal_orderings(&gps).solve_first();
+        assert_eq!(solution.planner(), None);
+        assert!(matches!(solution.outcome(), Outcome::LimitExceeded));
+
+        gps.set_goals(vec![Contain::new("other".to_owned()).into()]);
+        let solution = Portfolio::with_goal_orderings(&gps).solve_first();
+        assert!(matches!(solution.outcome(), Outcome::Unsolvable));
+    }
+}
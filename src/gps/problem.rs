@@ -0,0 +1,122 @@
+use super::axiom::Axioms;
+use super::condition::ConditionImpl;
+use super::numeric::ResourceBound;
+use super::operation::Operation;
+use super::state::StateSet;
+use super::trajectory::Constraint;
+use super::utility::SoftGoal;
+use super::GeneralProblemSolver;
+
+/// Everything a planner needs to know: the available operations, the
+/// initial states and the goals to reach from them.
+#[derive(Debug, Clone, Default)]
+pub struct Problem {
+    operations: Vec<Operation>,
+    states: StateSet,
+    goals: Vec<ConditionImpl>,
+    /// Conditions which must hold in every state on the way.
+    invariants: Vec<ConditionImpl>,
+    /// Limits integer states must stay within on the way.
+    resource_bounds: Vec<ResourceBound>,
+    /// Constraints over the states and operations on the way.
+    constraints: Vec<Constraint>,
+    /// Rules deriving states from the others.
+    axioms: Axioms,
+    /// Goals worth achieving if they pay for their cost.
+    soft_goals: Vec<SoftGoal>,
+}
+
+impl Problem {
+    pub fn new(operations: Vec<Operation>, states: StateSet, goals: Vec<ConditionImpl>) -> Self {
+        Self {
+            operations,
+            states,
+            goals,
+            invariants: Vec::new(),
+            resource_bounds: Vec::new(),
+            constraints: Vec::new(),
+            axioms: Axioms::default(),
+            soft_goals: Vec::new(),
+        }
+    }
+
+    pub fn with_invariants(mut self, invariants: Vec<ConditionImpl>) -> Self {
+        self.invariants = invariants;
+        self
+    }
+
+    pub fn operations(&self) -> &Vec<Operation> {
+        &self.operations
+    }
+
+    pub fn states(&self) -> &StateSet {
+        &self.states
+    }
+
+    pub fn goals(&self) -> &Vec<ConditionImpl> {
+        &self.goals
+    }
+
+    pub fn invariants(&self) -> &Vec<ConditionImpl> {
+        &self.invariants
+    }
+
+    pub fn with_resource_bounds(mut self, resource_bounds: Vec<ResourceBound>) -> Self {
+        self.resource_bounds = resource_bounds;
+        self
+    }
+
+    pub fn resource_bounds(&self) -> &Vec<ResourceBound> {
+        &self.resource_bounds
+    }
+
+    pub fn with_constraints(mut self, constraints: Vec<Constraint>) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    pub fn constraints(&self) -> &Vec<Constraint> {
+        &self.constraints
+    }
+
+    pub fn with_axioms(mut self, axioms: Axioms) -> Self {
+        self.axioms = axioms;
+        self
+    }
+
+    pub fn axioms(&self) -> &Axioms {
+        &self.axioms
+    }
+
+    pub fn with_soft_goals(mut self, soft_goals: Vec<SoftGoal>) -> Self {
+        self.soft_goals = soft_goals;
+        self
+    }
+
+    pub fn soft_goals(&self) -> &Vec<SoftGoal> {
+        &self.soft_goals
+    }
+
+    /// The operations, states and goals, dropping the invariants, the
+    /// resource bounds, the constraints, the axioms and the soft goals.
+    pub fn into_inner(self) -> (Vec<Operation>, StateSet, Vec<ConditionImpl>) {
+        (self.operations, self.states, self.goals)
+    }
+}
+
+/// The solver only plans for the goals, so the soft goals are left out for
+/// [`utility::solve`](super::utility::solve).
+impl From<Problem> for GeneralProblemSolver {
+    fn from(value: Problem) -> Self {
+        let mut solver = GeneralProblemSolver::new();
+        solver
+            .set_operations(value.operations)
+            .set_goals(value.goals)
+            .set_states(value.states)
+            .set_invariants(value.invariants)
+            .set_resource_bounds(value.resource_bounds)
+            .set_constraints(value.constraints)
+            .set_axioms(value.axioms);
+        solver
+    }
+}
@@ -0,0 +1,637 @@
+//! An interactive shell for exploring a problem one operation at a time.
+//!
+//! Each line is a command such as `set money = 10`, `goal son-at-school`,
+//! `operations`, `apply look-up-number` or `plan`; `help` lists them all.
+//! States and goals are written as in the [plain-text format](super::format::text).
+
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult, Write as _};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::condition::{Condition, ConditionImpl};
+use super::format::{self, text, LoadError, ParseError};
+use super::operation::Operation;
+use super::portfolio::CancellationToken;
+use super::problem::Problem;
+use super::search::{Limits, Outcome};
+use super::state::{StateData, StateSet};
+use super::symbol::Symbol;
+use super::GeneralProblemSolver;
+
+const HELP: &str = "\
+load <domain> [<problem>]  read a problem from files
+states                     list the current states
+set <state>                add or change a state, e.g. `set money = 10`
+unset <state>              remove a state, known or not
+unknown <state>            forget whether a state holds and its value
+goals                      list the goals
+goal <condition>           add a goal, e.g. `goal money >= 5`
+clear-goals                remove every goal
+operations                 list which operations are applicable and why
+                           the others are not
+apply <operation>          apply an applicable operation
+plan                       plan from the current states to the goals
+                           within the limits of the session
+step                       apply the next step of the plan
+undo                       take back the last applied operation or edit,
+                           goals included
+reset                      go back to the states the problem started with
+quit                       leave the shell";
+
+/// The problem being explored and the states reached so far.
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    solver: GeneralProblemSolver,
+    states: StateSet,
+    history: Vec<Snapshot>,
+    plan: Vec<Operation>,
+    next_step: usize,
+    limits: Limits,
+    timeout: Option<Duration>,
+}
+
+/// What to restore on undo.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    states: StateSet,
+    goals: Vec<ConditionImpl>,
+    plan: Vec<Operation>,
+    next_step: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    Output(String),
+    Quit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandError {
+    message: String,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop each `plan` within the limits.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Give up on each `plan` after the time.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn solver(&self) -> &GeneralProblemSolver {
+        &self.solver
+    }
+
+    pub fn states(&self) -> &StateSet {
+        &self.states
+    }
+
+    /// Start over with another problem.
+    pub fn load(&mut self, problem: Problem) {
+        self.solver = problem.into();
+        self.states = self.solver.states().clone();
+        self.derive();
+        self.history.clear();
+        self.plan.clear();
+        self.next_step = 0;
+    }
+
+    /// Run one command and return what it has to say.
+    pub fn execute(&mut self, line: &str) -> Result<Response, CommandError> {
+        let line = line.trim();
+        let (command, argument) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let argument = argument.trim();
+
+        let output = match command {
+            "" => String::new(),
+            "help" => HELP.to_owned(),
+            "quit" | "exit" => return Ok(Response::Quit),
+            "load" => {
+                let mut paths = argument.split_whitespace().map(Path::new);
+                let domain = paths
+                    .next()
+                    .ok_or_else(|| CommandError::new("`load` needs a domain file"))?;
+                self.load(format::load(domain, paths.next(), None)?);
+                format!(
+                    "loaded {} operations, {} states and {} goals",
+                    self.solver.operations().len(),
+                    self.states.len(),
+                    self.solver.goals().len()
+                )
+            }
+            "states" => write_states(&self.states),
+            "set" => {
+                let state = text::parse_state(argument)?;
+                self.check_not_derived(state.symbol())?;
+                self.save();
+                self.states.insert(state);
+                self.derive();
+                String::new()
+            }
+            "unset" => {
+                let state = text::parse_state(argument)?;
+                self.check_not_derived(state.symbol())?;
+                if self.states.get(state.name()).is_none()
+                    && !self.states.is_unknown(state.symbol())
+                {
+                    return Err(CommandError::new(format!(
+                        "there is no state `{}`",
+                        state.name()
+                    )));
+                }
+                self.save();
+                self.states.remove(state.name());
+                self.derive();
+                String::new()
+            }
+            "unknown" => {
+                let state = text::parse_state(argument)?;
+                if !matches!(state.data(), StateData::Symbol) {
+                    return Err(CommandError::new("an unknown state has no value"));
+                }
+                self.check_not_derived(state.symbol())?;
+                self.save();
+                self.states.insert_unknown(state.symbol());
+                self.derive();
+                String::new()
+            }
+            "goals" => self
+                .solver
+                .goals()
+                .iter()
+                .map(|goal| {
+                    let mark = if goal.check(&self.states) { "+" } else { "-" };
+                    format!("{mark} {}\n", text::write_condition(goal))
+                })
+                .collect(),
+            "goal" => {
+                let goal = text::parse_condition(argument)?;
+                self.save();
+                let mut goals = self.solver.goals().clone();
+                goals.push(goal);
+                self.solver.set_goals(goals);
+                self.plan.clear();
+                String::new()
+            }
+            "clear-goals" => {
+                self.save();
+                self.solver.set_goals(Vec::new());
+                self.plan.clear();
+                String::new()
+            }
+            "operations" => self.write_operations(),
+            "apply" => {
+                let operation = self.find_operation(argument)?;
+                self.apply(&operation)?
+            }
+            "plan" => self.plan()?,
+            "step" => {
+                let operation = self.plan.get(self.next_step).cloned().ok_or_else(|| {
+                    CommandError::new("there is no plan to follow, use `plan` first")
+                })?;
+                let changes = self.apply(&operation)?;
+                self.next_step += 1;
+                format!("{}. {}\n{changes}", self.next_step, operation.name())
+            }
+            "undo" => {
+                let snapshot = self
+                    .history
+                    .pop()
+                    .ok_or_else(|| CommandError::new("there is nothing to undo"))?;
+                let changes = write_changes(&self.states, &snapshot.states);
+                self.states = snapshot.states;
+                self.derive();
+                self.solver.set_goals(snapshot.goals);
+                self.plan = snapshot.plan;
+                self.next_step = snapshot.next_step;
+                changes
+            }
+            "reset" => {
+                self.save();
+                self.states = self.solver.states().clone();
+                self.derive();
+                self.plan.clear();
+                self.next_step = 0;
+                String::new()
+            }
+            _ => {
+                return Err(CommandError::new(format!(
+                    "unknown command `{command}`, try `help`"
+                )))
+            }
+        };
+
+        Ok(Response::Output(output))
+    }
+
+    fn save(&mut self) {
+        self.history.push(Snapshot {
+            states: self.states.clone(),
+            goals: self.solver.goals().clone(),
+            plan: self.plan.clone(),
+            next_step: self.next_step,
+        });
+    }
+
+    /// Recompute the derived states from the others.
+    fn derive(&mut self) {
+        self.solver.axioms().derive(&mut self.states);
+    }
+
+    /// Derived states follow from the others, so they cannot be edited.
+    fn check_not_derived(&self, state: Symbol) -> Result<(), CommandError> {
+        if self.solver.axioms().is_derived(state) {
+            return Err(CommandError::new(format!(
+                "`{state}` is derived from rules and cannot be changed"
+            )));
+        }
+        Ok(())
+    }
+
+    fn find_operation(&self, name: &str) -> Result<Operation, CommandError> {
+        let name = name.trim_matches('"');
+        self.solver
+            .operations()
+            .iter()
+            .find(|operation| operation.name() == name)
+            .cloned()
+            .ok_or_else(|| CommandError::new(format!("there is no operation `{name}`")))
+    }
+
+    /// Apply the operation if it is applicable and describe what changed.
+    fn apply(&mut self, operation: &Operation) -> Result<String, CommandError> {
+        let unsatisfied = operation.unsatisfied_prerequisites(&self.states);
+        if !unsatisfied.is_empty() {
+            return Err(CommandError::new(format!(
+                "`{}` is not applicable: {}",
+                operation.name(),
+                explain(&unsatisfied, &self.states)
+            )));
+        }
+
+        self.save();
+        operation.apply(&mut self.states);
+        self.derive();
+        Ok(write_changes(
+            &self.history.last().unwrap().states,
+            &self.states,
+        ))
+    }
+
+    fn plan(&mut self) -> Result<String, CommandError> {
+        let mut solver = self.solver.clone();
+        solver
+            .set_states(self.states.clone())
+            .set_limits(self.limits);
+        let cancellation = match self.timeout {
+            Some(timeout) => CancellationToken::with_deadline(Instant::now() + timeout),
+            None => CancellationToken::new(),
+        };
+
+        match solver.search(&cancellation).0 {
+            Outcome::Solved(operations) => {
+                let mut out = String::new();
+                for (step, operation) in operations.iter().enumerate() {
+                    writeln!(out, "{}. {}", step + 1, operation.name()).unwrap();
+                }
+                if operations.is_empty() {
+                    out.push_str("the goals already hold\n");
+                }
+                self.plan = operations;
+                self.next_step = 0;
+                Ok(out)
+            }
+            Outcome::Unsolvable => Err(CommandError::new("the goals cannot be achieved")),
+            Outcome::LimitExceeded => Err(CommandError::new("the search was cut short")),
+        }
+    }
+
+    fn write_operations(&self) -> String {
+        let mut applicable = String::new();
+        let mut blocked = String::new();
+
+        for operation in self.solver.operations() {
+            let unsatisfied = operation.unsatisfied_prerequisites(&self.states);
+            if unsatisfied.is_empty() {
+                writeln!(applicable, "  {}", operation.name()).unwrap();
+            } else {
+                writeln!(
+                    blocked,
+                    "  {}: {}",
+                    operation.name(),
+                    explain(&unsatisfied, &self.states)
+                )
+                .unwrap();
+            }
+        }
+
+        let mut out = String::new();
+        if !applicable.is_empty() {
+            writeln!(out, "applicable:\n{applicable}").unwrap();
+        }
+        if !blocked.is_empty() {
+            writeln!(out, "not applicable:\n{blocked}").unwrap();
+        }
+        out.trim_end().to_owned()
+    }
+}
+
+/// Describe why each condition does not hold.
+fn explain(conditions: &[&ConditionImpl], states: &StateSet) -> String {
+    let reasons: Vec<_> = conditions
+        .iter()
+        .map(|condition| {
+            let state_name = condition.state_name();
+            if states.is_unknown(condition.state_symbol()) {
+                return format!("`{state_name}` is unknown");
+            }
+            match (condition, states.get(state_name)) {
+                (ConditionImpl::Contain(_), _) => format!("`{state_name}` does not hold"),
+                (ConditionImpl::NotContain(_), _) => format!("`{state_name}` holds"),
+                (ConditionImpl::Compare(_), None) => format!(
+                    "`{}` needs `{state_name}`, which does not hold",
+                    condition.name()
+                ),
+                (ConditionImpl::Compare(_), Some(data)) => format!(
+                    "`{}` is false with `{}`",
+                    condition.name(),
+                    text::write_state(state_name, data)
+                ),
+            }
+        })
+        .collect();
+    reasons.join(", ")
+}
+
+fn write_states(states: &StateSet) -> String {
+    let mut known: Vec<_> = states.iter().collect();
+    known.sort_by_key(|(name, _)| name.as_str());
+    known
+        .into_iter()
+        .map(|(name, data)| format!("{}\n", text::write_state(name.as_str(), data)))
+        .chain(write_unknown(states))
+        .collect()
+}
+
+fn write_unknown(states: &StateSet) -> Vec<String> {
+    let mut names: Vec<_> = states.unknown().map(|name| name.as_str()).collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| format!("unknown {}\n", text::write_name(name)))
+        .collect()
+}
+
+/// The states added, removed or changed between two sets of states.
+fn write_changes(before: &StateSet, after: &StateSet) -> String {
+    let mut names: Vec<_> = before
+        .iter()
+        .chain(after.iter())
+        .map(|(name, _)| name)
+        .collect();
+    names.sort_by_key(|name| name.as_str());
+    names.dedup();
+
+    let mut out = String::new();
+    for name in names {
+        match (before.get_by_symbol(name), after.get_by_symbol(name)) {
+            (None, Some(data)) => {
+                writeln!(out, "+ {}", text::write_state(name.as_str(), data)).unwrap()
+            }
+            (Some(data), None) => {
+                writeln!(out, "- {}", text::write_state(name.as_str(), data)).unwrap()
+            }
+            (Some(old), Some(new)) if old != new => {
+                writeln!(out, "~ {}", text::write_state(name.as_str(), new)).unwrap()
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Read commands until the input ends or `quit` is given, writing a prompt
+/// before each command when asked to.
+pub fn run(
+    session: &mut Session,
+    input: impl BufRead,
+    mut output: impl Write,
+    prompt: bool,
+) -> io::Result<()> {
+    let mut lines = input.lines();
+
+    loop {
+        if prompt {
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+
+        match session.execute(&line?) {
+            Ok(Response::Quit) => return Ok(()),
+            Ok(Response::Output(text)) if text.is_empty() => {}
+            Ok(Response::Output(text)) => writeln!(output, "{}", text.trim_end())?,
+            Err(error) => writeln!(output, "error: {error}")?,
+        }
+    }
+}
+
+impl CommandError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for CommandError {}
+
+impl From<ParseError> for CommandError {
+    fn from(value: ParseError) -> Self {
+        Self::new(value.to_string())
+    }
+}
+
+impl From<LoadError> for CommandError {
+    fn from(value: LoadError) -> Self {
+        Self::new(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHOOL: &str = "
+        state son-at-home
+        state have-money
+        state car-needs-battery
+        operation drive-son-to-school {
+            require son-at-home
+            require car-works
+            add son-at-school
+            remove son-at-home
+        }
+        operation shop-installs-battery {
+            require car-needs-battery
+            require have-money
+            add car-works
+            remove have-money
+        }
+        goal son-at-school";
+
+    fn session() -> Session {
+        let mut session = Session::new();
+        session.load(text::parse(SCHOOL).unwrap());
+        session
+    }
+
+    fn output(session: &mut Session, line: &str) -> String {
+        match session.execute(line).unwrap() {
+            Response::Output(text) => text,
+            Response::Quit => panic!("unexpected quit"),
+        }
+    }
+
+    #[test]
+    fn it_should_explain_why_operations_are_not_applicable() {
+        let mut session = session();
+        assert_eq!(
+            output(&mut session, "operations"),
+            "applicable:\n  shop-installs-battery\n\n\
+             not applicable:\n  drive-son-to-school: `car-works` does not hold"
+        );
+
+        let error = session.execute("apply drive-son-to-school").unwrap_err();
+        assert_eq!(
+            error.message(),
+            "`drive-son-to-school` is not applicable: `car-works` does not hold"
+        );
+    }
+
+    #[test]
+    fn it_should_step_through_a_plan_and_undo() {
+        let mut session = session();
+        assert_eq!(
+            output(&mut session, "plan"),
+            "1. shop-installs-battery\n2. drive-son-to-school\n"
+        );
+        assert_eq!(
+            output(&mut session, "step"),
+            "1. shop-installs-battery\n+ car-works\n- have-money\n"
+        );
+        assert_eq!(output(&mut session, "undo"), "- car-works\n+ have-money\n");
+
+        output(&mut session, "step");
+        output(&mut session, "goal car-works");
+        assert!(session.execute("step").is_err());
+        output(&mut session, "undo");
+        output(&mut session, "step");
+        assert_eq!(output(&mut session, "goals"), "+ son-at-school\n");
+        assert!(session.execute("step").is_err());
+
+        output(&mut session, "reset");
+        output(&mut session, "undo");
+        assert_eq!(
+            output(&mut session, "undo"),
+            "+ son-at-home\n- son-at-school\n"
+        );
+        assert_eq!(
+            output(&mut session, "step"),
+            "2. drive-son-to-school\n- son-at-home\n+ son-at-school\n"
+        );
+    }
+
+    #[test]
+    fn it_should_plan_within_the_limits_of_the_session() {
+        let mut session = Session::new().with_limits(Limits::new().with_max_expansions(1));
+        session.load(text::parse(SCHOOL).unwrap());
+        assert_eq!(
+            session.execute("plan").unwrap_err().message(),
+            "the search was cut short"
+        );
+    }
+
+    #[test]
+    fn it_should_edit_states_and_goals() {
+        let mut session = session();
+        output(&mut session, "unset have-money");
+        output(&mut session, "set money = 3");
+        output(&mut session, "goal money >= 5");
+        assert_eq!(
+            session.execute("plan").unwrap_err().message(),
+            "the goals cannot be achieved"
+        );
+        assert_eq!(
+            output(&mut session, "states"),
+            "car-needs-battery\nmoney = 3\nson-at-home\n"
+        );
+
+        let mut transcript = Vec::new();
+        run(
+            &mut session,
+            "undo\nundo\nstates\nquit\nstates\n".as_bytes(),
+            &mut transcript,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(transcript).unwrap(),
+            "- money = 3\ncar-needs-battery\nson-at-home\n"
+        );
+
+        output(&mut session, "unknown son-at-home");
+        assert_eq!(
+            output(&mut session, "states"),
+            "car-needs-battery\nunknown son-at-home\n"
+        );
+        output(&mut session, "unset son-at-home");
+        assert_eq!(output(&mut session, "states"), "car-needs-battery\n");
+    }
+
+    #[test]
+    fn it_should_keep_derived_states_up_to_date() {
+        let mut session = Session::new();
+        session.load(
+            text::parse(
+                "state battery-ok
+                 operation fix-tires { add tires-ok }
+                 rule car-works { require battery-ok require tires-ok }",
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            output(&mut session, "apply fix-tires"),
+            "+ car-works\n+ tires-ok\n"
+        );
+        assert_eq!(output(&mut session, "unset battery-ok"), "");
+        assert_eq!(output(&mut session, "states"), "tires-ok\n");
+        assert_eq!(
+            session.execute("set car-works").unwrap_err().message(),
+            "`car-works` is derived from rules and cannot be changed"
+        );
+    }
+}
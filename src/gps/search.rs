@@ -0,0 +1,100 @@
+use super::operation::Operation;
+
+/// Bounds on the effort spent by one search. Time is bounded by the deadline
+/// of the [`CancellationToken`] instead.
+///
+/// [`CancellationToken`]: super::portfolio::CancellationToken
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    max_expansions: Option<usize>,
+    max_depth: Option<usize>,
+}
+
+/// What happened during one search.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Statistics {
+    expansions: usize,
+    goals: usize,
+    max_depth: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    Solved(Vec<Operation>),
+    /// Every way of achieving the goals has been tried.
+    Unsolvable,
+    /// The search was cut short, so a plan may still exist.
+    LimitExceeded,
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop after trying this many operations.
+    pub fn with_max_expansions(mut self, max_expansions: usize) -> Self {
+        self.max_expansions = Some(max_expansions);
+        self
+    }
+
+    /// Do not pursue subgoals nested deeper than this.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn max_expansions(&self) -> Option<usize> {
+        self.max_expansions
+    }
+
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+}
+
+impl Statistics {
+    /// The number of operations tried.
+    pub fn expansions(&self) -> usize {
+        self.expansions
+    }
+
+    /// The number of unachieved goals the search has tried to achieve.
+    pub fn goals(&self) -> usize {
+        self.goals
+    }
+
+    /// The deepest nesting of subgoals reached.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub(super) fn record_expansion(&mut self) {
+        self.expansions += 1;
+    }
+
+    pub(super) fn record_goal(&mut self, depth: usize) {
+        self.goals += 1;
+        self.max_depth = self.max_depth.max(depth);
+    }
+
+    /// Count the work of another search as well.
+    pub(super) fn add(&mut self, other: &Statistics) {
+        self.expansions += other.expansions;
+        self.goals += other.goals;
+        self.max_depth = self.max_depth.max(other.max_depth);
+    }
+}
+
+impl Outcome {
+    pub fn is_solved(&self) -> bool {
+        matches!(self, Outcome::Solved(_))
+    }
+
+    pub fn into_operations(self) -> Option<Vec<Operation>> {
+        match self {
+            Outcome::Solved(operations) => Some(operations),
+            _ => None,
+        }
+    }
+}
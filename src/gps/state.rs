@@ -1,46 +1,53 @@
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use super::condition::{Condition, ConditionImpl};
+use super::symbol::Symbol;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct State {
-    name: String,
+    name: Symbol,
     data: StateData,
 }
 
-#[derive(Debug, Clone, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum StateData {
     Symbol,
     Integer(i32),
 }
 
+/// The states which hold. A state which is neither held nor unknown is known
+/// not to hold.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StateSet {
-    states: HashMap<String, StateData>,
+    states: HashMap<Symbol, StateData>,
+    /// States which have not been observed yet.
+    unknown: HashSet<Symbol>,
 }
 
 impl State {
     pub fn new(name: String, data: StateData) -> Self {
+        Self::with_symbol(name.into(), data)
+    }
+
+    pub fn with_symbol(name: Symbol, data: StateData) -> Self {
         Self { name, data }
     }
 
     pub fn new_symbol(name: String) -> Self {
-        Self {
-            name,
-            data: StateData::Symbol,
-        }
+        Self::new(name, StateData::Symbol)
     }
 
     pub fn new_integer(name: String, data: i32) -> Self {
-        Self {
-            name,
-            data: StateData::Integer(data),
-        }
+        Self::new(name, StateData::Integer(data))
     }
 
     pub fn name(&self) -> &str {
-        &self.name
+        self.name.as_str()
+    }
+
+    pub fn symbol(&self) -> Symbol {
+        self.name
     }
 
     pub fn data(&self) -> &StateData {
@@ -51,23 +58,11 @@ impl State {
         &mut self.data
     }
 
-    pub fn into_inner(self) -> (String, StateData) {
+    pub fn into_inner(self) -> (Symbol, StateData) {
         (self.name, self.data)
     }
 }
 
-impl PartialEq for StateData {
-    fn eq(&self, other: &Self) -> bool {
-        match self {
-            StateData::Symbol => matches!(other, StateData::Symbol),
-            StateData::Integer(x) => match other {
-                StateData::Integer(y) => x == y,
-                _ => false,
-            },
-        }
-    }
-}
-
 impl PartialOrd for StateData {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match self {
@@ -83,38 +78,91 @@ impl PartialOrd for StateData {
     }
 }
 
+impl Default for StateSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl StateSet {
     pub fn new() -> Self {
         Self {
             states: HashMap::new(),
+            unknown: HashSet::new(),
         }
     }
 
+    /// Make the state hold, so it is known from then on.
     pub fn insert(&mut self, state: State) -> bool {
         let (name, data) = state.into_inner();
+        self.unknown.remove(&name);
         self.states.insert(name, data).is_none()
     }
 
+    /// Make the state not hold, so it is known from then on.
     pub fn remove(&mut self, name: &str) -> Option<StateData> {
-        self.states.remove(name)
+        self.remove_by_symbol(Symbol::lookup(name)?)
+    }
+
+    pub fn remove_by_symbol(&mut self, name: Symbol) -> Option<StateData> {
+        self.unknown.remove(&name);
+        self.states.remove(&name)
+    }
+
+    /// Forget whether the state holds and what its value is.
+    pub fn insert_unknown(&mut self, name: Symbol) -> bool {
+        self.states.remove(&name);
+        self.unknown.insert(name)
+    }
+
+    pub fn is_unknown(&self, name: Symbol) -> bool {
+        self.unknown.contains(&name)
+    }
+
+    /// Iterate over the unknown states in no particular order.
+    pub fn unknown(&self) -> impl Iterator<Item = Symbol> + '_ {
+        self.unknown.iter().copied()
     }
 
     pub fn get(&self, name: &str) -> Option<&StateData> {
-        self.states.get(name)
+        self.get_by_symbol(Symbol::lookup(name)?)
+    }
+
+    pub fn get_by_symbol(&self, name: Symbol) -> Option<&StateData> {
+        self.states.get(&name)
     }
 
     pub fn get_mut(&mut self, name: &str) -> Option<&mut StateData> {
-        self.states.get_mut(name)
+        self.get_mut_by_symbol(Symbol::lookup(name)?)
+    }
+
+    pub fn get_mut_by_symbol(&mut self, name: Symbol) -> Option<&mut StateData> {
+        self.states.get_mut(&name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// Iterate over the states which hold in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &StateData)> {
+        self.states.iter().map(|(name, data)| (*name, data))
     }
 
     pub fn contains(&self, state: &State) -> bool {
-        match self.states.get(state.name()) {
+        match self.states.get(&state.symbol()) {
             Some(data) => data == state.data(),
             None => false,
         }
     }
-    pub fn has_reached(&self, goals: &Vec<ConditionImpl>) -> bool {
-        // Test if the goal state is a subset of current states.
+
+    pub fn has_reached(&self, goals: &[ConditionImpl]) -> bool {
+        // Test if the goal state is a subset of current states. Goals on
+        // unknown states are not reached.
         goals.iter().all(|condition| condition.check(self))
     }
 }
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use std::sync::{OnceLock, RwLock};
+
+/// A compact id standing for an interned name. Symbols of the same name are
+/// always equal, so comparing and hashing them is an integer operation.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+/// Maps names to symbols. Writers hold the lock of the global table, while
+/// the names of symbols are read from [`NAMES`] without one.
+#[derive(Debug, Default)]
+struct SymbolTable {
+    ids: HashMap<&'static str, Symbol>,
+    len: u32,
+}
+
+/// The names of the symbols by id, in chunks which double in size and are
+/// never moved once allocated. Chunk `k` holds the ids from `2^k - 1`.
+static NAMES: [OnceLock<Box<[OnceLock<&'static str>]>>; 32] = [const { OnceLock::new() }; 32];
+
+impl Symbol {
+    /// Intern the given name in the global symbol table.
+    pub fn intern(name: &str) -> Self {
+        if let Some(symbol) = Self::lookup(name) {
+            return symbol;
+        }
+
+        SymbolTable::global()
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .intern(name)
+    }
+
+    /// Find the symbol of the given name without interning it.
+    pub fn lookup(name: &str) -> Option<Self> {
+        SymbolTable::global()
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .lookup(name)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        let (chunk, offset) = self.location();
+        NAMES[chunk]
+            .get()
+            .and_then(|names| names[offset].get())
+            .expect("symbols are only handed out once their name is stored")
+    }
+
+    pub fn id(self) -> u32 {
+        self.0
+    }
+
+    /// The chunk of [`NAMES`] holding the name and its position there.
+    fn location(self) -> (usize, usize) {
+        let index = self.0 + 1;
+        let chunk = index.ilog2();
+        (chunk as usize, (index - (1 << chunk)) as usize)
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(value: &str) -> Self {
+        Self::intern(value)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(value: String) -> Self {
+        Self::intern(&value)
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(self.as_str())
+    }
+}
+
+impl SymbolTable {
+    fn global() -> &'static RwLock<SymbolTable> {
+        static TABLE: OnceLock<RwLock<SymbolTable>> = OnceLock::new();
+        TABLE.get_or_init(|| RwLock::new(SymbolTable::default()))
+    }
+
+    fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(symbol) = self.lookup(name) {
+            return symbol;
+        }
+
+        // Interned names live as long as the program, which lets symbols
+        // hand out `&'static str` without holding the lock.
+        let name: &'static str = Box::leak(name.to_owned().into_boxed_str());
+        let symbol = Symbol(self.len);
+        self.len += 1;
+        let (chunk, offset) = symbol.location();
+        let names = NAMES[chunk].get_or_init(|| (0..1 << chunk).map(|_| OnceLock::new()).collect());
+        let _ = names[offset].set(name);
+        self.ids.insert(name, symbol);
+        symbol
+    }
+
+    fn lookup(&self, name: &str) -> Option<Symbol> {
+        self.ids.get(name).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_intern_the_same_name_into_the_same_symbol() {
+        let a = Symbol::intern("symbol-test-name");
+        let b = Symbol::from("symbol-test-name".to_owned());
+        assert_eq!(a, b);
+        assert_ne!(a, Symbol::intern("symbol-test-other-name"));
+        assert_eq!(a.as_str(), "symbol-test-name");
+    }
+
+    #[test]
+    fn it_should_not_intern_names_on_lookup() {
+        assert!(Symbol::lookup("symbol-test-never-interned").is_none());
+    }
+}
@@ -0,0 +1,611 @@
+//! Operations which take time.
+//!
+//! A [`DurativeOperation`] has an operation for its start and one for its
+//! end, whose prerequisites must hold at those points and whose effects
+//! happen there, and conditions which must hold over the whole time between.
+//!
+//! [`TemporalSolver`] compresses each durative operation into an ordinary
+//! one, solves the problem with GPS and then spreads the steps out in time,
+//! starting each as soon as the steps it interferes with have ended. If that
+//! schedule fails [`validate`], the steps run one after the other instead.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use super::axiom::Axioms;
+use super::condition::{Condition, ConditionImpl};
+use super::format::text::write_condition;
+use super::operation::{Operation, OperationBuilder};
+use super::plan::schedule::Schedule;
+use super::state::StateSet;
+use super::symbol::Symbol;
+use super::GeneralProblemSolver;
+
+#[derive(Debug, Clone)]
+pub struct DurativeOperation {
+    duration: u32,
+    at_start: Operation,
+    over_all: Vec<ConditionImpl>,
+    at_end: Operation,
+    /// The start and the end as one operation, for planning.
+    compressed: Operation,
+}
+
+#[derive(Debug, Clone)]
+pub struct TemporalSolver {
+    operations: Vec<DurativeOperation>,
+    states: StateSet,
+    goals: Vec<ConditionImpl>,
+    invariants: Vec<ConditionImpl>,
+}
+
+/// Durative operations with the times they start at.
+#[derive(Debug, Clone, Default)]
+pub struct TemporalPlan {
+    steps: Vec<TimedStep>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TimedStep {
+    operation: DurativeOperation,
+    start: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Start,
+    OverAll,
+    End,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// A condition of the step at the position did not hold at the time.
+    ConditionFailed {
+        step: usize,
+        time: u32,
+        phase: Phase,
+        condition: ConditionImpl,
+    },
+    /// The invariant did not hold initially, or after the step at the
+    /// position started or ended at the time.
+    InvariantBroken {
+        step: Option<usize>,
+        time: u32,
+        invariant: ConditionImpl,
+    },
+    GoalFailed(ConditionImpl),
+}
+
+impl DurativeOperation {
+    /// A durative operation named `name`. The prerequisites and effects of
+    /// `at_start` and `at_end` apply when it starts and ends, and `over_all`
+    /// must hold while it runs.
+    pub fn new(
+        name: String,
+        duration: u32,
+        at_start: Operation,
+        over_all: Vec<ConditionImpl>,
+        at_end: Operation,
+    ) -> Self {
+        let compressed = compress(name, duration, &at_start, &over_all, &at_end);
+        Self {
+            duration,
+            at_start,
+            over_all,
+            at_end,
+            compressed,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.compressed.name()
+    }
+
+    pub fn symbol(&self) -> Symbol {
+        self.compressed.symbol()
+    }
+
+    pub fn duration(&self) -> u32 {
+        self.duration
+    }
+
+    pub fn at_start(&self) -> &Operation {
+        &self.at_start
+    }
+
+    pub fn over_all(&self) -> &[ConditionImpl] {
+        &self.over_all
+    }
+
+    pub fn at_end(&self) -> &Operation {
+        &self.at_end
+    }
+
+    /// An operation with the conditions and the net effects of the whole
+    /// durative operation, costing its duration.
+    pub fn compressed(&self) -> &Operation {
+        &self.compressed
+    }
+}
+
+fn compress(
+    name: String,
+    duration: u32,
+    at_start: &Operation,
+    over_all: &[ConditionImpl],
+    at_end: &Operation,
+) -> Operation {
+    let started: Vec<Symbol> = at_start
+        .add_states()
+        .iter()
+        .map(|state| state.symbol())
+        .chain(at_start.remove_states().iter().copied())
+        .chain(
+            at_start
+                .modification_states()
+                .iter()
+                .map(|modification| modification.target_symbol()),
+        )
+        .collect();
+    let ends_adding = |name: Symbol| at_end.add_states().iter().any(|s| s.symbol() == name);
+    let ends_removing = |name: Symbol| at_end.remove_states().contains(&name);
+
+    let mut builder = OperationBuilder::new(name).set_cost(duration);
+    // Later conditions the start takes care of are left to the validator.
+    let later_conditions = over_all
+        .iter()
+        .chain(at_end.prerequisites())
+        .filter(|condition| !started.contains(&condition.state_symbol()));
+    for condition in at_start.prerequisites().iter().chain(later_conditions) {
+        builder = builder.insert_prerequisite(condition.clone());
+    }
+
+    // The end overrides what the start did to the same state.
+    for state in at_start.add_states() {
+        if !ends_adding(state.symbol()) && !ends_removing(state.symbol()) {
+            builder = builder.insert_add_state(state.clone());
+        }
+    }
+    for name in at_start.remove_states() {
+        if !ends_adding(*name) {
+            builder = builder.insert_remove_state(name.as_str().to_owned());
+        }
+    }
+    for state in at_end.add_states() {
+        builder = builder.insert_add_state(state.clone());
+    }
+    for name in at_end.remove_states() {
+        builder = builder.insert_remove_state(name.as_str().to_owned());
+    }
+    for modification in at_start
+        .modification_states()
+        .iter()
+        .chain(at_end.modification_states())
+    {
+        builder = builder.insert_modify_state(modification.clone());
+    }
+    builder.build()
+}
+
+impl TemporalSolver {
+    pub fn new(
+        operations: Vec<DurativeOperation>,
+        states: StateSet,
+        goals: Vec<ConditionImpl>,
+    ) -> Self {
+        Self {
+            operations,
+            states,
+            goals,
+            invariants: Vec::new(),
+        }
+    }
+
+    /// Keep the invariants true in every state of the plan.
+    pub fn with_invariants(mut self, invariants: Vec<ConditionImpl>) -> Self {
+        self.invariants = invariants;
+        self
+    }
+
+    pub fn operations(&self) -> &[DurativeOperation] {
+        &self.operations
+    }
+
+    pub fn states(&self) -> &StateSet {
+        &self.states
+    }
+
+    pub fn goals(&self) -> &[ConditionImpl] {
+        &self.goals
+    }
+
+    pub fn invariants(&self) -> &[ConditionImpl] {
+        &self.invariants
+    }
+
+    /// Find a valid temporal plan, running steps in parallel where possible.
+    pub fn solve(&self) -> Option<TemporalPlan> {
+        let mut solver = GeneralProblemSolver::new();
+        solver
+            .set_operations(
+                self.operations
+                    .iter()
+                    .map(|operation| operation.compressed.clone())
+                    .collect(),
+            )
+            .set_states(self.states.clone())
+            .set_goals(self.goals.clone())
+            .set_invariants(self.invariants.clone());
+        let sequence = solver.solve()?;
+
+        let by_name: HashMap<Symbol, &DurativeOperation> = self
+            .operations
+            .iter()
+            .map(|operation| (operation.symbol(), operation))
+            .collect();
+        let durative = |operation: &Operation| by_name[&operation.symbol()];
+
+        let schedule = Schedule::with_rules(
+            &sequence,
+            |operation| durative(operation).duration,
+            &self.invariants,
+            &[],
+            &Axioms::default(),
+        );
+        let parallel = TemporalPlan::new(
+            schedule
+                .slots()
+                .iter()
+                .map(|slot| (durative(slot.operation()).clone(), slot.start()))
+                .collect(),
+        );
+        if validate(&self.states, &parallel, &self.goals, &self.invariants).is_ok() {
+            return Some(parallel);
+        }
+
+        let mut time = 0;
+        let mut steps = Vec::new();
+        for operation in &sequence {
+            let operation = durative(operation);
+            steps.push((operation.clone(), time));
+            time += operation.duration;
+        }
+        let sequential = TemporalPlan::new(steps);
+        validate(&self.states, &sequential, &self.goals, &self.invariants)
+            .is_ok()
+            .then_some(sequential)
+    }
+}
+
+impl TemporalPlan {
+    pub fn new(steps: Vec<(DurativeOperation, u32)>) -> Self {
+        Self {
+            steps: steps
+                .into_iter()
+                .map(|(operation, start)| TimedStep { operation, start })
+                .collect(),
+        }
+    }
+
+    pub fn steps(&self) -> &[TimedStep] {
+        &self.steps
+    }
+
+    /// When the last step ends.
+    pub fn makespan(&self) -> u32 {
+        self.steps.iter().map(TimedStep::end).max().unwrap_or(0)
+    }
+}
+
+/// One step per line in the style of PDDL 2.1, `start: (name) [duration]`,
+/// in the order the steps start.
+impl Display for TemporalPlan {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let mut steps: Vec<_> = self.steps.iter().collect();
+        steps.sort_by_key(|step| step.start);
+        for step in steps {
+            writeln!(
+                f,
+                "{}: ({}) [{}]",
+                step.start,
+                step.operation.name(),
+                step.operation.duration
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl TimedStep {
+    pub fn operation(&self) -> &DurativeOperation {
+        &self.operation
+    }
+
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    pub fn end(&self) -> u32 {
+        self.start + self.operation.duration
+    }
+}
+
+/// Execute a temporal plan from the given states and return the states at
+/// the end, or the first condition which does not hold.
+///
+/// Steps starting and ending at the same time are ordered so that steps
+/// ending then come first, except for steps taking no time, which end right
+/// after they start. The conditions over all of a step must hold after
+/// every change while it runs, and the invariants after every change at all.
+pub fn validate(
+    states: &StateSet,
+    plan: &TemporalPlan,
+    goals: &[ConditionImpl],
+    invariants: &[ConditionImpl],
+) -> Result<StateSet, ValidationError> {
+    // Events are (time, rank, step, is end).
+    let mut events = Vec::new();
+    for (position, step) in plan.steps.iter().enumerate() {
+        events.push((step.start, 1, position, false));
+        let rank = if step.operation.duration == 0 { 2 } else { 0 };
+        events.push((step.end(), rank, position, true));
+    }
+    events.sort();
+
+    let broken = |states: &StateSet, step, time| match invariants
+        .iter()
+        .find(|invariant| !invariant.check(states))
+    {
+        Some(invariant) => Err(ValidationError::InvariantBroken {
+            step,
+            time,
+            invariant: invariant.clone(),
+        }),
+        None => Ok(()),
+    };
+
+    let mut states = states.clone();
+    broken(&states, None, 0)?;
+    let mut running = vec![false; plan.steps.len()];
+    let failed = |step, time, phase, condition: &ConditionImpl| {
+        Err(ValidationError::ConditionFailed {
+            step,
+            time,
+            phase,
+            condition: condition.clone(),
+        })
+    };
+
+    for (time, _, position, is_end) in events {
+        let operation = &plan.steps[position].operation;
+        let (snap, phase) = if is_end {
+            running[position] = false;
+            (&operation.at_end, Phase::End)
+        } else {
+            (&operation.at_start, Phase::Start)
+        };
+        if let Some(condition) = snap.unsatisfied_prerequisites(&states).first() {
+            return failed(position, time, phase, condition);
+        }
+        snap.apply(&mut states);
+        broken(&states, Some(position), time)?;
+        running[position] |= !is_end;
+
+        for (position, step) in plan.steps.iter().enumerate() {
+            if !running[position] {
+                continue;
+            }
+            let broken = step.operation.over_all.iter().find(|c| !c.check(&states));
+            if let Some(condition) = broken {
+                return failed(position, time, Phase::OverAll, condition);
+            }
+        }
+    }
+
+    match goals.iter().find(|goal| !goal.check(&states)) {
+        Some(goal) => Err(ValidationError::GoalFailed(goal.clone())),
+        None => Ok(states),
+    }
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_str(match self {
+            Phase::Start => "start",
+            Phase::OverAll => "over all",
+            Phase::End => "end",
+        })
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            ValidationError::ConditionFailed {
+                step,
+                time,
+                phase,
+                condition,
+            } => write!(
+                f,
+                "step {} at time {time}: {phase} condition `{}` does not hold",
+                step + 1,
+                write_condition(condition)
+            ),
+            ValidationError::InvariantBroken {
+                step: Some(step),
+                time,
+                invariant,
+            } => write!(
+                f,
+                "step {} at time {time}: invariant `{}` does not hold",
+                step + 1,
+                write_condition(invariant)
+            ),
+            ValidationError::InvariantBroken { invariant, .. } => {
+                write!(
+                    f,
+                    "invariant `{}` does not hold initially",
+                    write_condition(invariant)
+                )
+            }
+            ValidationError::GoalFailed(goal) => {
+                write!(
+                    f,
+                    "goal `{}` does not hold at the end",
+                    write_condition(goal)
+                )
+            }
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps::condition::{Contain, NotContain};
+    use crate::gps::format::text;
+
+    fn snap(source: &str) -> Operation {
+        text::parse(&format!("operation snap {{ {source} }}"))
+            .unwrap()
+            .operations()[0]
+            .clone()
+    }
+
+    fn durative(
+        name: &str,
+        duration: u32,
+        start: &str,
+        over_all: &[&str],
+        end: &str,
+    ) -> DurativeOperation {
+        DurativeOperation::new(
+            name.to_owned(),
+            duration,
+            snap(start),
+            over_all
+                .iter()
+                .map(|state| Contain::new((*state).to_owned()).into())
+                .collect(),
+            snap(end),
+        )
+    }
+
+    fn kitchen() -> TemporalSolver {
+        let problem = text::parse("state have-water state oven-idle state have-dough").unwrap();
+        TemporalSolver::new(
+            vec![
+                durative(
+                    "boil-water",
+                    3,
+                    "require have-water remove have-water add kettle-busy",
+                    &[],
+                    "add hot-water remove kettle-busy",
+                ),
+                durative("preheat-oven", 5, "require oven-idle", &[], "add oven-hot"),
+                durative(
+                    "bake",
+                    4,
+                    "require oven-hot require have-dough",
+                    &["oven-hot"],
+                    "add bread remove have-dough",
+                ),
+                durative("make-tea", 1, "require hot-water", &[], "add tea"),
+                durative("cool-oven", 1, "remove oven-hot", &[], ""),
+            ],
+            problem.states().clone(),
+            vec![
+                Contain::new("bread".to_owned()).into(),
+                Contain::new("tea".to_owned()).into(),
+            ],
+        )
+    }
+
+    #[test]
+    fn it_should_run_independent_steps_at_the_same_time() {
+        let solver = kitchen();
+        let plan = solver.solve().unwrap();
+        assert_eq!(
+            plan.to_string(),
+            "0: (preheat-oven) [5]\n0: (boil-water) [3]\n3: (make-tea) [1]\n5: (bake) [4]\n"
+        );
+        assert_eq!(plan.makespan(), 9);
+        assert!(validate(solver.states(), &plan, solver.goals(), &[]).is_ok());
+    }
+
+    #[test]
+    fn it_should_report_the_first_condition_which_does_not_hold() {
+        let solver = kitchen();
+        let operation = |name: &str| {
+            solver
+                .operations()
+                .iter()
+                .find(|operation| operation.name() == name)
+                .unwrap()
+                .clone()
+        };
+
+        let early = TemporalPlan::new(vec![(operation("preheat-oven"), 0), (operation("bake"), 2)]);
+        let error = validate(solver.states(), &early, solver.goals(), &[]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "step 2 at time 2: start condition `oven-hot` does not hold"
+        );
+
+        let interrupted = TemporalPlan::new(vec![
+            (operation("preheat-oven"), 0),
+            (operation("bake"), 5),
+            (operation("cool-oven"), 6),
+        ]);
+        let error = validate(solver.states(), &interrupted, solver.goals(), &[]).unwrap_err();
+        assert!(matches!(
+            error,
+            ValidationError::ConditionFailed {
+                step: 1,
+                time: 6,
+                phase: Phase::OverAll,
+                ..
+            }
+        ));
+
+        let unfinished = TemporalPlan::new(vec![(operation("preheat-oven"), 0)]);
+        let error = validate(solver.states(), &unfinished, solver.goals(), &[]).unwrap_err();
+        assert_eq!(error.to_string(), "goal `bread` does not hold at the end");
+
+        let cold: ConditionImpl = NotContain::new("oven-hot".to_owned()).into();
+        let goals = [cold.clone()];
+        let error = validate(solver.states(), &unfinished, &goals, &[]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "goal `not oven-hot` does not hold at the end"
+        );
+        let error = validate(solver.states(), &unfinished, &[], &[cold]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "step 1 at time 5: invariant `not oven-hot` does not hold"
+        );
+    }
+
+    #[test]
+    fn it_should_leave_conditions_the_start_achieves_to_the_validator() {
+        let solver = TemporalSolver::new(
+            vec![durative(
+                "cook",
+                3,
+                "add stove-on",
+                &["stove-on"],
+                "add meal",
+            )],
+            StateSet::new(),
+            vec![Contain::new("meal".to_owned()).into()],
+        );
+        let plan = solver.solve().unwrap();
+        assert_eq!(plan.to_string(), "0: (cook) [3]\n");
+        assert!(validate(solver.states(), &plan, solver.goals(), &[]).is_ok());
+    }
+}
@@ -0,0 +1,366 @@
+//! Constraints over the whole trajectory of a plan, in the spirit of PDDL3.
+//!
+//! A trajectory is the sequence of states a plan goes through, starting with
+//! the initial states. A [`Constraint`] talks about [`Proposition`]s holding
+//! at points of the trajectory: a condition holding in the states there, or
+//! an operation having been applied to reach them. A [`Monitor`] is
+//! progressed one step at a time, so a search can drop a branch as soon as
+//! it breaks a constraint.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use super::axiom::Axioms;
+use super::condition::{Condition, ConditionImpl};
+use super::format::text::{write_condition, write_name};
+use super::operation::Operation;
+use super::plan::Step;
+use super::state::StateSet;
+use super::symbol::Symbol;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Proposition {
+    /// The condition holds in the states.
+    Condition(ConditionImpl),
+    /// The operation was applied to reach the states.
+    Operation(Symbol),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Constraint {
+    /// The proposition holds at every point.
+    Always(Proposition),
+    /// The proposition holds at some point.
+    Sometime(Proposition),
+    /// The proposition holds during at most one stretch of points. Every
+    /// application of an operation counts on its own.
+    AtMostOnce(Proposition),
+    /// Whenever the first proposition holds, the second one held at some
+    /// earlier point.
+    SometimeBefore(Proposition, Proposition),
+}
+
+/// Follows a trajectory and tells when it breaks a constraint.
+#[derive(Debug, Clone)]
+pub struct Monitor {
+    constraints: Vec<Constraint>,
+    progress: Vec<Progress>,
+    /// The point of the trajectory seen last.
+    step: Step,
+}
+
+/// What a monitor remembers about one constraint.
+#[derive(Debug, Clone, Copy, Default)]
+struct Progress {
+    /// Whether the proposition, or for `SometimeBefore` the second one, has
+    /// held at some point so far.
+    seen: bool,
+    /// Whether the proposition held at the last point.
+    holding: bool,
+}
+
+/// A constraint broken at a point of a trajectory. [`Step::Goal`] stands for
+/// the end, where a proposition which should have held sometime never did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    constraint: Constraint,
+    step: Step,
+}
+
+impl Proposition {
+    pub fn condition(&self) -> Option<&ConditionImpl> {
+        match self {
+            Self::Condition(condition) => Some(condition),
+            Self::Operation(_) => None,
+        }
+    }
+
+    fn holds(&self, operation: Option<Symbol>, states: &StateSet) -> bool {
+        match self {
+            Self::Condition(condition) => condition.check(states),
+            Self::Operation(name) => operation == Some(*name),
+        }
+    }
+
+    /// Turn conditions on a state named like one of the operations into
+    /// propositions on the operation, for formats which do not tell them
+    /// apart.
+    pub fn resolve(self, operations: &[Operation]) -> Self {
+        match self {
+            Self::Condition(ConditionImpl::Contain(condition))
+                if operations
+                    .iter()
+                    .any(|operation| operation.symbol() == condition.symbol()) =>
+            {
+                Self::Operation(condition.symbol())
+            }
+            proposition => proposition,
+        }
+    }
+}
+
+impl Constraint {
+    pub fn propositions(&self) -> Vec<&Proposition> {
+        match self {
+            Self::Always(proposition)
+            | Self::Sometime(proposition)
+            | Self::AtMostOnce(proposition) => vec![proposition],
+            Self::SometimeBefore(first, second) => vec![first, second],
+        }
+    }
+
+    /// The constraint with its propositions resolved as by
+    /// [`Proposition::resolve`].
+    pub fn resolve(self, operations: &[Operation]) -> Self {
+        match self {
+            Self::Always(proposition) => Self::Always(proposition.resolve(operations)),
+            Self::Sometime(proposition) => Self::Sometime(proposition.resolve(operations)),
+            Self::AtMostOnce(proposition) => Self::AtMostOnce(proposition.resolve(operations)),
+            Self::SometimeBefore(first, second) => {
+                Self::SometimeBefore(first.resolve(operations), second.resolve(operations))
+            }
+        }
+    }
+}
+
+impl Monitor {
+    /// Start following a trajectory at the initial states.
+    pub fn new(constraints: Vec<Constraint>, states: &StateSet) -> Result<Self, Violation> {
+        let mut monitor = Self {
+            progress: vec![Progress::default(); constraints.len()],
+            constraints,
+            step: Step::Initial,
+        };
+        monitor.observe(None, states)?;
+        Ok(monitor)
+    }
+
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    /// Follow the trajectory to the states reached by applying the
+    /// operation.
+    pub fn progress(&mut self, operation: &Operation, states: &StateSet) -> Result<(), Violation> {
+        self.step = match self.step {
+            Step::Operation(position) => Step::Operation(position + 1),
+            _ => Step::Operation(0),
+        };
+        self.observe(Some(operation.symbol()), states)
+    }
+
+    fn observe(&mut self, operation: Option<Symbol>, states: &StateSet) -> Result<(), Violation> {
+        for (constraint, progress) in self.constraints.iter().zip(&mut self.progress) {
+            let holds = |proposition: &Proposition| proposition.holds(operation, states);
+            let broken = match constraint {
+                Constraint::Always(proposition) => !holds(proposition),
+                Constraint::Sometime(proposition) => {
+                    progress.seen |= holds(proposition);
+                    false
+                }
+                Constraint::AtMostOnce(proposition) => {
+                    let holding = holds(proposition);
+                    let separate =
+                        !progress.holding || matches!(proposition, Proposition::Operation(_));
+                    let again = holding && separate && progress.seen;
+                    progress.seen |= holding;
+                    progress.holding = holding;
+                    again
+                }
+                Constraint::SometimeBefore(first, second) => {
+                    let early = holds(first) && !progress.seen;
+                    progress.seen |= holds(second);
+                    early
+                }
+            };
+            if broken {
+                return Err(Violation {
+                    constraint: constraint.clone(),
+                    step: self.step,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the constraints which can only be met by the end.
+    pub fn finish(&self) -> Result<(), Violation> {
+        let unmet = self
+            .constraints
+            .iter()
+            .zip(&self.progress)
+            .find(|(constraint, progress)| {
+                matches!(constraint, Constraint::Sometime(_)) && !progress.seen
+            });
+        match unmet {
+            Some((constraint, _)) => Err(Violation {
+                constraint: constraint.clone(),
+                step: Step::Goal,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// The conditions of `Sometime` constraints which have not held yet.
+    pub fn pending_conditions(&self) -> Vec<ConditionImpl> {
+        self.constraints
+            .iter()
+            .zip(&self.progress)
+            .filter_map(|(constraint, progress)| match constraint {
+                Constraint::Sometime(Proposition::Condition(condition)) if !progress.seen => {
+                    Some(condition.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Follow the trajectory of a plan and tell the first constraint it breaks.
+/// The steps are applied whether or not their prerequisites hold, and
+/// derived states are recomputed from the axioms initially and after every
+/// step.
+pub fn check(
+    constraints: &[Constraint],
+    states: &StateSet,
+    plan: &[Operation],
+    axioms: &Axioms,
+) -> Result<(), Violation> {
+    let mut states = states.clone();
+    axioms.derive(&mut states);
+    let mut monitor = Monitor::new(constraints.to_vec(), &states)?;
+    for operation in plan {
+        operation.apply(&mut states);
+        axioms.derive(&mut states);
+        monitor.progress(operation, &states)?;
+    }
+    monitor.finish()
+}
+
+impl Violation {
+    pub fn constraint(&self) -> &Constraint {
+        &self.constraint
+    }
+
+    /// Where the constraint was broken.
+    pub fn step(&self) -> Step {
+        self.step
+    }
+}
+
+/// Written like `sometime-before shop-installs-battery give-shop-money`.
+impl Display for Proposition {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Condition(condition) => f.write_str(&write_condition(condition)),
+            Self::Operation(name) => f.write_str(&write_name(name.as_str())),
+        }
+    }
+}
+
+impl Display for Constraint {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Always(proposition) => write!(f, "always {proposition}"),
+            Self::Sometime(proposition) => write!(f, "sometime {proposition}"),
+            Self::AtMostOnce(proposition) => write!(f, "at-most-once {proposition}"),
+            Self::SometimeBefore(first, second) => {
+                write!(f, "sometime-before {first} {second}")
+            }
+        }
+    }
+}
+
+/// Steps are counted from 1.
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self.step {
+            Step::Initial => write!(f, "`{}` is broken initially", self.constraint),
+            Step::Operation(position) => {
+                write!(
+                    f,
+                    "`{}` is broken by step {}",
+                    self.constraint,
+                    position + 1
+                )
+            }
+            Step::Goal => write!(f, "`{}` is not met by the end", self.constraint),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps::domains::school;
+    use crate::gps::format::text;
+    use crate::gps::plan::repair::repair;
+    use crate::gps::{names, GeneralProblemSolver};
+
+    fn operation(name: &str) -> Proposition {
+        Proposition::Operation(name.into())
+    }
+
+    #[test]
+    fn it_should_report_the_step_breaking_a_constraint() {
+        let solver: GeneralProblemSolver = school::problem_with_battery().into();
+        let plan = solver.solve().unwrap();
+        assert_eq!(
+            names(&plan)[3..5],
+            ["give-shop-money", "shop-installs-battery"]
+        );
+
+        let paid_first = Constraint::SometimeBefore(
+            operation("shop-installs-battery"),
+            operation("give-shop-money"),
+        );
+        let once = Constraint::AtMostOnce(operation("telephone-shop"));
+        assert!(check(
+            &[paid_first, once.clone()],
+            solver.states(),
+            &plan,
+            solver.axioms()
+        )
+        .is_ok());
+
+        let paid_last = Constraint::SometimeBefore(
+            operation("give-shop-money"),
+            operation("shop-installs-battery"),
+        );
+        let violation = check(&[paid_last], solver.states(), &plan, solver.axioms()).unwrap_err();
+        assert_eq!(violation.step(), Step::Operation(3));
+        assert_eq!(
+            violation.to_string(),
+            "`sometime-before give-shop-money shop-installs-battery` is broken by step 4"
+        );
+
+        let mut twice = plan.clone();
+        twice.insert(2, plan[1].clone());
+        let violation = check(&[once], solver.states(), &twice, solver.axioms()).unwrap_err();
+        assert_eq!(
+            violation.to_string(),
+            "`at-most-once telephone-shop` is broken by step 3"
+        );
+
+        let visit = Constraint::Sometime(operation("ask-neighbour"));
+        let violation = check(&[visit], solver.states(), &plan, solver.axioms()).unwrap_err();
+        assert_eq!(violation.step(), Step::Goal);
+    }
+
+    #[test]
+    fn it_should_derive_states_along_the_trajectory() {
+        let source = "state battery-ok
+                      operation fix-tires { add tires-ok }
+                      operation drive { require car-works add at-school }
+                      rule car-works { require battery-ok require tires-ok }
+                      goal at-school
+                      sometime car-works";
+        let solver: GeneralProblemSolver = text::parse(source).unwrap().into();
+        let plan = solver.solve().unwrap();
+        assert_eq!(names(&plan), vec!["fix-tires", "drive"]);
+
+        let (constraints, states) = (solver.constraints(), solver.states());
+        assert!(check(constraints, states, &plan, solver.axioms()).is_ok());
+        assert!(check(constraints, states, &plan, &Axioms::default()).is_err());
+        assert_eq!(repair(&solver, &plan).unwrap().edit_count(), 0);
+    }
+}
@@ -0,0 +1,303 @@
+//! The AND/OR tree explored by one search.
+//!
+//! Achieving a list of goals needs every goal ([`NodeKind::Goals`], an AND
+//! node), achieving one goal needs any of the operations able to
+//! ([`NodeKind::Goal`], an OR node), and applying an operation needs its
+//! prerequisites ([`NodeKind::Operation`], whose only child is the list of
+//! prerequisites unless they already held).
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use super::condition::{Condition, ConditionImpl};
+use super::numeric::ResourceBound;
+use super::operation::Operation;
+use super::trajectory::Constraint;
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchTree {
+    /// Nodes in the order they were visited, so parents come before their
+    /// children and siblings in the order they were tried.
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    kind: NodeKind,
+    status: NodeStatus,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    depth: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum NodeKind {
+    Goals(Vec<ConditionImpl>),
+    Goal(ConditionImpl),
+    Operation(Operation),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeStatus {
+    /// Nothing had to be done.
+    Holds,
+    Achieved,
+    Failed(Failure),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Failure {
+    /// The goal is already being pursued further up the tree.
+    Recursive,
+    /// Every operation able to achieve the goal would undo a protected goal,
+    /// or there is no such operation at all.
+    NoOperations,
+    /// The state of the goal is unknown and no usable operation achieves it,
+    /// so it cannot be relied on.
+    UnknownState,
+    AllOperationsFailed,
+    /// The goal is a derived state and the body of none of its rules could be
+    /// achieved.
+    AllRulesFailed,
+    /// Applying the operation would undo a goal achieved earlier.
+    UndoesProtectedGoal,
+    PrerequisitesFailed,
+    SubgoalFailed(ConditionImpl),
+    /// The goal was achieved but undone while achieving the next ones.
+    Undone(ConditionImpl),
+    /// Applying the operation would leave a resource out of its bounds.
+    OutOfBounds(ResourceBound),
+    /// Applying the operation would make the invariant false.
+    BreaksInvariant(ConditionImpl),
+    /// Applying the operation would break the trajectory constraint.
+    BreaksConstraint(Constraint),
+    LimitExceeded,
+}
+
+/// Builds the tree while the search descends and returns.
+#[derive(Debug, Default)]
+pub(super) struct TreeBuilder {
+    tree: SearchTree,
+    open: Vec<NodeId>,
+}
+
+impl SearchTree {
+    pub fn root(&self) -> Option<NodeId> {
+        (!self.nodes.is_empty()).then_some(NodeId(0))
+    }
+
+    pub fn get(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    /// All nodes, parents first.
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (NodeId(i), node))
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl NodeId {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+impl Node {
+    pub fn kind(&self) -> &NodeKind {
+        &self.kind
+    }
+
+    pub fn status(&self) -> &NodeStatus {
+        &self.status
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    /// The number of ancestors of the node.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn is_success(&self) -> bool {
+        !matches!(self.status, NodeStatus::Failed(_))
+    }
+}
+
+impl Display for NodeKind {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            NodeKind::Goals(goals) => {
+                let names: Vec<_> = goals.iter().map(|goal| goal.name()).collect();
+                write!(f, "achieve all of {}", names.join(", "))
+            }
+            NodeKind::Goal(goal) => write!(f, "achieve {}", describe(goal)),
+            NodeKind::Operation(operation) => write!(f, "apply {}", operation.name()),
+        }
+    }
+}
+
+impl Display for NodeStatus {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            NodeStatus::Holds => f.write_str("already holds"),
+            NodeStatus::Achieved => f.write_str("achieved"),
+            NodeStatus::Failed(failure) => write!(f, "failed: {failure}"),
+        }
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Failure::Recursive => f.write_str("the goal is already being pursued"),
+            Failure::NoOperations => f.write_str("no usable operation achieves the goal"),
+            Failure::UnknownState => {
+                f.write_str("the goal is unknown and no usable operation achieves it")
+            }
+            Failure::AllOperationsFailed => f.write_str("every operation failed"),
+            Failure::AllRulesFailed => f.write_str("no rule deriving the goal could be used"),
+            Failure::UndoesProtectedGoal => f.write_str("it would undo a protected goal"),
+            Failure::PrerequisitesFailed => f.write_str("its prerequisites cannot be achieved"),
+            Failure::SubgoalFailed(goal) => write!(f, "{} cannot be achieved", describe(goal)),
+            Failure::Undone(goal) => write!(f, "{} was undone later", describe(goal)),
+            Failure::OutOfBounds(bound) => write!(f, "it would break the bound {bound}"),
+            Failure::BreaksInvariant(invariant) => {
+                write!(f, "it would break the invariant {}", describe(invariant))
+            }
+            Failure::BreaksConstraint(constraint) => {
+                write!(f, "it would break the constraint `{constraint}`")
+            }
+            Failure::LimitExceeded => f.write_str("the search was cut short"),
+        }
+    }
+}
+
+fn describe(condition: &ConditionImpl) -> String {
+    match condition {
+        ConditionImpl::NotContain(_) => format!("not {}", condition.name()),
+        _ => condition.name().to_owned(),
+    }
+}
+
+impl TreeBuilder {
+    /// Add a node below the innermost open one and open it.
+    pub(super) fn enter(&mut self, kind: NodeKind) {
+        let id = self.push(kind, NodeStatus::Achieved);
+        self.open.push(id);
+    }
+
+    /// Close the innermost open node.
+    pub(super) fn leave(&mut self, status: NodeStatus) {
+        let id = self.open.pop().expect("no node is open");
+        self.tree.nodes[id.0].status = status;
+    }
+
+    /// Add a node which needs no further search.
+    pub(super) fn record(&mut self, kind: NodeKind, status: NodeStatus) {
+        self.push(kind, status);
+    }
+
+    pub(super) fn finish(self) -> SearchTree {
+        self.tree
+    }
+
+    fn push(&mut self, kind: NodeKind, status: NodeStatus) -> NodeId {
+        let id = NodeId(self.tree.nodes.len());
+        let parent = self.open.last().copied();
+        if let Some(parent) = parent {
+            self.tree.nodes[parent.0].children.push(id);
+        }
+        self.tree.nodes.push(Node {
+            kind,
+            status,
+            parent,
+            children: Vec::new(),
+            depth: self.open.len(),
+        });
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gps::format::text;
+    use crate::gps::portfolio::CancellationToken;
+    use crate::gps::GeneralProblemSolver;
+
+    #[test]
+    fn it_should_record_goals_operations_and_reasons() {
+        let problem = text::parse(
+            "state son-at-home
+             state have-money
+             operation taxi-son-to-school {
+                 require son-at-home
+                 require have-money
+                 add son-at-school
+                 remove son-at-home
+                 remove have-money
+             }
+             operation drive-son-to-school {
+                 require son-at-home
+                 require car-works
+                 add son-at-school
+                 remove son-at-home
+             }
+             goal son-at-school
+             goal have-money",
+        )
+        .unwrap();
+        let gps: GeneralProblemSolver = problem.into();
+        let (outcome, _, tree) = gps.search_tree(&CancellationToken::new());
+        assert!(!outcome.is_solved());
+
+        let lines: Vec<_> = tree
+            .nodes()
+            .map(|(_, node)| {
+                format!(
+                    "{}{} ({})",
+                    "  ".repeat(node.depth()),
+                    node.kind(),
+                    node.status()
+                )
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "achieve all of son-at-school, have-money (failed: son-at-school cannot be \
+                 achieved)",
+                "  achieve have-money (already holds)",
+                "  achieve son-at-school (failed: every operation failed)",
+                "    apply taxi-son-to-school (failed: it would undo a protected goal)",
+                "    apply drive-son-to-school (failed: its prerequisites cannot be achieved)",
+                "      achieve all of son-at-home, car-works (failed: car-works cannot be \
+                 achieved)",
+                "        achieve son-at-home (already holds)",
+                "        achieve car-works (failed: no usable operation achieves the goal)",
+            ]
+        );
+
+        let root = tree.get(tree.root().unwrap());
+        assert_eq!(root.children().len(), 2);
+        assert_eq!(tree.get(root.children()[1]).parent(), tree.root());
+    }
+}
@@ -0,0 +1,331 @@
+//! Planning with more goals than can be achieved together.
+//!
+//! Besides the goals of a solver, which every plan must achieve, soft goals
+//! carry utilities. [`solve`] looks for the plan whose achieved utility minus
+//! its cost is highest, and tells why each soft goal it leaves out was
+//! abandoned. [`search`] takes the same searches, giving up as soon as it is
+//! cancelled.
+
+use std::cmp::Reverse;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use super::condition::{Condition, ConditionImpl};
+use super::operation::Operation;
+use super::plan::{cost, validate};
+use super::portfolio::CancellationToken;
+use super::search::{Outcome, Statistics};
+use super::state::StateSet;
+use super::GeneralProblemSolver;
+
+/// Up to this many soft goals, every combination of them is tried. With more,
+/// they are added one at a time as long as that pays off.
+const MAX_COMBINED: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SoftGoal {
+    goal: ConditionImpl,
+    utility: u32,
+}
+
+/// The best plan found and what became of each soft goal.
+#[derive(Debug, Clone)]
+pub struct SoftSolution {
+    plan: Vec<Operation>,
+    achieved: Vec<SoftGoal>,
+    abandoned: Vec<(SoftGoal, Abandonment)>,
+}
+
+/// The searches for combinations of soft goals, and what they add up to.
+struct Searches<'a> {
+    solver: &'a GeneralProblemSolver,
+    cancellation: &'a CancellationToken,
+    statistics: Statistics,
+}
+
+/// Why a soft goal is not achieved by the plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Abandonment {
+    /// No plan achieves it together with the hard goals.
+    Unreachable,
+    /// No plan achieves it together with the goals which are achieved.
+    Conflicts,
+    /// Achieving it as well costs more than it is worth in the plans found.
+    TooCostly { cost: u64, utility: u32 },
+}
+
+impl SoftGoal {
+    pub fn new(goal: ConditionImpl, utility: u32) -> Self {
+        Self { goal, utility }
+    }
+
+    pub fn goal(&self) -> &ConditionImpl {
+        &self.goal
+    }
+
+    pub fn utility(&self) -> u32 {
+        self.utility
+    }
+}
+
+/// Find the plan achieving the goals of the solver and the soft goals with
+/// the highest utility minus cost, or `None` if the goals of the solver
+/// cannot be achieved. Soft goals the plan happens to achieve count as well.
+///
+/// The plans come from the solver, which does not look for the cheapest
+/// plan, so the result is the best among the plans it finds.
+pub fn solve(solver: &GeneralProblemSolver, soft_goals: &[SoftGoal]) -> Option<SoftSolution> {
+    let cancellation = CancellationToken::new();
+    let mut searches = Searches::new(solver, &cancellation);
+    let plan = searches.solve_for(&[])?;
+    Some(find(&mut searches, plan, soft_goals))
+}
+
+/// Find the best plan like [`solve`], giving up as soon as the token is
+/// cancelled, and add up the statistics of every search it takes. The plan
+/// is only reported if the searches were not cut short.
+pub fn search(
+    solver: &GeneralProblemSolver,
+    soft_goals: &[SoftGoal],
+    cancellation: &CancellationToken,
+) -> (Outcome, Statistics) {
+    let mut searches = Searches::new(solver, cancellation);
+    // Searching for the goals of the solver alone first tells an unsolvable
+    // problem from an exceeded limit.
+    let (outcome, statistics) = solver.search(cancellation);
+    searches.statistics.add(&statistics);
+    let Outcome::Solved(plan) = outcome else {
+        return (outcome, searches.statistics);
+    };
+    let solution = find(&mut searches, plan, soft_goals);
+    let outcome = if cancellation.is_cancelled() {
+        Outcome::LimitExceeded
+    } else {
+        Outcome::Solved(solution.plan)
+    };
+    (outcome, searches.statistics)
+}
+
+/// The best plan starting from one for the goals of the solver alone.
+fn find(searches: &mut Searches, plan: Vec<Operation>, soft_goals: &[SoftGoal]) -> SoftSolution {
+    let solver = searches.solver;
+    let mut best = plan;
+    let mut best_value = value(solver, &best, soft_goals);
+
+    if soft_goals.len() <= MAX_COMBINED {
+        let mut combinations: Vec<Vec<&SoftGoal>> = (1..1usize << soft_goals.len())
+            .map(|mask| {
+                (0..soft_goals.len())
+                    .filter(|i| mask & (1 << i) != 0)
+                    .map(|i| &soft_goals[i])
+                    .collect()
+            })
+            .collect();
+        combinations.sort_by_key(|combination| Reverse(utility(combination)));
+        for combination in combinations {
+            // Plans cost nothing at best, so a combination worth no more than
+            // the best value cannot beat it, leaving aside goals achieved by
+            // chance.
+            if i128::from(utility(&combination)) <= best_value {
+                break;
+            }
+            if let Some(plan) = searches.solve_for(&combination) {
+                let plan_value = value(solver, &plan, soft_goals);
+                if plan_value > best_value {
+                    (best, best_value) = (plan, plan_value);
+                }
+            }
+        }
+    } else {
+        let mut by_utility: Vec<_> = soft_goals.iter().collect();
+        by_utility.sort_by_key(|soft_goal| Reverse(soft_goal.utility));
+        let mut chosen = Vec::new();
+        for soft_goal in by_utility {
+            chosen.push(soft_goal);
+            let found = searches
+                .solve_for(&chosen)
+                .map(|plan| (value(solver, &plan, soft_goals), plan))
+                .filter(|(plan_value, _)| *plan_value > best_value);
+            match found {
+                Some((plan_value, plan)) => (best_value, best) = (plan_value, plan),
+                None => {
+                    chosen.pop();
+                }
+            }
+        }
+    }
+
+    let states = end_states(solver, &best);
+    let (achieved, missed): (Vec<_>, Vec<_>) = soft_goals
+        .iter()
+        .cloned()
+        .partition(|soft_goal| soft_goal.goal.check(&states));
+    let achieved_refs: Vec<_> = achieved.iter().collect();
+    let abandoned = missed
+        .into_iter()
+        .map(|soft_goal| {
+            let reason = if searches.solve_for(&[&soft_goal]).is_none() {
+                Abandonment::Unreachable
+            } else {
+                let mut together = achieved_refs.clone();
+                together.push(&soft_goal);
+                match searches.solve_for(&together) {
+                    None => Abandonment::Conflicts,
+                    Some(plan) => Abandonment::TooCostly {
+                        cost: cost(&plan).saturating_sub(cost(&best)),
+                        utility: soft_goal.utility,
+                    },
+                }
+            };
+            (soft_goal, reason)
+        })
+        .collect();
+
+    SoftSolution {
+        plan: best,
+        achieved,
+        abandoned,
+    }
+}
+
+impl<'a> Searches<'a> {
+    fn new(solver: &'a GeneralProblemSolver, cancellation: &'a CancellationToken) -> Self {
+        Self {
+            solver,
+            cancellation,
+            statistics: Statistics::default(),
+        }
+    }
+
+    /// A plan for the goals of the solver and the given soft goals.
+    fn solve_for(&mut self, soft_goals: &[&SoftGoal]) -> Option<Vec<Operation>> {
+        let mut goals = self.solver.goals().clone();
+        goals.extend(soft_goals.iter().map(|soft_goal| soft_goal.goal.clone()));
+        let mut solver = self.solver.clone();
+        solver.set_goals(goals);
+        let (outcome, statistics) = solver.search(self.cancellation);
+        self.statistics.add(&statistics);
+        outcome.into_operations()
+    }
+}
+
+fn utility(soft_goals: &[&SoftGoal]) -> u64 {
+    soft_goals
+        .iter()
+        .map(|soft_goal| u64::from(soft_goal.utility))
+        .sum()
+}
+
+/// The states at the end of the plan, derived states included.
+fn end_states(solver: &GeneralProblemSolver, plan: &[Operation]) -> StateSet {
+    validate(solver.states(), plan, &[], &[], &[], solver.axioms()).unwrap_or_default()
+}
+
+/// The utility of the soft goals holding at the end of the plan minus its
+/// cost.
+fn value(solver: &GeneralProblemSolver, plan: &[Operation], soft_goals: &[SoftGoal]) -> i128 {
+    let states = end_states(solver, plan);
+    let achieved: Vec<_> = soft_goals
+        .iter()
+        .filter(|soft_goal| soft_goal.goal.check(&states))
+        .collect();
+    i128::from(utility(&achieved)) - i128::from(cost(plan))
+}
+
+impl SoftSolution {
+    pub fn plan(&self) -> &[Operation] {
+        &self.plan
+    }
+
+    pub fn achieved(&self) -> &[SoftGoal] {
+        &self.achieved
+    }
+
+    pub fn abandoned(&self) -> &[(SoftGoal, Abandonment)] {
+        &self.abandoned
+    }
+
+    /// The total utility of the achieved soft goals.
+    pub fn utility(&self) -> u64 {
+        self.achieved
+            .iter()
+            .map(|soft_goal| u64::from(soft_goal.utility))
+            .sum()
+    }
+
+    pub fn cost(&self) -> u64 {
+        cost(&self.plan)
+    }
+
+    /// The utility minus the cost.
+    pub fn net_benefit(&self) -> i128 {
+        i128::from(self.utility()) - i128::from(self.cost())
+    }
+}
+
+impl Display for Abandonment {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Abandonment::Unreachable => f.write_str("no plan achieves it"),
+            Abandonment::Conflicts => f.write_str("it conflicts with the achieved goals"),
+            Abandonment::TooCostly { cost, utility } => {
+                write!(f, "it costs {cost} more for a utility of {utility}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps::format::text;
+    use crate::gps::names;
+
+    #[test]
+    fn it_should_trade_utility_against_cost() {
+        let problem = text::parse(
+            "state at-home
+             operation go-to-beach { require at-home add at-beach remove at-home cost 3 }
+             operation go-to-museum { require at-home add at-museum remove at-home cost 2 }
+             operation buy-ticket { add have-ticket cost 8 }
+             prefer at-beach 6
+             prefer at-museum 4
+             prefer have-ticket 1
+             prefer at-moon 100",
+        )
+        .unwrap();
+        let soft_goals = problem.soft_goals().clone();
+        let solver: GeneralProblemSolver = problem.into();
+
+        let solution = solve(&solver, &soft_goals).unwrap();
+        assert_eq!(names(solution.plan()), vec!["go-to-beach"]);
+        assert_eq!(solution.achieved(), &soft_goals[..1]);
+        assert_eq!(solution.net_benefit(), 3);
+
+        let reasons: Vec<_> = solution
+            .abandoned()
+            .iter()
+            .map(|(soft_goal, reason)| format!("{}: {reason}", soft_goal.goal().name()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                "at-museum: it conflicts with the achieved goals",
+                "have-ticket: it costs 8 more for a utility of 1",
+                "at-moon: no plan achieves it",
+            ]
+        );
+
+        let (outcome, statistics) = search(&solver, &soft_goals, &CancellationToken::new());
+        assert_eq!(
+            names(&outcome.into_operations().unwrap()),
+            vec!["go-to-beach"]
+        );
+        let (_, alone) = solver.search(&CancellationToken::new());
+        assert!(statistics.expansions() > alone.expansions());
+
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        let (outcome, _) = search(&solver, &soft_goals, &cancellation);
+        assert!(matches!(outcome, Outcome::LimitExceeded));
+    }
+}